@@ -0,0 +1,38 @@
+use glam::Vec3;
+
+const N: u32 = 6 * 6;
+
+/// Horizontal bar showing the colors of `evalue` over [0, 1], the colors being linear
+pub fn gradient_preview(ui: &mut egui::Ui, evalue: impl Fn(f32) -> Vec3) -> egui::Response {
+    let desired_size = egui::vec2(ui.spacing().slider_width * 2.0, ui.spacing().interact_size.y * 2.0);
+    let (rect, response) = ui.allocate_at_least(desired_size, egui::Sense::click());
+
+    if ui.is_rect_visible(rect) {
+        let visuals = ui.style().interact(&response);
+
+        {
+            let mut mesh = egui::Mesh::default();
+            for i in 0..=N {
+                let t = i as f32 / (N as f32);
+                let color: Vec3 = evalue(t);
+                let color32 = egui::Color32::from_rgb(
+                    ecolor::gamma_u8_from_linear_f32(color[0]),
+                    ecolor::gamma_u8_from_linear_f32(color[1]),
+                    ecolor::gamma_u8_from_linear_f32(color[2]),
+                );
+                let x = egui::lerp(rect.left()..=rect.right(), t);
+                mesh.colored_vertex(egui::pos2(x, rect.top()), color32);
+                mesh.colored_vertex(egui::pos2(x, rect.bottom()), color32);
+                if i < N {
+                    mesh.add_triangle(2 * i + 0, 2 * i + 1, 2 * i + 2);
+                    mesh.add_triangle(2 * i + 1, 2 * i + 2, 2 * i + 3);
+                }
+            }
+            ui.painter().add(egui::Shape::mesh(mesh));
+        }
+
+        ui.painter().rect_stroke(rect, 0.0, visuals.bg_stroke, egui::StrokeKind::Outside); // outline
+    }
+
+    response
+}
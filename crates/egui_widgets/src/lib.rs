@@ -2,7 +2,9 @@
 extern crate lazy_static;
 
 mod code_editor;
+mod gradient_preview;
 mod iq_gradiant;
+mod linear_gradient;
 pub mod glam_helpers;
 mod syntax_highlighting;
 
@@ -12,4 +14,6 @@ pub trait UiWidget {
 }
 
 pub use code_editor::CodeEditor;
+pub use gradient_preview::gradient_preview;
 pub use iq_gradiant::{IqGradient, IQ_GRADIENT_PRESETS};
+pub use linear_gradient::{ColorStop, LinearGradient};
@@ -0,0 +1,105 @@
+use glam::Vec3;
+
+// Vec3 to mut slice
+fn vec3_as_mut_slice(v: &mut Vec3) -> &mut [f32; 3] {
+    unsafe { &mut *(v as *mut Vec3 as *mut [f32; 3]) }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColorStop {
+    /// In [0, 1]
+    pub position: f32,
+    pub color: Vec3,
+}
+
+/// Piecewise linear gradient between color stops, constant before the first stop and after the last one
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinearGradient {
+    /// Sorted by position
+    pub stops: Vec<ColorStop>,
+}
+
+impl Default for LinearGradient {
+    fn default() -> Self {
+        Self {
+            stops: vec![
+                ColorStop { position: 0.0, color: Vec3::ZERO },
+                ColorStop { position: 1.0, color: Vec3::ONE },
+            ],
+        }
+    }
+}
+
+impl LinearGradient {
+    pub fn evalue(&self, t: f32) -> Vec3 {
+        let (Some(first), Some(last)) = (self.stops.first(), self.stops.last()) else {
+            return Vec3::ZERO;
+        };
+        if t <= first.position {
+            return first.color;
+        }
+
+        for pair in self.stops.windows(2) {
+            if t <= pair[1].position {
+                let width = pair[1].position - pair[0].position;
+                let blend = if width > 0.0 { (t - pair[0].position) / width } else { 1.0 };
+                return pair[0].color.lerp(pair[1].color, blend);
+            }
+        }
+        last.color
+    }
+
+    fn sort(&mut self) { self.stops.sort_by(|a, b| a.position.total_cmp(&b.position)); }
+
+    /// Stops editor, at most `max_stops` of them
+    pub fn ui_control(&mut self, ui: &mut egui::Ui, max_stops: usize) -> bool {
+        let mut changed: bool = false;
+        ui.collapsing("gradient stops", |ui| {
+            let mut removed: Option<usize> = None;
+            let stop_count = self.stops.len();
+            egui::Grid::new("gradient stops").show(ui, |ui| {
+                for (index, stop) in self.stops.iter_mut().enumerate() {
+                    changed |= ui.add(egui::DragValue::new(&mut stop.position).speed(0.01).range(0.0..=1.0)).changed();
+                    changed |= egui::color_picker::color_edit_button_rgb(ui, vec3_as_mut_slice(&mut stop.color)).changed();
+                    if ui.add_enabled(stop_count > 1, egui::Button::new("🗑")).on_hover_text("Remove the stop").clicked() {
+                        removed = Some(index);
+                    }
+                    ui.end_row();
+                }
+            });
+
+            if let Some(index) = removed {
+                self.stops.remove(index);
+                changed = true;
+            }
+
+            if ui.add_enabled(self.stops.len() < max_stops, egui::Button::new("Add stop")).clicked() {
+                // In the middle of the widest interval
+                let stop = self
+                    .stops
+                    .windows(2)
+                    .max_by(|a, b| (a[1].position - a[0].position).total_cmp(&(b[1].position - b[0].position)))
+                    .map(|pair| {
+                        let position = 0.5 * (pair[0].position + pair[1].position);
+                        ColorStop { position, color: self.evalue(position) }
+                    })
+                    .unwrap_or(ColorStop { position: 1.0, color: Vec3::ONE });
+                self.stops.push(stop);
+                changed = true;
+            }
+        });
+
+        if changed {
+            self.sort();
+        }
+        changed
+    }
+}
+
+impl crate::UiWidget for LinearGradient {
+    fn show(&mut self, ui: &mut egui::Ui) -> egui::Response {
+        crate::gradient_preview(ui, |t| self.evalue(t)).on_hover_text("linear interpolation between the color stops")
+    }
+}
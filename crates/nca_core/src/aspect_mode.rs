@@ -0,0 +1,42 @@
+/// How the grid is scaled in a viewport of another aspect ratio
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum AspectMode {
+    /// The whole grid is visible, with bars along the longer axis of the viewport (letterbox)
+    #[default]
+    Fit,
+    /// The grid covers the viewport and is cropped along its longer axis
+    Fill,
+    /// The grid is stretched to the viewport, the cells are not square anymore
+    Stretch,
+}
+
+impl std::fmt::Display for AspectMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            AspectMode::Fit => write!(f, "Fit"),
+            AspectMode::Fill => write!(f, "Fill"),
+            AspectMode::Stretch => write!(f, "Stretch"),
+        }
+    }
+}
+
+impl AspectMode {
+    pub const ALL: [AspectMode; 3] = [AspectMode::Fit, AspectMode::Fill, AspectMode::Stretch];
+
+    // Must match the ASPECT_* constants of Screen.wgsl
+    pub fn shader_mode(&self) -> u32 {
+        match self {
+            AspectMode::Fit => 0,
+            AspectMode::Fill => 1,
+            AspectMode::Stretch => 2,
+        }
+    }
+
+    pub fn from_shader_mode(mode: u32) -> Self {
+        match mode {
+            1 => AspectMode::Fill,
+            2 => AspectMode::Stretch,
+            _ => AspectMode::Fit,
+        }
+    }
+}
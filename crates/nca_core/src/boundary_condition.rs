@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+
+use oxyde::wgpu as wgpu;
+
+/// What the neighbourhood of the border cells sees outside of the grid.
+/// Applied both by the simulation shader and by the address mode of the simulation sampler.
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq, Default)]
+pub enum BoundaryCondition {
+    /// Opposite borders are connected (torus)
+    #[default]
+    Wrap,
+    /// The border cells are repeated outside
+    Clamp,
+    /// The grid is reflected at its borders
+    Mirror,
+    /// Every cell outside has this value
+    Constant(f32),
+}
+
+impl std::fmt::Display for BoundaryCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BoundaryCondition::Wrap => write!(f, "Wrap"),
+            BoundaryCondition::Clamp => write!(f, "Clamp"),
+            BoundaryCondition::Mirror => write!(f, "Mirror"),
+            BoundaryCondition::Constant(_) => write!(f, "Constant"),
+        }
+    }
+}
+
+impl BoundaryCondition {
+    pub const ALL: [BoundaryCondition; 4] = [BoundaryCondition::Wrap, BoundaryCondition::Clamp, BoundaryCondition::Mirror, BoundaryCondition::Constant(0.0)];
+
+    // Must match the BOUNDARY_* constants of simulationBase.wgsl
+    pub fn shader_mode(&self) -> u32 {
+        match self {
+            BoundaryCondition::Wrap => 0,
+            BoundaryCondition::Clamp => 1,
+            BoundaryCondition::Mirror => 2,
+            BoundaryCondition::Constant(_) => 3,
+        }
+    }
+
+    pub fn from_shader_mode(mode: u32, constant_value: f32) -> Self {
+        match mode {
+            1 => BoundaryCondition::Clamp,
+            2 => BoundaryCondition::Mirror,
+            3 => BoundaryCondition::Constant(constant_value),
+            _ => BoundaryCondition::Wrap,
+        }
+    }
+
+    pub fn constant_value(&self) -> f32 {
+        match self {
+            BoundaryCondition::Constant(value) => *value,
+            _ => 0.0,
+        }
+    }
+
+    /// Texel read for the coordinates `coords` of a grid of `size` texels, None when the constant value is used instead.
+    /// Mirrors the getCoords function of simulationBase.wgsl
+    pub fn resolve(&self, coords: [i32; 2], size: [u32; 2]) -> Option<[i32; 2]> {
+        let resolve_axis = |coord: i32, size: u32| -> Option<i32> {
+            let size = size as i32;
+            match self {
+                BoundaryCondition::Wrap => Some(coord.rem_euclid(size)),
+                BoundaryCondition::Clamp => Some(coord.clamp(0, size - 1)),
+                BoundaryCondition::Mirror => {
+                    let reflected = if coord < 0 { -coord - 1 } else { coord };
+                    Some(if reflected >= size { 2 * size - reflected - 1 } else { reflected })
+                },
+                BoundaryCondition::Constant(_) => (0..size).contains(&coord).then_some(coord),
+            }
+        };
+        Some([resolve_axis(coords[0], size[0])?, resolve_axis(coords[1], size[1])?])
+    }
+
+    // The constant value is substituted by the shader, the sampler only has to stay inside the grid
+    pub fn address_mode(&self) -> wgpu::AddressMode {
+        match self {
+            BoundaryCondition::Wrap => wgpu::AddressMode::Repeat,
+            BoundaryCondition::Clamp | BoundaryCondition::Constant(_) => wgpu::AddressMode::ClampToEdge,
+            BoundaryCondition::Mirror => wgpu::AddressMode::MirrorRepeat,
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use oxyde::wgpu as wgpu;
+
+/// Maximum number of cells read at once
+pub const MAX_PROBED_CELLS: u32 = 64 * 64;
+const WORKGROUP_SIZE: u32 = 8;
+const CELLS_BUFFER_SIZE: wgpu::BufferAddress = (MAX_PROBED_CELLS as usize * std::mem::size_of::<[f32; 4]>()) as wgpu::BufferAddress;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct ProbeRegion {
+    origin: [i32; 2],
+    size: [u32; 2],
+}
+
+/// Values of a rectangular region of a simulation texture, the region wraps around the borders of the texture
+#[derive(Debug, Clone)]
+pub struct CellRegion {
+    pub origin: [i32; 2],
+    pub size: [u32; 2],
+    /// RGBA values, row by row
+    pub cells: Vec<[f32; 4]>,
+}
+
+impl CellRegion {
+    /// Value of the cell at (x, y), in the same unwrapped coordinates as the origin, if the region contains it
+    pub fn cell(&self, x: i32, y: i32) -> Option<[f32; 4]> {
+        let (column, row) = (x - self.origin[0], y - self.origin[1]);
+        if column < 0 || row < 0 || column >= self.size[0] as i32 || row >= self.size[1] as i32 {
+            return None;
+        }
+        Some(self.cells[(row as u32 * self.size[0] + column as u32) as usize])
+    }
+
+    /// Value of the texel (x, y) of a texture of `texture_size` texels, found in the region after wrapping around the borders
+    pub fn cell_wrapped(&self, x: i32, y: i32, texture_size: [u32; 2]) -> Option<[f32; 4]> {
+        let column = (x - self.origin[0]).rem_euclid(texture_size[0] as i32);
+        let row = (y - self.origin[1]).rem_euclid(texture_size[1] as i32);
+        self.cell(self.origin[0] + column, self.origin[1] + row)
+    }
+}
+
+/// Compute pass copying a few cells of a simulation texture to the CPU, with the exact values seen by the shaders
+pub struct CellProbe {
+    pipeline: wgpu::ComputePipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    region_buffer: wgpu::Buffer,
+    cells_buffer: wgpu::Buffer,
+    read_back_buffer: wgpu::Buffer,
+}
+
+impl CellProbe {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("Cell probe bind group layout"),
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Cell probe Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/cellProbe.wgsl").into()),
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Cell probe Pipeline"),
+            layout: Some(&device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Cell probe Pipeline Layout"),
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[],
+            })),
+            module: &shader,
+            entry_point: "main",
+        });
+
+        let region_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Cell probe region Buffer"),
+            size: std::mem::size_of::<ProbeRegion>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let cells_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Cell probe Buffer"),
+            size: CELLS_BUFFER_SIZE,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let read_back_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Cell probe read back Buffer"),
+            size: CELLS_BUFFER_SIZE,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            pipeline,
+            bind_group_layout,
+            region_buffer,
+            cells_buffer,
+            read_back_buffer,
+        }
+    }
+
+    /// Blocking read of the cells of `texture` in the region starting at `origin`, its height is reduced to stay within MAX_PROBED_CELLS
+    pub fn read(&self, device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::TextureView, origin: [i32; 2], size: [u32; 2]) -> CellRegion {
+        let width = size[0].clamp(1, MAX_PROBED_CELLS);
+        let size = [width, size[1].clamp(1, MAX_PROBED_CELLS / width)];
+        let cells_size = (size[0] * size[1]) as usize * std::mem::size_of::<[f32; 4]>();
+
+        queue.write_buffer(&self.region_buffer, 0, bytemuck::bytes_of(&ProbeRegion { origin, size }));
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(texture),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: self.region_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.cells_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("Cell probe bind group"),
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Cell probe encoder") });
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Cell probe Compute Pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_pipeline(&self.pipeline);
+            compute_pass.set_bind_group(0, &bind_group, &[]);
+            compute_pass.dispatch_workgroups(size[0].div_ceil(WORKGROUP_SIZE), size[1].div_ceil(WORKGROUP_SIZE), 1);
+        }
+        encoder.copy_buffer_to_buffer(&self.cells_buffer, 0, &self.read_back_buffer, 0, cells_size as wgpu::BufferAddress);
+        queue.submit(Some(encoder.finish()));
+
+        let buffer_slice = self.read_back_buffer.slice(..cells_size as wgpu::BufferAddress);
+        buffer_slice.map_async(wgpu::MapMode::Read, |result| result.expect("Unable to map the cell probe buffer"));
+        device.poll(wgpu::PollType::wait_indefinitely()).expect("Unable to wait for the cell probe");
+
+        let cells: Vec<[f32; 4]> = bytemuck::cast_slice(&buffer_slice.get_mapped_range()).to_vec();
+        self.read_back_buffer.unmap();
+
+        CellRegion { origin, size, cells }
+    }
+}
@@ -0,0 +1,225 @@
+use crevice::std140::AsStd140;
+use glam::{Vec2, Vec3, Vec4};
+use serde::{Deserialize, Serialize};
+
+use egui_widgets::{IqGradient, LinearGradient};
+
+/// Maximum number of stops of a linear gradient sent to the screen shader
+pub const MAX_COLOR_STOPS: usize = 8;
+// Smallest value of the logarithmic scale, same as LOG_SCALE_MIN in Screen.wgsl
+const LOG_SCALE_MIN: f32 = 1e-6;
+
+/// How the displayed channel is turned into a color
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum ColorMapKind {
+    /// The IQ cosine `gradient` of the view
+    #[default]
+    Cosine,
+    /// Piecewise linear gradient between color stops
+    Stops,
+    Viridis,
+    Magma,
+    Turbo,
+}
+
+impl std::fmt::Display for ColorMapKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ColorMapKind::Cosine => write!(f, "Cosine"),
+            ColorMapKind::Stops => write!(f, "Stops"),
+            ColorMapKind::Viridis => write!(f, "Viridis"),
+            ColorMapKind::Magma => write!(f, "Magma"),
+            ColorMapKind::Turbo => write!(f, "Turbo"),
+        }
+    }
+}
+
+impl ColorMapKind {
+    pub const ALL: [ColorMapKind; 5] = [ColorMapKind::Cosine, ColorMapKind::Stops, ColorMapKind::Viridis, ColorMapKind::Magma, ColorMapKind::Turbo];
+
+    // Must match the COLOR_MAP_* constants of Screen.wgsl
+    pub fn shader_mode(&self) -> u32 {
+        match self {
+            ColorMapKind::Cosine => 0,
+            ColorMapKind::Stops => 1,
+            ColorMapKind::Viridis => 2,
+            ColorMapKind::Magma => 3,
+            ColorMapKind::Turbo => 4,
+        }
+    }
+
+    pub fn from_shader_mode(mode: u32) -> Self {
+        match mode {
+            1 => ColorMapKind::Stops,
+            2 => ColorMapKind::Viridis,
+            3 => ColorMapKind::Magma,
+            4 => ColorMapKind::Turbo,
+            _ => ColorMapKind::Cosine,
+        }
+    }
+}
+
+/// Color mapping of the display, saved in the presets next to the cosine gradient
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct ColorMap {
+    pub kind: ColorMapKind,
+    /// Used by ColorMapKind::Stops, only the first MAX_COLOR_STOPS stops are displayed
+    pub stops: LinearGradient,
+    /// Values mapped to the start and the end of the color map
+    pub range: [f32; 2],
+    /// Exponent applied to the normalized value
+    pub gamma: f32,
+    /// Normalize the logarithm of the values, the range must then be positive
+    pub log_scale: bool,
+}
+
+impl Default for ColorMap {
+    fn default() -> Self {
+        Self {
+            kind: ColorMapKind::Cosine,
+            stops: LinearGradient::default(),
+            range: [0.0, 1.0],
+            gamma: 1.0,
+            log_scale: false,
+        }
+    }
+}
+
+impl ColorMap {
+    pub fn parameters(&self) -> ColorMapParameters {
+        let mut stops = [Vec4::ZERO; MAX_COLOR_STOPS];
+        for (stop, color_stop) in stops.iter_mut().zip(self.stops.stops.iter()) {
+            *stop = color_stop.color.extend(color_stop.position);
+        }
+
+        ColorMapParameters {
+            kind: self.kind.shader_mode(),
+            log_scale: self.log_scale as u32,
+            range: Vec2::from_array(self.range),
+            gamma: self.gamma,
+            stop_count: self.stops.stops.len().min(MAX_COLOR_STOPS) as u32,
+            stops: ColorStops::from_array(stops),
+        }
+    }
+}
+
+// Color in rgb and position in w of each stop
+#[repr(C)]
+#[derive(Debug, Copy, Clone, AsStd140)]
+pub struct ColorStops {
+    stop_0: Vec4,
+    stop_1: Vec4,
+    stop_2: Vec4,
+    stop_3: Vec4,
+    stop_4: Vec4,
+    stop_5: Vec4,
+    stop_6: Vec4,
+    stop_7: Vec4,
+}
+
+impl ColorStops {
+    fn from_array(stops: [Vec4; MAX_COLOR_STOPS]) -> Self {
+        Self {
+            stop_0: stops[0],
+            stop_1: stops[1],
+            stop_2: stops[2],
+            stop_3: stops[3],
+            stop_4: stops[4],
+            stop_5: stops[5],
+            stop_6: stops[6],
+            stop_7: stops[7],
+        }
+    }
+
+    fn to_array(self) -> [Vec4; MAX_COLOR_STOPS] {
+        [self.stop_0, self.stop_1, self.stop_2, self.stop_3, self.stop_4, self.stop_5, self.stop_6, self.stop_7]
+    }
+}
+
+/// ColorMap as read by the screen shader
+#[repr(C)]
+#[derive(Debug, Copy, Clone, AsStd140)]
+pub struct ColorMapParameters {
+    /// ColorMapKind::shader_mode
+    pub kind: u32,
+    pub log_scale: u32,
+    pub range: Vec2,
+    pub gamma: f32,
+    pub stop_count: u32,
+    pub stops: ColorStops,
+}
+
+impl Default for ColorMapParameters {
+    fn default() -> Self { ColorMap::default().parameters() }
+}
+
+// Polynomial fits of the matplotlib colormaps by Matt Zucker (https://www.shadertoy.com/view/WlfXRN), highest degree last
+const VIRIDIS: [[f32; 3]; 7] = [
+    [0.277_727_33, 0.005_407_344_5, 0.334_099_8],
+    [0.105_093_04, 1.404_613_5, 1.384_590_1],
+    [-0.330_861_83, 0.214_847_56, 0.095_095_16],
+    [-4.634_230_5, -5.799_101, -19.332_441],
+    [6.228_27, 14.179_933, 56.690_55],
+    [4.776_385, -13.745_145, -65.353_03],
+    [-5.435_456, 4.645_852_6, 26.312_435],
+];
+const MAGMA: [[f32; 3]; 7] = [
+    [-0.002_136_485, -0.000_749_655_05, -0.005_386_128],
+    [0.251_660_54, 0.677_523_2, 2.494_026_7],
+    [8.353_717, -3.577_719_5, 0.314_467_9],
+    [-27.668_733, 14.264_731, -13.649_213],
+    [52.176_14, -27.943_606, 12.944_169],
+    [-50.768_524, 29.046_583, 4.234_153],
+    [18.655_705, -11.489_774, -5.601_961_5],
+];
+// Polynomial approximation of Turbo by Ruofei Du (https://www.shadertoy.com/view/3lBXR3)
+const TURBO: [[f32; 3]; 6] = [
+    [0.135_721_38, 0.091_402_61, 0.106_673_3],
+    [4.615_392_6, 2.194_188_4, 12.641_946],
+    [-42.660_324, 4.842_966_6, -60.582_047],
+    [132.131_08, -14.185_033, 110.362_77],
+    [-152.942_4, 4.277_299, -89.903_11],
+    [59.286_38, 2.829_566, 27.348_25],
+];
+
+fn polynomial(coefficients: &[[f32; 3]], t: f32) -> Vec3 {
+    coefficients.iter().rev().fold(Vec3::ZERO, |color, coefficient| color * t + Vec3::from_array(*coefficient))
+}
+
+impl ColorMapParameters {
+    /// Position of `value` in the color map, in [0, 1]
+    pub fn normalize(&self, value: f32) -> f32 {
+        let t = if self.log_scale != 0 {
+            let (min, max) = (self.range.x.max(LOG_SCALE_MIN), self.range.y.max(LOG_SCALE_MIN));
+            (value.max(LOG_SCALE_MIN).ln() - min.ln()) / (max.ln() - min.ln())
+        } else {
+            (value - self.range.x) / (self.range.y - self.range.x)
+        };
+        // Also maps a NaN from an empty range to 0
+        t.clamp(0.0, 1.0).max(0.0).powf(self.gamma)
+    }
+
+    /// Color of `value`, mirrors the colorMap function of Screen.wgsl
+    pub fn color(&self, gradient: &IqGradient, value: f32) -> Vec3 { self.color_at(gradient, self.normalize(value)) }
+
+    /// Color at the normalized position `t` of the color map
+    pub fn color_at(&self, gradient: &IqGradient, t: f32) -> Vec3 {
+        match ColorMapKind::from_shader_mode(self.kind) {
+            ColorMapKind::Cosine => gradient.evalue(t),
+            ColorMapKind::Stops => {
+                let stops = &self.stops.to_array()[..(self.stop_count as usize).clamp(1, MAX_COLOR_STOPS)];
+                let mut color = stops[0].truncate();
+                for pair in stops.windows(2) {
+                    let width = pair[1].w - pair[0].w;
+                    let blend = if width > 0.0 { ((t - pair[0].w) / width).clamp(0.0, 1.0) } else { (t >= pair[1].w) as u32 as f32 };
+                    color = color.lerp(pair[1].truncate(), blend);
+                }
+                color
+            },
+            ColorMapKind::Viridis => polynomial(&VIRIDIS, t),
+            ColorMapKind::Magma => polynomial(&MAGMA, t),
+            ColorMapKind::Turbo => polynomial(&TURBO, t),
+        }
+    }
+}
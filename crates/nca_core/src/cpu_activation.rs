@@ -0,0 +1,1285 @@
+use anyhow::{anyhow, bail, ensure, Context};
+use std::{collections::HashMap, fmt};
+
+// Interpreter of the subset of WGSL the activation functions are written in: f32, i32, u32 and bool scalars and vectors,
+// `var`/`let`/`const` declarations, assignments, `if`/`else`, `for` loops, `return`, arithmetic and comparisons,
+// swizzles, constructors, functions of the activation code and the usual builtins.
+// The types follow WGSL: the integer literals without suffix are abstract until they meet a typed value, the integers
+// divide with truncation and wrap around, comparisons of vectors are component-wise, and mixing types is an error
+// rather than a silent conversion, so that the values never differ from the shader ones.
+
+const MAX_LOOP_ITERATIONS: usize = 1_000_000;
+const MAX_CALL_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Literal(Value),
+    Identifier(String),
+    Punctuation(&'static str),
+}
+
+// Longest first, so that the multi characters ones are matched before their prefixes
+const PUNCTUATIONS: [&str; 34] = [
+    "->", "==", "!=", "<=", ">=", "&&", "||", "+=", "-=", "*=", "/=", "++", "--", "(", ")", "{", "}", "[", "]", ",", ";", ":", ".", "+", "-", "*",
+    "/", "%", "<", ">", "=", "!", "@", "&",
+];
+
+fn tokenize(code: &str) -> anyhow::Result<Vec<Token>> {
+    let characters: Vec<char> = code.chars().collect();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+
+    while index < characters.len() {
+        let character = characters[index];
+        let next = characters.get(index + 1).copied();
+
+        if character.is_whitespace() {
+            index += 1;
+        } else if character == '/' && next == Some('/') {
+            while index < characters.len() && characters[index] != '\n' {
+                index += 1;
+            }
+        } else if character == '/' && next == Some('*') {
+            index += 2;
+            while index < characters.len() && !(characters[index] == '*' && characters.get(index + 1) == Some(&'/')) {
+                index += 1;
+            }
+            index += 2;
+        } else if character.is_ascii_digit() || (character == '.' && next.is_some_and(|c| c.is_ascii_digit())) {
+            let start = index;
+            while index < characters.len() && (characters[index].is_ascii_digit() || characters[index] == '.') {
+                index += 1;
+            }
+            if index < characters.len() && (characters[index] == 'e' || characters[index] == 'E') {
+                index += 1;
+                if index < characters.len() && (characters[index] == '+' || characters[index] == '-') {
+                    index += 1;
+                }
+                while index < characters.len() && characters[index].is_ascii_digit() {
+                    index += 1;
+                }
+            }
+            let literal: String = characters[start..index].iter().collect();
+            let suffix = characters.get(index).copied().filter(|c| matches!(c, 'f' | 'h' | 'i' | 'u'));
+            if suffix.is_some() {
+                index += 1;
+            }
+            tokens.push(Token::Literal(number_literal(&literal, suffix)?));
+        } else if character.is_alphabetic() || character == '_' {
+            let start = index;
+            while index < characters.len() && (characters[index].is_alphanumeric() || characters[index] == '_') {
+                index += 1;
+            }
+            tokens.push(Token::Identifier(characters[start..index].iter().collect()));
+        } else {
+            let punctuation = PUNCTUATIONS
+                .iter()
+                .find(|punctuation| punctuation.chars().enumerate().all(|(offset, c)| characters.get(index + offset) == Some(&c)))
+                .ok_or_else(|| anyhow!("Unexpected character `{}`", character))?;
+            index += punctuation.len();
+            tokens.push(Token::Punctuation(punctuation));
+        }
+    }
+
+    Ok(tokens)
+}
+
+// Floating point with a fractional part, an exponent or a `f`/`h` suffix, integer otherwise
+fn number_literal(literal: &str, suffix: Option<char>) -> anyhow::Result<Value> {
+    if matches!(suffix, Some('f' | 'h')) || literal.contains(['.', 'e', 'E']) {
+        ensure!(!matches!(suffix, Some('i' | 'u')), "`{}` is not a valid integer", literal);
+        let value = literal.parse::<f32>().with_context(|| format!("`{}` is not a valid number", literal))?;
+        return Ok(Value::scalar(ScalarType::F32, value as f64));
+    }
+
+    let value = literal.parse::<i64>().with_context(|| format!("`{}` is not a valid integer", literal))?;
+    let literal = Value::scalar(ScalarType::AbstractInt, value as f64);
+    match suffix {
+        Some('i') => literal.convert(ScalarType::I32),
+        Some('u') => literal.convert(ScalarType::U32),
+        _ => Ok(literal),
+    }
+}
+
+// Type of the components, the integer literals without suffix are abstract until they meet a typed value
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ScalarType {
+    Bool,
+    F32,
+    I32,
+    U32,
+    AbstractInt,
+}
+
+impl ScalarType {
+    fn is_integer(self) -> bool { matches!(self, ScalarType::I32 | ScalarType::U32 | ScalarType::AbstractInt) }
+}
+
+impl fmt::Display for ScalarType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ScalarType::Bool => "bool",
+            ScalarType::F32 => "f32",
+            ScalarType::I32 => "i32",
+            ScalarType::U32 => "u32",
+            ScalarType::AbstractInt => "abstract integer",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Type {
+    scalar_type: ScalarType,
+    size: usize,
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.size == 1 {
+            write!(f, "{}", self.scalar_type)
+        } else {
+            write!(f, "vec{}<{}>", self.size, self.scalar_type)
+        }
+    }
+}
+
+// Component type and size of the types usable as constructors, without component type for the vectors taking it
+// from a template (`vec4<f32>`) or from their arguments
+fn constructor(name: &str) -> Option<(Option<ScalarType>, usize)> {
+    match name {
+        "f32" | "f16" => Some((Some(ScalarType::F32), 1)),
+        "i32" => Some((Some(ScalarType::I32), 1)),
+        "u32" => Some((Some(ScalarType::U32), 1)),
+        "bool" => Some((Some(ScalarType::Bool), 1)),
+        _ => {
+            let size = name.strip_prefix("vec")?.get(..1)?.parse::<usize>().ok().filter(|size| (2..=4).contains(size))?;
+            let scalar_type = match &name[4..] {
+                "" => None,
+                "f" | "h" => Some(ScalarType::F32),
+                "i" => Some(ScalarType::I32),
+                "u" => Some(ScalarType::U32),
+                _ => return None,
+            };
+            Some((scalar_type, size))
+        },
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Expression {
+    Literal(Value),
+    Variable(String),
+    Unary(&'static str, Box<Expression>),
+    Binary(&'static str, Box<Expression>, Box<Expression>),
+    Call(String, Vec<Expression>),
+    // Constructors and conversions, with the component type unless it is inferred from the arguments
+    Construct(Option<ScalarType>, usize, Vec<Expression>),
+    Member(Box<Expression>, String),
+    Index(Box<Expression>, Box<Expression>),
+}
+
+#[derive(Debug, Clone)]
+enum Statement {
+    // Name, declared type, initial value and whether abstract values stay abstract (`const`)
+    Declare(String, Option<Type>, Option<Expression>, bool),
+    // Target variable, optional swizzle or index, compound operator (`+` for `+=`) and value
+    Assign(String, Option<Expression>, Option<&'static str>, Expression),
+    If(Vec<(Expression, Vec<Statement>)>, Vec<Statement>),
+    For(Box<Statement>, Expression, Box<Statement>, Vec<Statement>),
+    Block(Vec<Statement>),
+    Return(Expression),
+    Break,
+    Continue,
+    Empty,
+}
+
+#[derive(Debug, Clone)]
+struct Function {
+    parameters: Vec<(String, Type)>,
+    return_type: Option<Type>,
+    body: Vec<Statement>,
+}
+
+// Functions and module constants, in declaration order for the constants which may depend on the previous ones
+type Module = (HashMap<String, Function>, Vec<Statement>);
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+// Binary operators by increasing precedence
+const BINARY_OPERATORS: [&[&str]; 5] = [&["||"], &["&&"], &["==", "!=", "<", "<=", ">", ">="], &["+", "-"], &["*", "/", "%"]];
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> { self.tokens.get(self.position) }
+
+    fn next(&mut self) -> anyhow::Result<Token> {
+        let token = self.tokens.get(self.position).cloned().ok_or_else(|| anyhow!("Unexpected end of the code"))?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn is(&self, punctuation: &str) -> bool { matches!(self.peek(), Some(Token::Punctuation(p)) if *p == punctuation) }
+
+    fn is_keyword(&self, keyword: &str) -> bool { matches!(self.peek(), Some(Token::Identifier(identifier)) if identifier == keyword) }
+
+    fn accept(&mut self, punctuation: &str) -> bool {
+        let accepted = self.is(punctuation);
+        if accepted {
+            self.position += 1;
+        }
+        accepted
+    }
+
+    fn expect(&mut self, punctuation: &str) -> anyhow::Result<()> {
+        ensure!(self.accept(punctuation), "Expected `{}`, found {}", punctuation, self.describe_next());
+        Ok(())
+    }
+
+    fn identifier(&mut self) -> anyhow::Result<String> {
+        match self.next()? {
+            Token::Identifier(identifier) => Ok(identifier),
+            token => bail!("Expected an identifier, found {:?}", token),
+        }
+    }
+
+    fn describe_next(&self) -> String { self.peek().map_or("the end of the code".to_owned(), |token| format!("{:?}", token)) }
+
+    // Attributes (`@must_use`) are not needed to evaluate the code
+    fn skip_attributes(&mut self) -> anyhow::Result<()> {
+        while self.accept("@") {
+            self.identifier()?;
+            if self.is("(") {
+                self.skip_balanced("(", ")")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_type(&mut self) -> anyhow::Result<Type> {
+        let name = self.identifier()?;
+        let (scalar_type, size) = constructor(&name).ok_or_else(|| anyhow!("Unsupported type `{}`", name))?;
+        let scalar_type = match scalar_type {
+            Some(scalar_type) => scalar_type,
+            None => self.template_type()?.ok_or_else(|| anyhow!("`{}` needs the type of its components", name))?,
+        };
+        Ok(Type { scalar_type, size })
+    }
+
+    // Component type of the vectors (`vec4<f32>`)
+    fn template_type(&mut self) -> anyhow::Result<Option<ScalarType>> {
+        if !self.accept("<") {
+            return Ok(None);
+        }
+        let component_type = self.parse_type()?;
+        ensure!(component_type.size == 1, "The components of a vector are scalars, not `{}`", component_type);
+        self.expect(">")?;
+        Ok(Some(component_type.scalar_type))
+    }
+
+    fn skip_balanced(&mut self, open: &str, close: &str) -> anyhow::Result<()> {
+        let mut depth = 0;
+        loop {
+            match self.next()? {
+                Token::Punctuation(p) if p == open => depth += 1,
+                Token::Punctuation(p) if p == close => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                },
+                _ => (),
+            }
+        }
+    }
+
+    fn module(&mut self) -> anyhow::Result<Module> {
+        let mut functions = HashMap::new();
+        let mut constants = Vec::new();
+
+        while self.peek().is_some() {
+            self.skip_attributes()?;
+            let keyword = self.identifier()?;
+            match keyword.as_str() {
+                "fn" => {
+                    let name = self.identifier()?;
+                    self.expect("(")?;
+                    let mut parameters = Vec::new();
+                    while !self.accept(")") {
+                        self.skip_attributes()?;
+                        let parameter = self.identifier()?;
+                        self.expect(":")?;
+                        parameters.push((parameter, self.parse_type()?));
+                        self.accept(",");
+                    }
+                    let return_type = if self.accept("->") {
+                        self.skip_attributes()?;
+                        Some(self.parse_type()?)
+                    } else {
+                        None
+                    };
+                    let body = self.block()?;
+                    functions.insert(name, Function { parameters, return_type, body });
+                },
+                "const" | "override" => {
+                    constants.push(self.declaration(&keyword)?);
+                    self.expect(";")?;
+                },
+                keyword => bail!("Unsupported declaration `{}`", keyword),
+            }
+        }
+
+        Ok((functions, constants))
+    }
+
+    fn block(&mut self) -> anyhow::Result<Vec<Statement>> {
+        self.expect("{")?;
+        let mut statements = Vec::new();
+        while !self.accept("}") {
+            statements.push(self.statement()?);
+        }
+        Ok(statements)
+    }
+
+    fn statement(&mut self) -> anyhow::Result<Statement> {
+        if self.accept(";") {
+            return Ok(Statement::Empty);
+        }
+        if self.is("{") {
+            return Ok(Statement::Block(self.block()?));
+        }
+
+        let Some(Token::Identifier(keyword)) = self.peek().cloned() else {
+            bail!("Expected a statement, found {}", self.describe_next());
+        };
+        match keyword.as_str() {
+            "return" => {
+                self.position += 1;
+                let value = self.expression()?;
+                self.expect(";")?;
+                Ok(Statement::Return(value))
+            },
+            "if" => {
+                let mut branches = Vec::new();
+                let mut otherwise = Vec::new();
+                self.position += 1;
+                loop {
+                    let condition = self.expression()?;
+                    branches.push((condition, self.block()?));
+                    if !self.is_keyword("else") {
+                        break;
+                    }
+                    self.position += 1;
+                    if self.is_keyword("if") {
+                        self.position += 1;
+                    } else {
+                        otherwise = self.block()?;
+                        break;
+                    }
+                }
+                Ok(Statement::If(branches, otherwise))
+            },
+            "for" => {
+                self.position += 1;
+                self.expect("(")?;
+                let initializer = self.simple_statement()?;
+                self.expect(";")?;
+                let condition = if self.is(";") { Expression::Literal(Value::bool(true)) } else { self.expression()? };
+                self.expect(";")?;
+                let update = if self.is(")") { Statement::Empty } else { self.simple_statement()? };
+                self.expect(")")?;
+                let body = self.block()?;
+                Ok(Statement::For(Box::new(initializer), condition, Box::new(update), body))
+            },
+            "break" | "continue" => {
+                self.position += 1;
+                self.expect(";")?;
+                Ok(if keyword == "break" { Statement::Break } else { Statement::Continue })
+            },
+            _ => {
+                let statement = self.simple_statement()?;
+                self.expect(";")?;
+                Ok(statement)
+            },
+        }
+    }
+
+    // Rest of a `var`, `let`, `const` or `override` declaration
+    fn declaration(&mut self, keyword: &str) -> anyhow::Result<Statement> {
+        if self.is("<") {
+            self.skip_balanced("<", ">")?;
+        }
+        let name = self.identifier()?;
+        let declared_type = if self.accept(":") { Some(self.parse_type()?) } else { None };
+        let value = if self.accept("=") { Some(self.expression()?) } else { None };
+        ensure!(declared_type.is_some() || value.is_some(), "`{}` is declared without type nor value", name);
+        Ok(Statement::Declare(name, declared_type, value, keyword == "const"))
+    }
+
+    // Declarations, assignments and increments, the statements allowed in the header of a for loop
+    fn simple_statement(&mut self) -> anyhow::Result<Statement> {
+        if self.is(";") {
+            return Ok(Statement::Empty);
+        }
+
+        if let Some(Token::Identifier(keyword)) = self.peek().cloned() {
+            if matches!(keyword.as_str(), "var" | "let" | "const") {
+                self.position += 1;
+                return self.declaration(&keyword);
+            }
+        }
+
+        let name = self.identifier()?;
+        let accessor = if self.accept(".") {
+            Some(Expression::Member(Box::new(Expression::Variable(name.clone())), self.identifier()?))
+        } else if self.accept("[") {
+            let index = self.expression()?;
+            self.expect("]")?;
+            Some(Expression::Index(Box::new(Expression::Variable(name.clone())), Box::new(index)))
+        } else {
+            None
+        };
+
+        let Token::Punctuation(operator) = self.next()? else {
+            bail!("Expected an assignment to `{}`", name);
+        };
+        let one = || Expression::Literal(Value::scalar(ScalarType::AbstractInt, 1.0));
+        let (compound, value) = match operator {
+            "=" => (None, self.expression()?),
+            "+=" | "-=" | "*=" | "/=" => (Some(&operator[..1]), self.expression()?),
+            "++" => (Some("+"), one()),
+            "--" => (Some("-"), one()),
+            _ => bail!("Unexpected `{}` after `{}`", operator, name),
+        };
+        Ok(Statement::Assign(name, accessor, compound, value))
+    }
+
+    fn expression(&mut self) -> anyhow::Result<Expression> { self.binary(0) }
+
+    fn binary(&mut self, level: usize) -> anyhow::Result<Expression> {
+        if level == BINARY_OPERATORS.len() {
+            return self.unary();
+        }
+
+        let mut left = self.binary(level + 1)?;
+        while let Some(Token::Punctuation(operator)) = self.peek().cloned() {
+            if !BINARY_OPERATORS[level].contains(&operator) {
+                break;
+            }
+            self.position += 1;
+            let right = self.binary(level + 1)?;
+            left = Expression::Binary(operator, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> anyhow::Result<Expression> {
+        if self.accept("-") {
+            return Ok(Expression::Unary("-", Box::new(self.unary()?)));
+        }
+        if self.accept("!") {
+            return Ok(Expression::Unary("!", Box::new(self.unary()?)));
+        }
+        if self.accept("+") {
+            return self.unary();
+        }
+        self.postfix()
+    }
+
+    fn postfix(&mut self) -> anyhow::Result<Expression> {
+        let mut expression = self.primary()?;
+        loop {
+            if self.accept(".") {
+                expression = Expression::Member(Box::new(expression), self.identifier()?);
+            } else if self.accept("[") {
+                let index = self.expression()?;
+                self.expect("]")?;
+                expression = Expression::Index(Box::new(expression), Box::new(index));
+            } else {
+                return Ok(expression);
+            }
+        }
+    }
+
+    // After the opening parenthesis of a call
+    fn arguments(&mut self) -> anyhow::Result<Vec<Expression>> {
+        let mut arguments = Vec::new();
+        while !self.accept(")") {
+            arguments.push(self.expression()?);
+            if !self.is(")") {
+                self.expect(",")?;
+            }
+        }
+        Ok(arguments)
+    }
+
+    fn primary(&mut self) -> anyhow::Result<Expression> {
+        match self.next()? {
+            Token::Literal(value) => Ok(Expression::Literal(value)),
+            Token::Punctuation("(") => {
+                let expression = self.expression()?;
+                self.expect(")")?;
+                Ok(expression)
+            },
+            Token::Identifier(identifier) => match identifier.as_str() {
+                "true" => Ok(Expression::Literal(Value::bool(true))),
+                "false" => Ok(Expression::Literal(Value::bool(false))),
+                _ => {
+                    if let Some((scalar_type, size)) = constructor(&identifier) {
+                        let scalar_type = match scalar_type {
+                            Some(scalar_type) => Some(scalar_type),
+                            None => self.template_type()?,
+                        };
+                        self.expect("(")?;
+                        return Ok(Expression::Construct(scalar_type, size, self.arguments()?));
+                    }
+                    if self.accept("(") {
+                        Ok(Expression::Call(identifier, self.arguments()?))
+                    } else {
+                        Ok(Expression::Variable(identifier))
+                    }
+                },
+            },
+            token => bail!("Unexpected {:?} in an expression", token),
+        }
+    }
+}
+
+// Scalars are values with a single component, WGSL having no one component vectors.
+// The components are stored as f64, which holds every f32, i32 and u32 exactly, the booleans being 0 or 1.
+#[derive(Debug, Clone, PartialEq)]
+struct Value {
+    scalar_type: ScalarType,
+    components: Vec<f64>,
+}
+
+impl Value {
+    fn scalar(scalar_type: ScalarType, value: f64) -> Self { Value { scalar_type, components: vec![value] } }
+
+    fn bool(value: bool) -> Self { Value::scalar(ScalarType::Bool, value as u8 as f64) }
+
+    fn zero(value_type: Type) -> Self {
+        Value {
+            scalar_type: value_type.scalar_type,
+            components: vec![0.0; value_type.size],
+        }
+    }
+
+    fn value_type(&self) -> Type {
+        Type {
+            scalar_type: self.scalar_type,
+            size: self.components.len(),
+        }
+    }
+
+    fn as_bool(&self) -> anyhow::Result<bool> {
+        ensure!(self.value_type() == Type { scalar_type: ScalarType::Bool, size: 1 }, "Expected a bool, got a {}", self.value_type());
+        Ok(self.components[0] != 0.0)
+    }
+
+    fn as_index(&self) -> anyhow::Result<usize> {
+        ensure!(self.scalar_type.is_integer() && self.components.len() == 1, "Expected an integer index, got a {}", self.value_type());
+        ensure!(self.components[0] >= 0.0, "Negative index {}", self.components[0]);
+        Ok(self.components[0] as usize)
+    }
+
+    // Implicit conversion, only the abstract integers are converted
+    fn convert(self, scalar_type: ScalarType) -> anyhow::Result<Value> {
+        if self.scalar_type == scalar_type {
+            return Ok(self);
+        }
+        ensure!(
+            self.scalar_type == ScalarType::AbstractInt && scalar_type != ScalarType::Bool,
+            "A {} is used as a {} (WGSL has no implicit conversion)",
+            self.value_type(),
+            scalar_type
+        );
+
+        let range = match scalar_type {
+            ScalarType::I32 => i32::MIN as f64..=i32::MAX as f64,
+            ScalarType::U32 => 0.0..=u32::MAX as f64,
+            _ => f64::MIN..=f64::MAX,
+        };
+        let components = self
+            .components
+            .into_iter()
+            .map(|value| {
+                ensure!(range.contains(&value), "{} does not fit in a {}", value, scalar_type);
+                Ok(if scalar_type == ScalarType::F32 { value as f32 as f64 } else { value })
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Value { scalar_type, components })
+    }
+
+    // Conversion to a declared type, of a variable, a parameter or a return value
+    fn declared(self, value_type: Type) -> anyhow::Result<Value> {
+        ensure!(self.components.len() == value_type.size, "Expected a {}, got a {}", value_type, self.value_type());
+        self.convert(value_type.scalar_type)
+    }
+
+    // Explicit conversion (`f32(i)`, `vec4<u32>(v)`): floats are truncated and saturated, i32 and u32 reinterpreted
+    fn cast(self, scalar_type: ScalarType) -> anyhow::Result<Value> {
+        if self.scalar_type == ScalarType::AbstractInt && scalar_type != ScalarType::Bool {
+            return self.convert(scalar_type);
+        }
+
+        let source = self.scalar_type;
+        let components = self
+            .components
+            .into_iter()
+            .map(|value| match (scalar_type, source) {
+                (ScalarType::Bool, _) => (value != 0.0) as u8 as f64,
+                (ScalarType::F32, _) => value as f32 as f64,
+                (ScalarType::I32, ScalarType::F32) => value as i32 as f64,
+                (ScalarType::I32, ScalarType::U32) => value as u32 as i32 as f64,
+                (ScalarType::U32, ScalarType::F32) => value as u32 as f64,
+                (ScalarType::U32, ScalarType::I32) => value as i32 as u32 as f64,
+                _ => value,
+            })
+            .collect();
+        Ok(Value { scalar_type, components })
+    }
+
+    // Values converted to their common type, the abstract integers taking the type of the others
+    fn unify(values: Vec<Value>) -> anyhow::Result<(ScalarType, Vec<Value>)> {
+        let mut common = values.first().map_or(ScalarType::AbstractInt, |value| value.scalar_type);
+        for value in &values {
+            match (common, value.scalar_type) {
+                (common, scalar_type) if common == scalar_type => (),
+                (ScalarType::AbstractInt, scalar_type) if scalar_type != ScalarType::Bool => common = scalar_type,
+                (common, ScalarType::AbstractInt) if common != ScalarType::Bool => (),
+                (common, scalar_type) => bail!("Mismatched types {} and {}", common, scalar_type),
+            }
+        }
+        let values = values.into_iter().map(|value| value.convert(common)).collect::<anyhow::Result<_>>()?;
+        Ok((common, values))
+    }
+
+    // Component-wise operation, scalars being broadcast to the size of the vectors
+    fn map(values: &[Value], scalar_type: ScalarType, operation: impl Fn(&[f64]) -> anyhow::Result<f64>) -> anyhow::Result<Value> {
+        let size = values.iter().map(|value| value.components.len()).max().unwrap_or(1);
+        ensure!(values.iter().all(|value| value.components.len() == 1 || value.components.len() == size), "Mismatched vector sizes");
+
+        let components = (0..size)
+            .map(|index| {
+                let components: Vec<f64> = values.iter().map(|value| if value.components.len() == 1 { value.components[0] } else { value.components[index] }).collect();
+                operation(&components)
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Value { scalar_type, components })
+    }
+
+    // Component-wise floating point operation, computed in f32 as on the GPU
+    fn map_floats(values: &[Value], operation: impl Fn(&[f32]) -> f32) -> anyhow::Result<Value> {
+        let values = values.iter().map(|value| value.clone().convert(ScalarType::F32)).collect::<anyhow::Result<Vec<_>>>()?;
+        Value::map(&values, ScalarType::F32, |components| {
+            let components: Vec<f32> = components.iter().map(|&component| component as f32).collect();
+            Ok(operation(&components) as f64)
+        })
+    }
+
+    fn floats(&self) -> anyhow::Result<Vec<f32>> {
+        Ok(self.clone().convert(ScalarType::F32)?.components.into_iter().map(|component| component as f32).collect())
+    }
+}
+
+// Two's complement wrapping of the integer results
+fn wrap(value: i64, scalar_type: ScalarType) -> f64 {
+    match scalar_type {
+        ScalarType::I32 => value as i32 as f64,
+        ScalarType::U32 => value as u32 as f64,
+        _ => value as f64,
+    }
+}
+
+fn arithmetic(operator: &str, left: Value, right: Value) -> anyhow::Result<Value> {
+    let (scalar_type, values) = Value::unify(vec![left, right])?;
+    match scalar_type {
+        ScalarType::Bool => bail!("`{}` is not defined on booleans", operator),
+        ScalarType::F32 => Value::map_floats(&values, |x| match operator {
+            "+" => x[0] + x[1],
+            "-" => x[0] - x[1],
+            "*" => x[0] * x[1],
+            "/" => x[0] / x[1],
+            _ => x[0] % x[1],
+        }),
+        // Abstract integers are evaluated at compile time, where overflows and divisions by zero are errors
+        ScalarType::AbstractInt => Value::map(&values, scalar_type, |x| {
+            let (a, b) = (x[0] as i64, x[1] as i64);
+            let result = match operator {
+                "+" => a.checked_add(b),
+                "-" => a.checked_sub(b),
+                "*" => a.checked_mul(b),
+                "/" => a.checked_div(b),
+                _ => a.checked_rem(b),
+            };
+            result.map(|result| result as f64).ok_or_else(|| anyhow!("Invalid integer operation {} {} {}", a, operator, b))
+        }),
+        // A division by zero gives the dividend and the remainder zero, as in WGSL
+        _ => Value::map(&values, scalar_type, |x| {
+            let (a, b) = (x[0] as i64, x[1] as i64);
+            let result = match operator {
+                "+" => a.wrapping_add(b),
+                "-" => a.wrapping_sub(b),
+                "*" => a.wrapping_mul(b),
+                "/" if b == 0 => a,
+                "/" => a / b,
+                _ if b == 0 => 0,
+                _ => a % b,
+            };
+            Ok(wrap(result, scalar_type))
+        }),
+    }
+}
+
+fn comparison(operator: &str, left: Value, right: Value) -> anyhow::Result<Value> {
+    ensure!(left.components.len() == right.components.len(), "`{}` compares a {} and a {}", operator, left.value_type(), right.value_type());
+    let (scalar_type, values) = Value::unify(vec![left, right])?;
+    ensure!(scalar_type != ScalarType::Bool || matches!(operator, "==" | "!="), "`{}` is not defined on booleans", operator);
+
+    Value::map(&values, ScalarType::Bool, |x| {
+        let result = match operator {
+            "==" => x[0] == x[1],
+            "!=" => x[0] != x[1],
+            "<" => x[0] < x[1],
+            "<=" => x[0] <= x[1],
+            ">" => x[0] > x[1],
+            _ => x[0] >= x[1],
+        };
+        Ok(result as u8 as f64)
+    })
+}
+
+fn binary(operator: &str, left: Value, right: Value) -> anyhow::Result<Value> {
+    match operator {
+        "+" | "-" | "*" | "/" | "%" => arithmetic(operator, left, right),
+        _ => comparison(operator, left, right),
+    }
+}
+
+// Constructors concatenate their arguments, a single scalar being repeated. A single value of the same size is converted.
+fn construct(scalar_type: Option<ScalarType>, size: usize, arguments: Vec<Value>) -> anyhow::Result<Value> {
+    if let (Some(scalar_type), [argument]) = (scalar_type, arguments.as_slice()) {
+        if argument.components.len() == size {
+            return argument.clone().cast(scalar_type);
+        }
+    }
+
+    let argument_count = arguments.len();
+    let (common, arguments) = Value::unify(arguments)?;
+    let scalar_type = scalar_type.unwrap_or(common);
+    let components: Vec<f64> =
+        arguments.into_iter().map(|argument| argument.convert(scalar_type)).collect::<anyhow::Result<Vec<_>>>()?.into_iter().flat_map(|argument| argument.components).collect();
+    let constructed = Type { scalar_type, size };
+    let components = match components.len() {
+        0 => vec![0.0; size],
+        1 if argument_count == 1 => vec![components[0]; size],
+        count if count == size => components,
+        count => bail!("A {} built from {} components", constructed, count),
+    };
+    Ok(Value { scalar_type, components })
+}
+
+// Number of arguments and component-wise operation of the floating point builtins
+type FloatBuiltin = (usize, fn(&[f32]) -> f32);
+
+fn builtin(name: &str, arguments: Vec<Value>) -> anyhow::Result<Option<Value>> {
+    let argument_count = |count: usize| -> anyhow::Result<()> {
+        ensure!(arguments.len() == count, "`{}` expects {} arguments, got {}", name, count, arguments.len());
+        Ok(())
+    };
+
+    // Also defined on integers
+    let integer_count = match name {
+        "abs" | "sign" => Some(1),
+        "min" | "max" => Some(2),
+        "clamp" => Some(3),
+        _ => None,
+    };
+    if let Some(count) = integer_count {
+        argument_count(count)?;
+        let (scalar_type, values) = Value::unify(arguments.clone())?;
+        if scalar_type.is_integer() {
+            return Value::map(&values, scalar_type, |x| {
+                let x: Vec<i64> = x.iter().map(|&x| x as i64).collect();
+                let result = match name {
+                    "abs" => x[0].abs(),
+                    "sign" => x[0].signum(),
+                    "min" => x[0].min(x[1]),
+                    "max" => x[0].max(x[1]),
+                    _ => x[0].max(x[1]).min(x[2]),
+                };
+                Ok(wrap(result, scalar_type))
+            })
+            .map(Some);
+        }
+    }
+
+    let float: Option<FloatBuiltin> = match name {
+        "abs" => Some((1, |x| x[0].abs())),
+        "sin" => Some((1, |x| x[0].sin())),
+        "cos" => Some((1, |x| x[0].cos())),
+        "tan" => Some((1, |x| x[0].tan())),
+        "asin" => Some((1, |x| x[0].asin())),
+        "acos" => Some((1, |x| x[0].acos())),
+        "atan" => Some((1, |x| x[0].atan())),
+        "sinh" => Some((1, |x| x[0].sinh())),
+        "cosh" => Some((1, |x| x[0].cosh())),
+        "tanh" => Some((1, |x| x[0].tanh())),
+        "asinh" => Some((1, |x| x[0].asinh())),
+        "acosh" => Some((1, |x| x[0].acosh())),
+        "atanh" => Some((1, |x| x[0].atanh())),
+        "exp" => Some((1, |x| x[0].exp())),
+        "exp2" => Some((1, |x| x[0].exp2())),
+        "log" => Some((1, |x| x[0].ln())),
+        "log2" => Some((1, |x| x[0].log2())),
+        "sqrt" => Some((1, |x| x[0].sqrt())),
+        "inverseSqrt" => Some((1, |x| 1.0 / x[0].sqrt())),
+        "floor" => Some((1, |x| x[0].floor())),
+        "ceil" => Some((1, |x| x[0].ceil())),
+        "round" => Some((1, |x| x[0].round_ties_even())),
+        "trunc" => Some((1, |x| x[0].trunc())),
+        "fract" => Some((1, |x| x[0] - x[0].floor())),
+        "sign" => Some((1, |x| if x[0] == 0.0 { 0.0 } else { x[0].signum() })),
+        "saturate" => Some((1, |x| x[0].clamp(0.0, 1.0))),
+        "degrees" => Some((1, |x| x[0].to_degrees())),
+        "radians" => Some((1, |x| x[0].to_radians())),
+        "pow" => Some((2, |x| x[0].powf(x[1]))),
+        "min" => Some((2, |x| x[0].min(x[1]))),
+        "max" => Some((2, |x| x[0].max(x[1]))),
+        "atan2" => Some((2, |x| x[0].atan2(x[1]))),
+        "step" => Some((2, |x| if x[1] >= x[0] { 1.0 } else { 0.0 })),
+        "clamp" => Some((3, |x| x[0].max(x[1]).min(x[2]))),
+        "mix" => Some((3, |x| x[0] * (1.0 - x[2]) + x[1] * x[2])),
+        "fma" => Some((3, |x| x[0] * x[1] + x[2])),
+        "smoothstep" => Some((3, |x| {
+            let t = ((x[2] - x[0]) / (x[1] - x[0])).clamp(0.0, 1.0);
+            t * t * (3.0 - 2.0 * t)
+        })),
+        _ => None,
+    };
+    if let Some((count, function)) = float {
+        argument_count(count)?;
+        return Value::map_floats(&arguments, function).with_context(|| format!("In `{}`", name)).map(Some);
+    }
+
+    let value = match name {
+        "dot" => {
+            argument_count(2)?;
+            let (a, b) = (arguments[0].floats()?, arguments[1].floats()?);
+            ensure!(a.len() == b.len(), "`dot` of vectors of different sizes");
+            Value::scalar(ScalarType::F32, a.iter().zip(b.iter()).map(|(a, b)| a * b).sum::<f32>() as f64)
+        },
+        "length" => {
+            argument_count(1)?;
+            Value::scalar(ScalarType::F32, arguments[0].floats()?.iter().map(|x| x * x).sum::<f32>().sqrt() as f64)
+        },
+        "distance" => {
+            argument_count(2)?;
+            let difference = arithmetic("-", arguments[0].clone(), arguments[1].clone())?.floats()?;
+            Value::scalar(ScalarType::F32, difference.iter().map(|x| x * x).sum::<f32>().sqrt() as f64)
+        },
+        "normalize" => {
+            argument_count(1)?;
+            let length = arguments[0].floats()?.iter().map(|x| x * x).sum::<f32>().sqrt();
+            Value::map_floats(&arguments, |x| x[0] / length)?
+        },
+        "select" => {
+            argument_count(3)?;
+            let mut arguments = arguments;
+            let condition = arguments.pop().expect("There are three arguments");
+            ensure!(condition.scalar_type == ScalarType::Bool, "The condition of `select` is a {}", condition.value_type());
+            let (scalar_type, mut values) = Value::unify(arguments)?;
+            let size = values[0].components.len();
+            ensure!(values[1].components.len() == size, "`select` between a {} and a {}", values[0].value_type(), values[1].value_type());
+            ensure!(condition.components.len() == 1 || condition.components.len() == size, "`select` of a {} with a {} condition", values[0].value_type(), condition.value_type());
+            values.push(condition);
+            Value::map(&values, scalar_type, |x| Ok(if x[2] != 0.0 { x[1] } else { x[0] }))?
+        },
+        "all" | "any" => {
+            argument_count(1)?;
+            ensure!(arguments[0].scalar_type == ScalarType::Bool, "`{}` expects booleans, got a {}", name, arguments[0].value_type());
+            let components = &arguments[0].components;
+            Value::bool(if name == "all" { components.iter().all(|x| *x != 0.0) } else { components.iter().any(|x| *x != 0.0) })
+        },
+        _ => return Ok(None),
+    };
+    Ok(Some(value))
+}
+
+fn swizzle_index(component: char) -> anyhow::Result<usize> {
+    match component {
+        'x' | 'r' => Ok(0),
+        'y' | 'g' => Ok(1),
+        'z' | 'b' => Ok(2),
+        'w' | 'a' => Ok(3),
+        _ => bail!("`{}` is not a vector component", component),
+    }
+}
+
+enum Flow {
+    Normal,
+    Return(Value),
+    Break,
+    Continue,
+}
+
+struct Interpreter<'a> {
+    functions: &'a HashMap<String, Function>,
+    constants: &'a HashMap<String, Value>,
+    depth: usize,
+}
+
+impl Interpreter<'_> {
+    fn call(&mut self, name: &str, arguments: Vec<Value>) -> anyhow::Result<Value> {
+        if let Some(function) = self.functions.get(name) {
+            ensure!(function.parameters.len() == arguments.len(), "`{}` expects {} arguments, got {}", name, function.parameters.len(), arguments.len());
+            ensure!(self.depth < MAX_CALL_DEPTH, "Too many nested calls (recursion is not allowed in WGSL)");
+
+            let parameters = function
+                .parameters
+                .iter()
+                .zip(arguments)
+                .map(|((parameter, parameter_type), argument)| {
+                    let argument = argument.declared(*parameter_type).with_context(|| format!("Argument `{}` of `{}`", parameter, name))?;
+                    Ok((parameter.clone(), argument))
+                })
+                .collect::<anyhow::Result<HashMap<String, Value>>>()?;
+            let mut scopes = vec![parameters];
+            self.depth += 1;
+            let flow = self.execute_block(&function.body, &mut scopes);
+            self.depth -= 1;
+            return match (flow?, function.return_type) {
+                (Flow::Return(value), Some(return_type)) => value.declared(return_type).with_context(|| format!("Value returned by `{}`", name)),
+                _ => bail!("`{}` does not return a value", name),
+            };
+        }
+
+        builtin(name, arguments)?.ok_or_else(|| anyhow!("Unknown function `{}`", name))
+    }
+
+    fn variable<'s>(&self, name: &str, scopes: &'s mut [HashMap<String, Value>]) -> Option<&'s mut Value> {
+        scopes.iter_mut().rev().find_map(|scope| scope.get_mut(name))
+    }
+
+    fn evaluate(&mut self, expression: &Expression, scopes: &mut Vec<HashMap<String, Value>>) -> anyhow::Result<Value> {
+        match expression {
+            Expression::Literal(value) => Ok(value.clone()),
+            Expression::Variable(name) => match self.variable(name, scopes) {
+                Some(value) => Ok(value.clone()),
+                None => self.constants.get(name).cloned().ok_or_else(|| anyhow!("Unknown variable `{}`", name)),
+            },
+            Expression::Unary(operator, operand) => {
+                let operand = self.evaluate(operand, scopes)?;
+                let scalar_type = operand.scalar_type;
+                match (*operator, scalar_type) {
+                    ("!", ScalarType::Bool) => Value::map(&[operand], scalar_type, |x| Ok(1.0 - x[0])),
+                    ("-", ScalarType::F32 | ScalarType::AbstractInt) => Value::map(&[operand], scalar_type, |x| Ok(-x[0])),
+                    ("-", ScalarType::I32) => Value::map(&[operand], scalar_type, |x| Ok(wrap(-(x[0] as i64), scalar_type))),
+                    (operator, _) => bail!("`{}` is not defined on a {}", operator, operand.value_type()),
+                }
+            },
+            Expression::Binary(operator, left, right) => {
+                let left = self.evaluate(left, scopes)?;
+                // Short circuit as in WGSL
+                match *operator {
+                    "&&" if !left.as_bool()? => return Ok(Value::bool(false)),
+                    "||" if left.as_bool()? => return Ok(Value::bool(true)),
+                    "&&" | "||" => return Ok(Value::bool(self.evaluate(right, scopes)?.as_bool()?)),
+                    _ => (),
+                }
+                let right = self.evaluate(right, scopes)?;
+                binary(operator, left, right)
+            },
+            Expression::Call(name, arguments) => {
+                let arguments = arguments.iter().map(|argument| self.evaluate(argument, scopes)).collect::<anyhow::Result<Vec<Value>>>()?;
+                self.call(name, arguments)
+            },
+            Expression::Construct(scalar_type, size, arguments) => {
+                let arguments = arguments.iter().map(|argument| self.evaluate(argument, scopes)).collect::<anyhow::Result<Vec<Value>>>()?;
+                construct(*scalar_type, *size, arguments)
+            },
+            Expression::Member(value, swizzle) => {
+                let value = self.evaluate(value, scopes)?;
+                ensure!(value.components.len() > 1, "`.{}` on a {}", swizzle, value.value_type());
+                let components = swizzle
+                    .chars()
+                    .map(|component| swizzle_index(component).and_then(|index| value.components.get(index).copied().ok_or_else(|| anyhow!("No `{}` component", component))))
+                    .collect::<anyhow::Result<Vec<f64>>>()?;
+                Ok(Value {
+                    scalar_type: value.scalar_type,
+                    components,
+                })
+            },
+            Expression::Index(value, index) => {
+                let value = self.evaluate(value, scopes)?;
+                let index = self.evaluate(index, scopes)?.as_index()?;
+                let component = value.components.get(index).copied().ok_or_else(|| anyhow!("Index {} out of bounds", index))?;
+                Ok(Value::scalar(value.scalar_type, component))
+            },
+        }
+    }
+
+    fn assign(&mut self, name: &str, accessor: &Option<Expression>, value: Value, scopes: &mut Vec<HashMap<String, Value>>) -> anyhow::Result<()> {
+        let indices: Option<Vec<usize>> = match accessor {
+            None => None,
+            Some(Expression::Member(_, swizzle)) => Some(swizzle.chars().map(swizzle_index).collect::<anyhow::Result<_>>()?),
+            Some(Expression::Index(_, index)) => Some(vec![self.evaluate(index, scopes)?.as_index()?]),
+            Some(_) => bail!("Unsupported assignment to `{}`", name),
+        };
+
+        let variable = self.variable(name, scopes).ok_or_else(|| anyhow!("Unknown variable `{}`", name))?;
+        let value = match indices {
+            None => value.declared(variable.value_type()),
+            Some(_) => value.convert(variable.scalar_type),
+        }
+        .with_context(|| format!("Assignment to `{}`", name))?;
+
+        match indices {
+            None => *variable = value,
+            Some(indices) => {
+                ensure!(variable.components.len() > 1, "`{}` is not a vector", name);
+                for (position, index) in indices.into_iter().enumerate() {
+                    let component = variable.components.get_mut(index).ok_or_else(|| anyhow!("Index {} out of bounds", index))?;
+                    *component = if value.components.len() == 1 { value.components[0] } else { value.components[position] };
+                }
+            },
+        }
+        Ok(())
+    }
+
+    fn execute_block(&mut self, statements: &[Statement], scopes: &mut Vec<HashMap<String, Value>>) -> anyhow::Result<Flow> {
+        scopes.push(HashMap::new());
+        let mut flow = Flow::Normal;
+        for statement in statements {
+            flow = self.execute(statement, scopes)?;
+            if !matches!(flow, Flow::Normal) {
+                break;
+            }
+        }
+        scopes.pop();
+        Ok(flow)
+    }
+
+    fn execute(&mut self, statement: &Statement, scopes: &mut Vec<HashMap<String, Value>>) -> anyhow::Result<Flow> {
+        match statement {
+            Statement::Declare(name, declared_type, value, keep_abstract) => {
+                let value = match (value, declared_type) {
+                    (Some(value), _) => self.evaluate(value, scopes)?,
+                    (None, Some(declared_type)) => Value::zero(*declared_type),
+                    (None, None) => bail!("`{}` is declared without type nor value", name),
+                };
+                let value = match declared_type {
+                    Some(declared_type) => value.declared(*declared_type).with_context(|| format!("Declaration of `{}`", name))?,
+                    // `var` and `let` give the abstract integers the i32 type
+                    None if !keep_abstract && value.scalar_type == ScalarType::AbstractInt => value.convert(ScalarType::I32)?,
+                    None => value,
+                };
+                scopes.last_mut().expect("There is always a scope").insert(name.clone(), value);
+            },
+            Statement::Assign(name, accessor, compound, value) => {
+                let mut value = self.evaluate(value, scopes)?;
+                if let Some(operator) = compound {
+                    let current = match accessor {
+                        Some(accessor) => self.evaluate(accessor, scopes)?,
+                        None => self.evaluate(&Expression::Variable(name.clone()), scopes)?,
+                    };
+                    value = binary(operator, current, value)?;
+                }
+                self.assign(name, accessor, value, scopes)?;
+            },
+            Statement::If(branches, otherwise) => {
+                for (condition, body) in branches {
+                    if self.evaluate(condition, scopes)?.as_bool()? {
+                        return self.execute_block(body, scopes);
+                    }
+                }
+                return self.execute_block(otherwise, scopes);
+            },
+            Statement::For(initializer, condition, update, body) => {
+                scopes.push(HashMap::new());
+                self.execute(initializer, scopes)?;
+                let mut iterations = 0;
+                let mut flow = Flow::Normal;
+                while self.evaluate(condition, scopes)?.as_bool()? {
+                    iterations += 1;
+                    ensure!(iterations <= MAX_LOOP_ITERATIONS, "The loop does not end after {} iterations", MAX_LOOP_ITERATIONS);
+                    match self.execute_block(body, scopes)? {
+                        Flow::Return(value) => {
+                            flow = Flow::Return(value);
+                            break;
+                        },
+                        Flow::Break => break,
+                        Flow::Normal | Flow::Continue => (),
+                    }
+                    self.execute(update, scopes)?;
+                }
+                scopes.pop();
+                return Ok(flow);
+            },
+            Statement::Block(statements) => return self.execute_block(statements, scopes),
+            Statement::Return(value) => return Ok(Flow::Return(self.evaluate(value, scopes)?)),
+            Statement::Break => return Ok(Flow::Break),
+            Statement::Continue => return Ok(Flow::Continue),
+            Statement::Empty => (),
+        }
+        Ok(Flow::Normal)
+    }
+}
+
+/// Activation code parsed once to be evaluated on the CPU, to check the values computed by the simulation shader
+#[derive(Debug, Clone)]
+pub struct CpuActivation {
+    functions: HashMap<String, Function>,
+    constants: HashMap<String, Value>,
+}
+
+impl CpuActivation {
+    pub fn parse(activation_code: &str) -> anyhow::Result<Self> {
+        let mut parser = Parser { tokens: tokenize(activation_code)?, position: 0 };
+        let (functions, declarations) = parser.module()?;
+        ensure!(functions.contains_key("activationFunction"), "The code does not define `activationFunction`");
+
+        let mut constants = HashMap::new();
+        for declaration in declarations {
+            let Statement::Declare(name, ..) = &declaration else {
+                unreachable!("The module only declares constants");
+            };
+            let mut scopes = vec![HashMap::new()];
+            Interpreter { functions: &functions, constants: &constants, depth: 0 }
+                .execute(&declaration, &mut scopes)
+                .with_context(|| format!("Unable to evaluate the constant `{}`", name))?;
+            constants.extend(scopes.pop().expect("The constant scope is still there"));
+        }
+
+        Ok(Self { functions, constants })
+    }
+
+    /// Output of `activationFunction` for the weighted sum of the neighbourhood
+    pub fn evaluate(&self, kernel_output: [f32; 4]) -> anyhow::Result<[f32; 4]> {
+        let mut interpreter = Interpreter { functions: &self.functions, constants: &self.constants, depth: 0 };
+        let kernel_output = Value {
+            scalar_type: ScalarType::F32,
+            components: kernel_output.iter().map(|&component| component as f64).collect(),
+        };
+        let output = interpreter.call("activationFunction", vec![kernel_output])?;
+        let output_type = output.value_type();
+        ensure!(output_type == Type { scalar_type: ScalarType::F32, size: 4 }, "`activationFunction` returns a {} instead of a vec4<f32>", output_type);
+        Ok(std::array::from_fn(|index| output.components[index] as f32))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::preset::{ACTIVATION_FUNCTIONS_PRESETS, PRESETS};
+
+    const SAMPLE_INPUTS: [f32; 8] = [-2.5, -1.0, 0.0, 0.25, 1.0, 3.0, 3.5, 11.0];
+
+    // The activations of the presets compute `r` from `kernelOutput.x` and return `vec4<f32>(r, r, r, 1.0)`
+    fn expected_activation(name: &str) -> fn(f32) -> f32 {
+        match name {
+            "Identity" => |x| x,
+            "Sin" => f32::sin,
+            "Abs" | "Stars" | "test" => f32::abs,
+            "Power" => |x| x.powf(2.0),
+            "Tanh" | "test2" => |x| ((2.0 * x).exp() - 1.0) / ((2.0 * x).exp() + 1.0),
+            " inverted gaussian" | "Slime" => |x| -1.0 / (0.89 * x.powf(2.0) + 1.0) + 1.0,
+            "Mitosis" => |x| -1.0 / (0.9 * x.powf(2.0) + 1.0) + 1.0,
+            "Blob" => |x| -1.0 / 2f32.powf(x.powf(2.0)) + 1.0,
+            "Pathways" => |x| 1.0 / 2f32.powf((x - 3.5).powf(2.0)),
+            "Waves" => |x| (1.2 * x).abs(),
+            "Game Of life" => |x| if x == 3.0 || x == 11.0 || x == 12.0 { 1.0 } else { 0.0 },
+            name => panic!("No expected values for the activation `{}`", name),
+        }
+    }
+
+    fn check_activation(name: &str, code: &str) {
+        let activation = CpuActivation::parse(code).unwrap_or_else(|error| panic!("`{}` does not parse: {:#}", name, error));
+        let expected_activation = expected_activation(name);
+        for x in SAMPLE_INPUTS {
+            let output = activation.evaluate([x, 0.0, 0.0, 1.0]).unwrap_or_else(|error| panic!("`{}` fails on {}: {:#}", name, x, error));
+            let r = expected_activation(x);
+            let expected = [r, r, r, 1.0];
+            assert!(
+                output.iter().zip(expected).all(|(output, expected)| (output - expected).abs() <= 1e-6 * expected.abs().max(1.0)),
+                "`{}` of {} gives {:?} instead of {:?}",
+                name,
+                x,
+                output,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn activation_presets() {
+        for (name, code) in ACTIVATION_FUNCTIONS_PRESETS.iter() {
+            check_activation(name, code);
+        }
+    }
+
+    #[test]
+    fn preset_activations() {
+        for (name, preset) in PRESETS.iter() {
+            check_activation(name, &preset.activation_code);
+        }
+    }
+
+    fn evaluate(body: &str) -> anyhow::Result<[f32; 4]> {
+        let code = format!("fn activationFunction(kernelOutput: vec4<f32>) -> vec4<f32> {{ {} }}", body);
+        CpuActivation::parse(&code)?.evaluate([7.0, 2.0, -7.0, 1.0])
+    }
+
+    #[test]
+    fn integer_semantics() {
+        let division = evaluate("let a: i32 = 7; let b = 2; return vec4<f32>(f32(a / b), f32(a % b), f32(-a / b), f32(-a % b));");
+        assert_eq!(division.unwrap(), [3.0, 1.0, -3.0, -1.0]);
+
+        // Abstract integers are divided as integers before being converted
+        let abstract_division = evaluate("return vec4<f32>(7 / 2, 7.0 / 2.0, f32(i32(kernelOutput.x) / 2), 1);");
+        assert_eq!(abstract_division.unwrap(), [3.0, 3.5, 3.0, 1.0]);
+
+        let by_zero = evaluate("var zero = 0; return vec4<f32>(f32(7 / zero), f32(7 % zero), f32(7u / u32(zero)), 1.0);");
+        assert_eq!(by_zero.unwrap(), [7.0, 0.0, 7.0, 1.0]);
+
+        let wrapping = evaluate("var a = 2147483647; a += 1; var b = 0u; b -= 1u; return vec4<f32>(f32(a), f32(b), f32(i32(b)), f32(u32(-1i)));");
+        assert_eq!(wrapping.unwrap(), [i32::MIN as f32, u32::MAX as f32, -1.0, u32::MAX as f32]);
+
+        let conversions = evaluate("return vec4<f32>(f32(i32(-2.7)), f32(u32(-1.0)), f32(i32(kernelOutput.z)), f32(bool(kernelOutput.w)));");
+        assert_eq!(conversions.unwrap(), [-2.0, 0.0, -7.0, 1.0]);
+
+        let counter = evaluate("var sum = 0; for (var i = 0; i < 5; i++) { sum += i; } return vec4<f32>(f32(sum) / 4, f32(max(sum, 12)), f32(abs(-sum)), 1.0);");
+        assert_eq!(counter.unwrap(), [2.5, 12.0, 10.0, 1.0]);
+    }
+
+    #[test]
+    fn component_wise_comparisons() {
+        let equal = evaluate("return select(vec4<f32>(0.0), vec4<f32>(1.0), kernelOutput == vec4<f32>(7.0, 0.0, -7.0, 0.0));");
+        assert_eq!(equal.unwrap(), [1.0, 0.0, 1.0, 0.0]);
+
+        let not_equal = evaluate("return select(vec4<f32>(0.0), vec4<f32>(1.0), kernelOutput.xyzw != kernelOutput.xxzz);");
+        assert_eq!(not_equal.unwrap(), [0.0, 1.0, 0.0, 1.0]);
+
+        let reductions = evaluate("let less = kernelOutput < vec4<f32>(8.0); return vec4<f32>(f32(all(less)), f32(any(less)), f32(all(less.xy)), f32(!any(!less)));");
+        assert_eq!(reductions.unwrap(), [1.0, 1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn rejects_what_the_shader_rejects() {
+        // Implicit conversions between concrete types
+        assert!(evaluate("let a: i32 = 1; return vec4<f32>(f32(a * 2.0));").is_err());
+        assert!(evaluate("return vec4<f32>(kernelOutput.x + 1u);").is_err());
+        assert!(evaluate("var r: f32 = 1i; return vec4<f32>(r);").is_err());
+        // Vector conditions
+        assert!(evaluate("if kernelOutput == kernelOutput { return kernelOutput; } return vec4<f32>(0.0);").is_err());
+        assert!(evaluate("return select(vec4<f32>(0.0), vec4<f32>(1.0), kernelOutput.xy == kernelOutput.xy);").is_err());
+        // Negation of an unsigned integer and overflow of an abstract one
+        assert!(evaluate("let a = 1u; return vec4<f32>(f32(-a));").is_err());
+        assert!(evaluate("return vec4<f32>(f32(i32(9223372036854775807 + 1)));").is_err());
+        assert!(evaluate("return vec4<f32>(f32(3000000000i));").is_err());
+        assert!(evaluate("return vec4<f32>(1.0 / 0);").is_ok());
+        assert!(evaluate("return vec4<f32>(f32(1 / 0));").is_err());
+    }
+}
@@ -0,0 +1,145 @@
+use crate::{boundary_condition::BoundaryCondition, lattice::Lattice, simulation::StateSnapshot, simulation_data::KERNEL_SIZE};
+
+/// Reference implementation of the simulation shader on the CPU, with the same integer neighbourhood arithmetic.
+/// Slow, it is meant to check the states computed by the GPU.
+pub struct CpuSimulation {
+    kernel: [f32; KERNEL_SIZE * KERNEL_SIZE],
+    boundary_condition: BoundaryCondition,
+    lattice: Lattice,
+}
+
+impl CpuSimulation {
+    /// `kernel` in row-major order, as given to `Simulation::set_kernel`
+    pub fn new(kernel: [f32; KERNEL_SIZE * KERNEL_SIZE], boundary_condition: BoundaryCondition, lattice: Lattice) -> Self {
+        // The kernel cells without neighbour on the lattice are cleared, like in SimulationUniforms::set_lattice
+        let mut kernel = kernel;
+        lattice.mask(&mut kernel);
+        Self { kernel, boundary_condition, lattice }
+    }
+
+    /// Weighted sum of the neighbourhood of the cell (x, y), in the same order as the sums of simulationBase.wgsl
+    pub fn kernel_output(&self, state: &StateSnapshot, x: i32, y: i32) -> [f32; 4] {
+        let mut sum = [0.0f32; 4];
+        for row in 0..KERNEL_SIZE {
+            for col in 0..KERNEL_SIZE {
+                let Some([dx, dy]) = self.lattice.neighbour_offset(col, row, x, y) else {
+                    continue;
+                };
+                let weight = self.kernel[row * KERNEL_SIZE + col];
+                let neighbour = self.neighbour(state, [x + dx, y + dy]);
+                sum.iter_mut().zip(neighbour).for_each(|(channel, value)| *channel += value * weight);
+            }
+        }
+        sum
+    }
+
+    /// Next generation of `state`, quantized like the simulation texture.
+    /// `activation` stands for the activationFunction of the shader.
+    pub fn step(&self, state: &StateSnapshot, activation: impl Fn([f32; 4]) -> [f32; 4]) -> StateSnapshot {
+        let [width, height] = state.size;
+        let mut rgba = Vec::with_capacity(state.rgba.len());
+        for y in 0..height as i32 {
+            for x in 0..width as i32 {
+                let output = activation(self.kernel_output(state, x, y));
+                rgba.extend(output.map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8));
+            }
+        }
+        StateSnapshot { size: state.size, rgba }
+    }
+
+    // Mirrors getNeighbour of simulationBase.wgsl
+    fn neighbour(&self, state: &StateSnapshot, coords: [i32; 2]) -> [f32; 4] {
+        match self.boundary_condition.resolve(coords, state.size) {
+            Some([x, y]) => {
+                let index = ((y as u32 * state.size[0] + x as u32) * 4) as usize;
+                std::array::from_fn(|channel| state.rgba[index + channel] as f32 / 255.0)
+            },
+            None => {
+                let value = self.boundary_condition.constant_value();
+                [value, value, value, 1.0]
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIFE_KERNEL: [f32; 9] = [1., 1., 1., 1., 9., 1., 1., 1., 1.];
+
+    // activationFunction of the "Game Of life" preset
+    fn life(kernel_output: [f32; 4]) -> [f32; 4] {
+        let r = if [3.0, 11.0, 12.0].contains(&kernel_output[0]) { 1.0 } else { 0.0 };
+        [r, r, r, 1.0]
+    }
+
+    fn state(size: [u32; 2], alive: &[[u32; 2]]) -> StateSnapshot {
+        let mut rgba: Vec<u8> = [0, 0, 0, 255].repeat((size[0] * size[1]) as usize);
+        for [x, y] in alive {
+            let index = ((y * size[0] + x) * 4) as usize;
+            rgba[index..index + 3].fill(255);
+        }
+        StateSnapshot { size, rgba }
+    }
+
+    fn alive_cells(state: &StateSnapshot) -> Vec<[u32; 2]> {
+        (0..state.texel_count() as u32).filter(|index| state.rgba[*index as usize * 4] == 255).map(|index| [index % state.size[0], index / state.size[0]]).collect()
+    }
+
+    #[test]
+    fn blinker_oscillates() {
+        let simulation = CpuSimulation::new(LIFE_KERNEL, BoundaryCondition::Wrap, Lattice::Square);
+        let horizontal = state([37, 23], &[[9, 10], [10, 10], [11, 10]]);
+
+        let vertical = simulation.step(&horizontal, life);
+        assert_eq!(alive_cells(&vertical), vec![[10, 9], [10, 10], [10, 11]]);
+        assert_eq!(simulation.step(&vertical, life).rgba, horizontal.rgba);
+    }
+
+    #[test]
+    fn blinker_across_the_border() {
+        // Only the torus keeps the blinker whole when it crosses the left and right borders
+        let horizontal = state([37, 23], &[[36, 10], [0, 10], [1, 10]]);
+        assert_eq!(alive_cells(&CpuSimulation::new(LIFE_KERNEL, BoundaryCondition::Wrap, Lattice::Square).step(&horizontal, life)), vec![[0, 9], [0, 10], [0, 11]]);
+        assert_eq!(alive_cells(&CpuSimulation::new(LIFE_KERNEL, BoundaryCondition::Constant(0.0), Lattice::Square).step(&horizontal, life)), Vec::<[u32; 2]>::new());
+    }
+
+    #[test]
+    fn boundary_conditions_at_the_corner() {
+        let state = state([5, 4], &[[0, 0]]);
+        // The corner cell weighted by the kernel center, and by its neighbours outside of the grid that read it back
+        let kernel_output = |boundary_condition| CpuSimulation::new(LIFE_KERNEL, boundary_condition, Lattice::Square).kernel_output(&state, 0, 0)[0];
+        assert_eq!(kernel_output(BoundaryCondition::Wrap), 9.0);
+        assert_eq!(kernel_output(BoundaryCondition::Clamp), 9.0 + 3.0);
+        assert_eq!(kernel_output(BoundaryCondition::Mirror), 9.0 + 3.0);
+        assert_eq!(kernel_output(BoundaryCondition::Constant(1.0)), 9.0 + 5.0);
+    }
+
+    #[test]
+    fn kernel_orientation() {
+        // The kernel is laid out as displayed, the previous texture row being below the cell:
+        // the last kernel cell alone weights the texel at (+1, -1)
+        let state = state([5, 5], &[[3, 1]]);
+        for index in 0..9 {
+            let mut kernel = [0.0; 9];
+            kernel[index] = 1.0;
+            let expected = if index == 8 { 1.0 } else { 0.0 };
+            assert_eq!(CpuSimulation::new(kernel, BoundaryCondition::Wrap, Lattice::Square).kernel_output(&state, 2, 2)[0], expected, "kernel cell {}", index);
+        }
+    }
+
+    #[test]
+    fn lattice_neighbourhoods() {
+        // Every cell alive: the kernel output counts the neighbours and the cell itself, including across the seams
+        let full = StateSnapshot { size: [6, 4], rgba: vec![255; 6 * 4 * 4] };
+        for (lattice, count) in [(Lattice::Square, 9.0), (Lattice::Hexagonal, 7.0), (Lattice::Triangular, 4.0)] {
+            let simulation = CpuSimulation::new([1.0; 9], BoundaryCondition::Wrap, lattice);
+            for y in 0..4 {
+                for x in 0..6 {
+                    assert_eq!(simulation.kernel_output(&full, x, y)[0], count, "{} at {:?}", lattice, (x, y));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use anyhow::Context;
+use std::path::Path;
+
+/// Text representations of a 3x3 kernel, always written in row-major order
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum KernelFormat {
+    /// Flat json array: `[1, 1, 1, 1, 9, 1, 1, 1, 1]`
+    JsonArray,
+    /// One row per line, values separated by whitespaces
+    Matrix,
+    /// Nested rows as kernels are written on https://neuralpatterns.io: `[[1, 1, 1], [1, 9, 1], [1, 1, 1]]`
+    NeuralPatterns,
+}
+
+impl std::fmt::Display for KernelFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            KernelFormat::JsonArray => write!(f, "Json array"),
+            KernelFormat::Matrix => write!(f, "Matrix"),
+            KernelFormat::NeuralPatterns => write!(f, "neuralpatterns.io"),
+        }
+    }
+}
+
+impl KernelFormat {
+    pub const ALL: [KernelFormat; 3] = [KernelFormat::JsonArray, KernelFormat::Matrix, KernelFormat::NeuralPatterns];
+
+    pub fn format(&self, kernel: &[f32; 9]) -> String {
+        match self {
+            KernelFormat::JsonArray => format!("[{}]", join_values(kernel, ", ")),
+            KernelFormat::Matrix => kernel.chunks(3).map(|row| join_values(row, " ")).collect::<Vec<_>>().join("\n"),
+            KernelFormat::NeuralPatterns => format!("[{}]", kernel.chunks(3).map(|row| format!("[{}]", join_values(row, ", "))).collect::<Vec<_>>().join(", ")),
+        }
+    }
+
+    /// Guess the format from the first non whitespace characters
+    pub fn detect(text: &str) -> Self {
+        let prefix: String = text.chars().filter(|c| !c.is_whitespace()).take(2).collect();
+        match prefix.as_str() {
+            "[[" => KernelFormat::NeuralPatterns,
+            p if p.starts_with('[') => KernelFormat::JsonArray,
+            _ => KernelFormat::Matrix,
+        }
+    }
+
+    pub fn parse(&self, text: &str) -> anyhow::Result<[f32; 9]> {
+        let values: Vec<f32> = match self {
+            KernelFormat::JsonArray => serde_json::from_str::<Vec<f32>>(text).context("Unable to parse the kernel as a json array")?,
+            KernelFormat::Matrix => text
+                .split(|c: char| c.is_whitespace() || c == ',' || c == ';')
+                .filter(|value| !value.is_empty())
+                .map(|value| value.parse::<f32>().with_context(|| format!("`{}` is not a valid kernel value", value)))
+                .collect::<anyhow::Result<Vec<f32>>>()?,
+            KernelFormat::NeuralPatterns => {
+                let rows = serde_json::from_str::<Vec<Vec<f32>>>(text).context("Unable to parse the kernel as nested rows")?;
+                anyhow::ensure!(
+                    rows.iter().all(|row| row.len() == 3),
+                    "Each kernel row must contain 3 values (got rows of size {:?})",
+                    rows.iter().map(Vec::len).collect::<Vec<_>>()
+                );
+                rows.concat()
+            },
+        };
+
+        values
+            .try_into()
+            .map_err(|values: Vec<f32>| anyhow::anyhow!("A kernel must contain 9 values (3x3), got {}", values.len()))
+    }
+}
+
+pub fn parse_kernel(text: &str) -> anyhow::Result<[f32; 9]> { KernelFormat::detect(text).parse(text.trim()) }
+
+pub fn load_kernel<P: AsRef<Path>>(path: P) -> anyhow::Result<[f32; 9]> {
+    fn inner(path: &Path) -> anyhow::Result<[f32; 9]> {
+        let string_path: &str = path.to_str().unwrap_or("");
+        let text = std::fs::read_to_string(path).with_context(|| format!("Could not open file `{}`", string_path))?;
+        parse_kernel(&text).with_context(|| format!("Unable to Parse the kernel in file `{}`", string_path))
+    }
+
+    inner(path.as_ref())
+}
+
+pub fn save_kernel<P: AsRef<Path>>(path: P, kernel: &[f32; 9], format: KernelFormat) -> std::io::Result<()> { std::fs::write(path, format.format(kernel)) }
+
+fn join_values(values: &[f32], separator: &str) -> String { values.iter().map(|value| value.to_string()).collect::<Vec<_>>().join(separator) }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation_data::SimulationUniforms;
+
+    // No symmetry, so that a transposed or mirrored kernel can not go unnoticed
+    const ASYMMETRIC_KERNEL: [f32; 9] = [0.68, -0.9, 0.25, 1.0, -0.66, 3.5, -2.0, 0.125, 7.0];
+
+    #[test]
+    fn format_round_trip() {
+        for format in KernelFormat::ALL {
+            let text = format.format(&ASYMMETRIC_KERNEL);
+            assert_eq!(format.parse(&text).unwrap(), ASYMMETRIC_KERNEL, "{} format of\n{}", format, text);
+            assert_eq!(KernelFormat::detect(&text), format, "detected format of\n{}", text);
+            assert_eq!(parse_kernel(&text).unwrap(), ASYMMETRIC_KERNEL, "auto detected {} format of\n{}", format, text);
+        }
+    }
+
+    #[test]
+    fn parse_is_row_major() {
+        let expected = [1., 2., 3., 4., 5., 6., 7., 8., 9.];
+        assert_eq!(parse_kernel("[1, 2, 3, 4, 5, 6, 7, 8, 9]").unwrap(), expected);
+        assert_eq!(parse_kernel("1 2 3\n4 5 6\n7 8 9").unwrap(), expected);
+        assert_eq!(parse_kernel("  [[1, 2, 3], [4, 5, 6], [7, 8, 9]]\n").unwrap(), expected);
+    }
+
+    #[test]
+    fn parse_rejects_invalid_kernels() {
+        assert!(parse_kernel("[1, 2, 3]").is_err());
+        assert!(parse_kernel("[[1, 2], [3, 4, 5, 6], [7, 8, 9]]").is_err());
+        assert!(parse_kernel("1 2 3\n4 x 6\n7 8 9").is_err());
+    }
+
+    // The uniforms store the kernel in a column-major matrix, the slices are row-major like the text formats
+    #[test]
+    fn uniforms_kernel_slice_is_row_major() {
+        let mut uniforms = SimulationUniforms::new(&[16, 16]);
+        uniforms.set_kernel_from_slice(ASYMMETRIC_KERNEL);
+        assert_eq!(uniforms.get_kernel_as_slice(), ASYMMETRIC_KERNEL);
+        for row in 0..3 {
+            for col in 0..3 {
+                assert_eq!(uniforms.get_kernel_at(col, row), ASYMMETRIC_KERNEL[row * 3 + col]);
+            }
+        }
+        assert_eq!(parse_kernel(&uniforms.kernel_to_string(KernelFormat::NeuralPatterns)).unwrap(), ASYMMETRIC_KERNEL);
+    }
+}
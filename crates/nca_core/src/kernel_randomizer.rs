@@ -0,0 +1,369 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rand_distr::{Distribution, Normal};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "egui")]
+use oxyde::egui as egui;
+
+use super::{
+    kernel_symmetry::KernelSymmetryMode,
+    lattice::Lattice,
+    simulation_data::KERNEL_SIZE,
+};
+
+const CENTER_INDEX: usize = (KERNEL_SIZE * KERNEL_SIZE) / 2;
+// Draws tried before giving up on the normalized sum, when the sign pattern makes it hard to reach
+const MAX_NORMALIZATION_DRAWS: usize = 64;
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub enum KernelDistribution {
+    Uniform { min: f32, max: f32 },
+    Normal { mean: f32, std_dev: f32 },
+    /// Zero with the given probability, uniform in [min, max] otherwise
+    Sparse { zero_probability: f32, min: f32, max: f32 },
+    /// Uniform integers in [min, max]
+    Integer { min: i32, max: i32 },
+}
+
+impl std::fmt::Display for KernelDistribution {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            KernelDistribution::Uniform { .. } => write!(f, "Uniform"),
+            KernelDistribution::Normal { .. } => write!(f, "Normal"),
+            KernelDistribution::Sparse { .. } => write!(f, "Sparse"),
+            KernelDistribution::Integer { .. } => write!(f, "Integer"),
+        }
+    }
+}
+
+impl KernelDistribution {
+    pub const DEFAULTS: [KernelDistribution; 4] = [
+        KernelDistribution::Uniform { min: -1.0, max: 1.0 },
+        KernelDistribution::Normal { mean: 0.0, std_dev: 0.5 },
+        KernelDistribution::Sparse { zero_probability: 0.5, min: -1.0, max: 1.0 },
+        KernelDistribution::Integer { min: -1, max: 1 },
+    ];
+
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> f32 {
+        match *self {
+            KernelDistribution::Uniform { min, max } => sample_uniform(rng, min, max),
+            KernelDistribution::Normal { mean, std_dev } => match Normal::new(mean, std_dev) {
+                Ok(normal) => normal.sample(rng),
+                Err(_) => mean,
+            },
+            KernelDistribution::Sparse { zero_probability, min, max } =>
+                if rng.random_bool(zero_probability.clamp(0.0, 1.0) as f64) {
+                    0.0
+                } else {
+                    sample_uniform(rng, min, max)
+                },
+            KernelDistribution::Integer { min, max } =>
+                if min < max {
+                    rng.random_range(min..=max) as f32
+                } else {
+                    min as f32
+                },
+        }
+    }
+}
+
+fn sample_uniform<R: Rng>(rng: &mut R, min: f32, max: f32) -> f32 {
+    if min < max {
+        rng.random_range(min..max)
+    } else {
+        min
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Default)]
+pub struct KernelConstraints {
+    /// Rescale the kernel (except a fixed center) so that its values sum to this target.
+    /// Only positive scales are applied so that the signs are kept, the kernel is drawn again when the scale would be negative
+    /// and left unnormalized if no draw fits. Integer kernels are not rescaled.
+    pub normalized_sum: Option<f32>,
+    pub fixed_center: Option<f32>,
+    /// Sign of each cell in row-major order (-1, 1 or 0 to leave it free).
+    /// With a symmetry mode, the sign of the orbit representative is used for the whole orbit.
+    pub sign_pattern: Option<[i8; KERNEL_SIZE * KERNEL_SIZE]>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct KernelRandomizer {
+    pub distribution: KernelDistribution,
+    pub constraints: KernelConstraints,
+    pub seed: u64,
+}
+
+impl Default for KernelRandomizer {
+    fn default() -> Self {
+        Self {
+            distribution: KernelDistribution::DEFAULTS[0],
+            constraints: KernelConstraints::default(),
+            seed: 0,
+        }
+    }
+}
+
+impl KernelRandomizer {
+    pub fn reseed(&mut self) { self.seed = rand::rng().random(); }
+
+    /// Row-major kernel, always the same for a given seed and settings, the cells unused by the lattice stay at zero
+    pub fn generate(&self, lattice: Lattice, mode: KernelSymmetryMode) -> [f32; KERNEL_SIZE * KERNEL_SIZE] {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut kernel = self.draw(&mut rng, lattice, mode);
+
+        let Some(target) = self.constraints.normalized_sum else {
+            return kernel;
+        };
+        // Rescaling would break the integer values
+        if let KernelDistribution::Integer { .. } = self.distribution {
+            return kernel;
+        }
+
+        for _ in 0..MAX_NORMALIZATION_DRAWS {
+            if let Some(scale) = self.normalization_scale(&kernel, target) {
+                for i in self.scaled_indices() {
+                    kernel[i] *= scale;
+                }
+                return kernel;
+            }
+            kernel = self.draw(&mut rng, lattice, mode);
+        }
+        kernel
+    }
+
+    fn draw(&self, rng: &mut StdRng, lattice: Lattice, mode: KernelSymmetryMode) -> [f32; KERNEL_SIZE * KERNEL_SIZE] {
+        let mut kernel = [0.0; KERNEL_SIZE * KERNEL_SIZE];
+
+        // one random value shared by all the cells of each symmetry orbit
+        for orbit in lattice.orbits(mode) {
+            let (col, row) = orbit[0];
+            let mut value = self.distribution.sample(rng);
+
+            if let Some(sign_pattern) = &self.constraints.sign_pattern {
+                match sign_pattern[row * KERNEL_SIZE + col].signum() {
+                    0 => (),
+                    sign => value = value.abs() * sign as f32,
+                }
+            }
+
+            for (col, row) in orbit {
+                kernel[row * KERNEL_SIZE + col] = value;
+            }
+        }
+
+        if let Some(center) = self.constraints.fixed_center {
+            kernel[CENTER_INDEX] = center;
+        }
+
+        kernel
+    }
+
+    // The center only follows the normalization if it is not fixed
+    fn scaled_indices(&self) -> impl Iterator<Item = usize> + Clone + '_ {
+        (0..KERNEL_SIZE * KERNEL_SIZE).filter(|&i| self.constraints.fixed_center.is_none() || i != CENTER_INDEX)
+    }
+
+    /// Scale bringing the sum of the kernel to `target`, None when it would flip the signs (or is undefined)
+    fn normalization_scale(&self, kernel: &[f32; KERNEL_SIZE * KERNEL_SIZE], target: f32) -> Option<f32> {
+        let fixed: f32 = self.constraints.fixed_center.unwrap_or(0.0);
+        let scaled_sum: f32 = self.scaled_indices().map(|i| kernel[i]).sum();
+        if scaled_sum.abs() <= f32::EPSILON {
+            return None;
+        }
+        let scale = (target - fixed) / scaled_sum;
+        (scale > 0.0 && scale.is_finite()).then_some(scale)
+    }
+
+    #[cfg(feature = "egui")]
+    pub fn ui_control(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed: bool = false;
+
+        ui.horizontal(|ui| {
+            ui.label("Distribution: ");
+            egui::ComboBox::from_id_source("kernel_distribution")
+                .selected_text(self.distribution.to_string())
+                .show_ui(ui, |ui| {
+                    for distribution in KernelDistribution::DEFAULTS {
+                        let selected = std::mem::discriminant(&self.distribution) == std::mem::discriminant(&distribution);
+                        if ui.selectable_label(selected, distribution.to_string()).clicked() && !selected {
+                            self.distribution = distribution;
+                            changed = true;
+                        }
+                    }
+                });
+        });
+
+        ui.horizontal(|ui| match &mut self.distribution {
+            KernelDistribution::Uniform { min, max } => {
+                changed |= ui.add(egui::DragValue::new(min).speed(0.01).prefix("min: ")).changed();
+                changed |= ui.add(egui::DragValue::new(max).speed(0.01).prefix("max: ")).changed();
+            },
+            KernelDistribution::Normal { mean, std_dev } => {
+                changed |= ui.add(egui::DragValue::new(mean).speed(0.01).prefix("mean: ")).changed();
+                changed |= ui.add(egui::DragValue::new(std_dev).speed(0.01).range(0.0..=f32::MAX).prefix("std dev: ")).changed();
+            },
+            KernelDistribution::Sparse { zero_probability, min, max } => {
+                changed |= ui.add(egui::DragValue::new(zero_probability).speed(0.01).range(0.0..=1.0).prefix("p(0): ")).changed();
+                changed |= ui.add(egui::DragValue::new(min).speed(0.01).prefix("min: ")).changed();
+                changed |= ui.add(egui::DragValue::new(max).speed(0.01).prefix("max: ")).changed();
+            },
+            KernelDistribution::Integer { min, max } => {
+                changed |= ui.add(egui::DragValue::new(min).speed(0.1).prefix("min: ")).changed();
+                changed |= ui.add(egui::DragValue::new(max).speed(0.1).prefix("max: ")).changed();
+            },
+        });
+
+        let integer = matches!(self.distribution, KernelDistribution::Integer { .. });
+        ui.add_enabled_ui(!integer, |ui| {
+            changed |= optional_value_ui(ui, &mut self.constraints.normalized_sum, "Normalize sum to", 1.0);
+        })
+        .response
+        .on_disabled_hover_text("Integer kernels are not rescaled");
+        changed |= optional_value_ui(ui, &mut self.constraints.fixed_center, "Fixed center", 0.0);
+
+        let mut use_sign_pattern = self.constraints.sign_pattern.is_some();
+        if ui.checkbox(&mut use_sign_pattern, "Fixed sign pattern").changed() {
+            self.constraints.sign_pattern = if use_sign_pattern { Some([0; KERNEL_SIZE * KERNEL_SIZE]) } else { None };
+            changed = true;
+        }
+        if let Some(sign_pattern) = &mut self.constraints.sign_pattern {
+            egui::Grid::new("kernel sign pattern").show(ui, |ui| {
+                for row in 0..KERNEL_SIZE {
+                    for col in 0..KERNEL_SIZE {
+                        let sign = &mut sign_pattern[row * KERNEL_SIZE + col];
+                        let label = match *sign {
+                            1 => "+",
+                            -1 => "-",
+                            _ => "±",
+                        };
+                        if ui.button(label).on_hover_text("Click to cycle between free, positive and negative").clicked() {
+                            *sign = match *sign {
+                                0 => 1,
+                                1 => -1,
+                                _ => 0,
+                            };
+                            changed = true;
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+        }
+
+        ui.horizontal(|ui| {
+            changed |= seed_ui(ui, &mut self.seed);
+            if ui.button("new seed").clicked() {
+                self.reseed();
+                changed = true;
+            }
+        });
+
+        changed
+    }
+}
+
+// Edited as text, a DragValue goes through f64 and would round the seeds above 2^53
+#[cfg(feature = "egui")]
+fn seed_ui(ui: &mut egui::Ui, seed: &mut u64) -> bool {
+    ui.label("seed: ");
+    // The text being edited is kept while it does not parse, the seed is shown again once the field loses the focus
+    let id = ui.make_persistent_id("kernel_randomizer_seed");
+    let mut text: String = ui.data(|data| data.get_temp(id)).unwrap_or_else(|| seed.to_string());
+    let parsed = text.trim().parse::<u64>();
+    let response = ui.add(
+        egui::TextEdit::singleline(&mut text)
+            .desired_width(180.0)
+            .text_color_opt(parsed.is_err().then_some(egui::Color32::RED)),
+    );
+
+    if response.has_focus() {
+        ui.data_mut(|data| data.insert_temp(id, text.clone()));
+    } else {
+        ui.data_mut(|data| data.remove::<String>(id));
+    }
+
+    match text.trim().parse::<u64>() {
+        Ok(value) if response.changed() && value != *seed => {
+            *seed = value;
+            true
+        },
+        _ => false,
+    }
+}
+
+#[cfg(feature = "egui")]
+fn optional_value_ui(ui: &mut egui::Ui, value: &mut Option<f32>, label: &str, default_value: f32) -> bool {
+    let mut changed: bool = false;
+    ui.horizontal(|ui| {
+        let mut enabled = value.is_some();
+        if ui.checkbox(&mut enabled, label).changed() {
+            *value = if enabled { Some(default_value) } else { None };
+            changed = true;
+        }
+        if let Some(value) = value {
+            changed |= ui.add(egui::DragValue::new(value).speed(0.01)).changed();
+        }
+    });
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEEDS: std::ops::Range<u64> = 0..200;
+
+    #[test]
+    fn same_seed_same_kernel() {
+        let randomizer = KernelRandomizer { seed: 42, ..Default::default() };
+        assert_eq!(randomizer.generate(Lattice::Square, KernelSymmetryMode::Any), randomizer.generate(Lattice::Square, KernelSymmetryMode::Any));
+    }
+
+    #[test]
+    fn normalization_keeps_the_sign_pattern() {
+        let mut sign_pattern = [1; KERNEL_SIZE * KERNEL_SIZE];
+        sign_pattern[0] = -1;
+        sign_pattern[CENTER_INDEX] = 0;
+        for target in [-1.0, 0.5, 1.0] {
+            for seed in SEEDS {
+                let randomizer = KernelRandomizer {
+                    distribution: KernelDistribution::Uniform { min: -1.0, max: 1.0 },
+                    constraints: KernelConstraints { normalized_sum: Some(target), fixed_center: None, sign_pattern: Some(sign_pattern) },
+                    seed,
+                };
+                let kernel = randomizer.generate(Lattice::Square, KernelSymmetryMode::Any);
+                for (value, sign) in kernel.iter().zip(sign_pattern) {
+                    assert!(sign == 0 || *value == 0.0 || value.signum() == sign as f32, "seed {}, target {}: {:?}", seed, target, kernel);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn normalization_reaches_the_target() {
+        for seed in SEEDS {
+            let randomizer = KernelRandomizer {
+                constraints: KernelConstraints { normalized_sum: Some(1.0), fixed_center: Some(-2.0), sign_pattern: None },
+                seed,
+                ..Default::default()
+            };
+            let kernel = randomizer.generate(Lattice::Square, KernelSymmetryMode::Full);
+            assert_eq!(kernel[CENTER_INDEX], -2.0);
+            assert!((kernel.iter().sum::<f32>() - 1.0).abs() < 1e-4, "seed {}: {:?}", seed, kernel);
+        }
+    }
+
+    #[test]
+    fn integer_kernels_stay_integer() {
+        for seed in SEEDS {
+            let randomizer = KernelRandomizer {
+                distribution: KernelDistribution::Integer { min: -3, max: 3 },
+                constraints: KernelConstraints { normalized_sum: Some(1.0), ..Default::default() },
+                seed,
+            };
+            let kernel = randomizer.generate(Lattice::Hexagonal, KernelSymmetryMode::Any);
+            assert!(kernel.iter().all(|value| value.fract() == 0.0), "seed {}: {:?}", seed, kernel);
+        }
+    }
+}
@@ -0,0 +1,266 @@
+use serde::{Deserialize, Serialize};
+
+// Kernels are square and stored in row-major order: value at (col, row) is kernel[row * size + col]
+
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq)]
+pub enum KernelSymmetryMode {
+    Any,
+    /// Mirrored along the vertical axis (left and right columns are equal)
+    Vertical,
+    /// Mirrored along the horizontal axis (top and bottom rows are equal)
+    Horizontal,
+    /// Both vertical and horizontal
+    Full,
+    /// Mirrored along the main diagonal (transpose)
+    Diagonal,
+    /// Mirrored along the anti-diagonal
+    AntiDiagonal,
+    /// Invariant under quarter turns
+    Rotational90,
+    /// Invariant under half turns
+    Rotational180,
+    /// Invariant under sixth of turns, only on the hexagonal lattice (see `Lattice::orbits`)
+    Rotational60,
+    /// Isotropic: cells at the same distance from the center share the same value
+    Radial,
+}
+
+impl std::fmt::Display for KernelSymmetryMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            KernelSymmetryMode::Any => write!(f, "Any"),
+            KernelSymmetryMode::Vertical => write!(f, "Vertical"),
+            KernelSymmetryMode::Horizontal => write!(f, "Horizontal"),
+            KernelSymmetryMode::Full => write!(f, "Full"),
+            KernelSymmetryMode::Diagonal => write!(f, "Diagonal"),
+            KernelSymmetryMode::AntiDiagonal => write!(f, "Anti-diagonal"),
+            KernelSymmetryMode::Rotational90 => write!(f, "Rotational 90°"),
+            KernelSymmetryMode::Rotational180 => write!(f, "Rotational 180°"),
+            KernelSymmetryMode::Rotational60 => write!(f, "Rotational 60°"),
+            KernelSymmetryMode::Radial => write!(f, "Radial"),
+        }
+    }
+}
+
+/// Maps (col, row) to its image, `last` being the index of the last row/column
+type CellTransform = fn(usize, usize, usize) -> (usize, usize);
+
+fn mirror_vertical(col: usize, row: usize, last: usize) -> (usize, usize) { (last - col, row) }
+fn mirror_horizontal(col: usize, row: usize, last: usize) -> (usize, usize) { (col, last - row) }
+fn mirror_diagonal(col: usize, row: usize, _last: usize) -> (usize, usize) { (row, col) }
+fn mirror_anti_diagonal(col: usize, row: usize, last: usize) -> (usize, usize) { (last - row, last - col) }
+fn rotate_90(col: usize, row: usize, last: usize) -> (usize, usize) { (last - row, col) }
+fn rotate_180(col: usize, row: usize, last: usize) -> (usize, usize) { (last - col, last - row) }
+
+impl KernelSymmetryMode {
+    pub const ALL: [KernelSymmetryMode; 10] = [
+        KernelSymmetryMode::Any,
+        KernelSymmetryMode::Vertical,
+        KernelSymmetryMode::Horizontal,
+        KernelSymmetryMode::Full,
+        KernelSymmetryMode::Diagonal,
+        KernelSymmetryMode::AntiDiagonal,
+        KernelSymmetryMode::Rotational90,
+        KernelSymmetryMode::Rotational180,
+        KernelSymmetryMode::Rotational60,
+        KernelSymmetryMode::Radial,
+    ];
+
+    // Cell permutations generating the symmetry group, the orbit of a cell is its closure under them
+    fn generators(&self) -> &'static [CellTransform] {
+        match self {
+            // No sixth of turn on a square kernel
+            KernelSymmetryMode::Any | KernelSymmetryMode::Radial | KernelSymmetryMode::Rotational60 => &[],
+            KernelSymmetryMode::Vertical => &[mirror_vertical],
+            KernelSymmetryMode::Horizontal => &[mirror_horizontal],
+            KernelSymmetryMode::Full => &[mirror_vertical, mirror_horizontal],
+            KernelSymmetryMode::Diagonal => &[mirror_diagonal],
+            KernelSymmetryMode::AntiDiagonal => &[mirror_anti_diagonal],
+            KernelSymmetryMode::Rotational90 => &[rotate_90],
+            KernelSymmetryMode::Rotational180 => &[rotate_180],
+        }
+    }
+
+    /// All the cells sharing their value with (col, row), sorted in row-major order (the first one is the orbit representative)
+    pub fn orbit(&self, col: usize, row: usize, size: usize) -> Vec<(usize, usize)> {
+        let mut orbit: Vec<(usize, usize)> = if let KernelSymmetryMode::Radial = self {
+            // Doubled coordinates relative to the center to stay in integers for even sizes
+            let squared_distance = |(col, row): (usize, usize)| {
+                let dx = 2 * col as i64 - (size as i64 - 1);
+                let dy = 2 * row as i64 - (size as i64 - 1);
+                dx * dx + dy * dy
+            };
+            let distance = squared_distance((col, row));
+            (0..size)
+                .flat_map(|row| (0..size).map(move |col| (col, row)))
+                .filter(|cell| squared_distance(*cell) == distance)
+                .collect()
+        } else {
+            let mut orbit = vec![(col, row)];
+            let mut index = 0;
+            while index < orbit.len() {
+                for generator in self.generators() {
+                    let (col, row) = orbit[index];
+                    let cell = generator(col, row, size - 1);
+                    if !orbit.contains(&cell) {
+                        orbit.push(cell);
+                    }
+                }
+                index += 1;
+            }
+            orbit
+        };
+
+        orbit.sort_by_key(|&(col, row)| (row, col));
+        orbit
+    }
+
+    /// Partition of the kernel cells in groups sharing the same value
+    pub fn orbits(&self, size: usize) -> Vec<Vec<(usize, usize)>> {
+        let mut orbits: Vec<Vec<(usize, usize)>> = Vec::new();
+        for row in 0..size {
+            for col in 0..size {
+                if !orbits.iter().any(|orbit| orbit.contains(&(col, row))) {
+                    orbits.push(self.orbit(col, row, size));
+                }
+            }
+        }
+        orbits
+    }
+}
+
+/// Copy the value of each orbit representative to the rest of its orbit
+pub fn apply_symmetry(kernel: &mut [f32], size: usize, mode: KernelSymmetryMode) {
+    debug_assert_eq!(kernel.len(), size * size);
+    for orbit in mode.orbits(size) {
+        let (col, row) = orbit[0];
+        let value = kernel[row * size + col];
+        for (col, row) in orbit {
+            kernel[row * size + col] = value;
+        }
+    }
+}
+
+/// Propagate the value at (col, row) to its whole orbit
+pub fn apply_symmetry_at(kernel: &mut [f32], size: usize, col: usize, row: usize, mode: KernelSymmetryMode) {
+    debug_assert_eq!(kernel.len(), size * size);
+    let value = kernel[row * size + col];
+    for (col, row) in mode.orbit(col, row, size) {
+        kernel[row * size + col] = value;
+    }
+}
+
+pub fn is_symmetric(kernel: &[f32], size: usize, mode: KernelSymmetryMode) -> bool {
+    mode.orbits(size).iter().all(|orbit| {
+        let (col, row) = orbit[0];
+        orbit.iter().all(|&(other_col, other_row)| kernel[other_row * size + other_col] == kernel[row * size + col])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZES: [usize; 3] = [3, 4, 5];
+
+    // Distinct values, so that any cell left out of a propagation shows up
+    fn distinct_kernel(size: usize) -> Vec<f32> { (0..size * size).map(|index| index as f32 + 1.0).collect() }
+
+    #[test]
+    fn orbits_partition_the_cells() {
+        for mode in KernelSymmetryMode::ALL {
+            for size in SIZES {
+                let orbits = mode.orbits(size);
+                let mut cells: Vec<(usize, usize)> = orbits.iter().flatten().copied().collect();
+                assert_eq!(cells.len(), size * size, "{} orbits of size {} overlap or miss cells", mode, size);
+                cells.sort();
+                cells.dedup();
+                assert_eq!(cells.len(), size * size, "{} orbits of size {} overlap", mode, size);
+
+                for orbit in &orbits {
+                    for &(col, row) in orbit {
+                        assert_eq!(&mode.orbit(col, row, size), orbit, "{} orbit of {:?} in size {}", mode, (col, row), size);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn apply_symmetry_is_symmetric() {
+        for mode in KernelSymmetryMode::ALL {
+            for size in SIZES {
+                let mut kernel = distinct_kernel(size);
+                apply_symmetry(&mut kernel, size, mode);
+                assert!(is_symmetric(&kernel, size, mode), "{} of size {}: {:?}", mode, size, kernel);
+            }
+        }
+    }
+
+    #[test]
+    fn apply_symmetry_at_writes_the_whole_orbit() {
+        for mode in KernelSymmetryMode::ALL {
+            for size in SIZES {
+                for row in 0..size {
+                    for col in 0..size {
+                        let mut kernel = distinct_kernel(size);
+                        kernel[row * size + col] = -1.0;
+                        apply_symmetry_at(&mut kernel, size, col, row, mode);
+
+                        let orbit = mode.orbit(col, row, size);
+                        for other_row in 0..size {
+                            for other_col in 0..size {
+                                let index = other_row * size + other_col;
+                                let expected = if orbit.contains(&(other_col, other_row)) { -1.0 } else { distinct_kernel(size)[index] };
+                                assert_eq!(kernel[index], expected, "{} at {:?} in size {}, cell {:?}", mode, (col, row), size, (other_col, other_row));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn orbits_of_the_3x3_kernel() {
+        let corners = vec![(0, 0), (2, 0), (0, 2), (2, 2)];
+        let edges = vec![(1, 0), (0, 1), (2, 1), (1, 2)];
+
+        assert_eq!(KernelSymmetryMode::Any.orbit(0, 0, 3), vec![(0, 0)]);
+        // No sixth of turn on a square kernel, see Lattice::orbits for the hexagonal one
+        assert_eq!(KernelSymmetryMode::Rotational60.orbit(0, 0, 3), vec![(0, 0)]);
+        assert_eq!(KernelSymmetryMode::Vertical.orbit(0, 1, 3), vec![(0, 1), (2, 1)]);
+        assert_eq!(KernelSymmetryMode::Vertical.orbit(1, 0, 3), vec![(1, 0)]);
+        assert_eq!(KernelSymmetryMode::Horizontal.orbit(1, 0, 3), vec![(1, 0), (1, 2)]);
+        assert_eq!(KernelSymmetryMode::Full.orbit(0, 0, 3), corners);
+        assert_eq!(KernelSymmetryMode::Full.orbit(1, 0, 3), vec![(1, 0), (1, 2)]);
+        assert_eq!(KernelSymmetryMode::Diagonal.orbit(1, 0, 3), vec![(1, 0), (0, 1)]);
+        assert_eq!(KernelSymmetryMode::Diagonal.orbit(2, 2, 3), vec![(2, 2)]);
+        assert_eq!(KernelSymmetryMode::AntiDiagonal.orbit(0, 0, 3), vec![(0, 0), (2, 2)]);
+        assert_eq!(KernelSymmetryMode::AntiDiagonal.orbit(2, 0, 3), vec![(2, 0)]);
+        assert_eq!(KernelSymmetryMode::Rotational90.orbit(0, 0, 3), corners);
+        assert_eq!(KernelSymmetryMode::Rotational90.orbit(1, 0, 3), edges);
+        assert_eq!(KernelSymmetryMode::Rotational180.orbit(1, 0, 3), vec![(1, 0), (1, 2)]);
+        assert_eq!(KernelSymmetryMode::Rotational180.orbit(2, 0, 3), vec![(2, 0), (0, 2)]);
+        for mode in KernelSymmetryMode::ALL {
+            assert_eq!(mode.orbit(1, 1, 3), vec![(1, 1)], "{} moves the center", mode);
+        }
+    }
+
+    #[test]
+    fn radial_rings() {
+        let ring_sizes = |size: usize| -> Vec<usize> { KernelSymmetryMode::Radial.orbits(size).iter().map(Vec::len).collect() };
+
+        assert_eq!(
+            KernelSymmetryMode::Radial.orbits(3),
+            vec![vec![(0, 0), (2, 0), (0, 2), (2, 2)], vec![(1, 0), (0, 1), (2, 1), (1, 2)], vec![(1, 1)]]
+        );
+        // Distances 2√2, √5, 2, √2, 1 and 0 from the center
+        let mut sizes_5 = ring_sizes(5);
+        sizes_5.sort();
+        assert_eq!(sizes_5, vec![1, 4, 4, 4, 4, 8]);
+        // Even sizes have no center cell: distances 3√2/2, √10/2 and √2/2
+        assert_eq!(ring_sizes(4), vec![4, 8, 4]);
+        assert_eq!(KernelSymmetryMode::Radial.orbit(1, 1, 4), vec![(1, 1), (2, 1), (1, 2), (2, 2)]);
+    }
+}
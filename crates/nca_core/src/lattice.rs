@@ -0,0 +1,371 @@
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+use crate::{kernel_symmetry::KernelSymmetryMode, simulation_data::KERNEL_SIZE};
+
+// Kernels of every lattice are stored in the 3x3 row-major kernel, value at (col, row) is kernel[row * KERNEL_SIZE + col].
+// On every lattice the cells are laid out as the neighbours are displayed: (1, 1) is the cell itself, the first column
+// its left neighbours (dx = col - 1) and the first row its upper neighbours. The next texture row is displayed above
+// a cell, so the rows go down the texture (dy = 1 - row).
+
+// Hexagonal lattice (odd rows shifted by half a cell to the right): two neighbours above, three cells in the middle row and two below
+const HEXAGONAL_CELLS: [(usize, usize); 7] = [(0, 0), (1, 0), (0, 1), (1, 1), (2, 1), (0, 2), (1, 2)];
+// Around the center, in turning order
+const HEXAGONAL_RING: [(usize, usize); 6] = [(0, 0), (1, 0), (2, 1), (1, 2), (0, 2), (0, 1)];
+
+// Triangular lattice: the two side neighbours in the middle row and the neighbour across the base of the triangle
+const TRIANGULAR_CELLS: [(usize, usize); 4] = [(0, 1), (1, 1), (2, 1), (1, 2)];
+
+/// Arrangement of the cells and of their neighbourhood, shared by the simulation and the display
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Lattice {
+    /// 8 neighbours
+    #[default]
+    Square,
+    /// 6 neighbours, odd rows are shifted by half a cell
+    Hexagonal,
+    /// 3 neighbours, triangles alternately point up and down
+    Triangular,
+}
+
+impl std::fmt::Display for Lattice {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Lattice::Square => write!(f, "Square"),
+            Lattice::Hexagonal => write!(f, "Hexagonal"),
+            Lattice::Triangular => write!(f, "Triangular"),
+        }
+    }
+}
+
+impl Lattice {
+    pub const ALL: [Lattice; 3] = [Lattice::Square, Lattice::Hexagonal, Lattice::Triangular];
+
+    // Must match the LATTICE_* constants of the shaders
+    pub fn shader_mode(&self) -> u32 {
+        match self {
+            Lattice::Square => 0,
+            Lattice::Hexagonal => 1,
+            Lattice::Triangular => 2,
+        }
+    }
+
+    pub fn from_shader_mode(mode: u32) -> Self {
+        match mode {
+            1 => Lattice::Hexagonal,
+            2 => Lattice::Triangular,
+            _ => Lattice::Square,
+        }
+    }
+
+    /// Whether the kernel cell weights a neighbour (or the cell itself) on this lattice
+    pub fn is_used(&self, col: usize, row: usize) -> bool {
+        match self {
+            Lattice::Square => col < KERNEL_SIZE && row < KERNEL_SIZE,
+            Lattice::Hexagonal => HEXAGONAL_CELLS.contains(&(col, row)),
+            Lattice::Triangular => TRIANGULAR_CELLS.contains(&(col, row)),
+        }
+    }
+
+    /// Displayed center of the cell stored in the texel (x, y), in texels
+    pub fn cell_center(&self, x: i32, y: i32) -> Vec2 {
+        match self {
+            Lattice::Hexagonal if y.rem_euclid(2) == 1 => Vec2::new(x as f32 + 1.0, y as f32 + 0.5),
+            _ => Vec2::new(x as f32 + 0.5, y as f32 + 0.5),
+        }
+    }
+
+    /// Texel of the cell displayed at `position` (in texels), mirrors the cellUv function of Screen.wgsl
+    pub fn cell_at(&self, position: Vec2) -> [i32; 2] {
+        match self {
+            Lattice::Square => [position.x.floor() as i32, position.y.floor() as i32],
+            Lattice::Hexagonal => {
+                // Nearest center among the rows around the position
+                let row = position.y.floor() as i32;
+                (row - 1..=row + 1)
+                    .map(|row| {
+                        let shift = 0.5 * row.rem_euclid(2) as f32;
+                        [(position.x - shift).floor() as i32, row]
+                    })
+                    .min_by(|a, b| position.distance(self.cell_center(a[0], a[1])).total_cmp(&position.distance(self.cell_center(b[0], b[1]))))
+                    .expect("There are always three candidate rows")
+            },
+            Lattice::Triangular => {
+                let row = position.y.floor() as i32;
+                let height = position.y - position.y.floor();
+                let column = position.x.floor() as i32;
+                let x = (column - 1..=column + 1)
+                    .find(|&x| {
+                        let half_width = if (x + row) & 1 == 0 { height } else { 1.0 - height };
+                        (position.x - (x as f32 + 0.5)).abs() <= half_width
+                    })
+                    .unwrap_or(column);
+                [x, row]
+            },
+        }
+    }
+
+    /// Offset of the texel weighted by the kernel cell (col, row) for the cell (x, y), mirrors the sums of simulationBase.wgsl
+    pub fn neighbour_offset(&self, col: usize, row: usize, x: i32, y: i32) -> Option<[i32; 2]> {
+        if !self.is_used(col, row) {
+            return None;
+        }
+        let (col, row) = (col as i32, row as i32);
+        match self {
+            Lattice::Square => Some([col - 1, 1 - row]),
+            Lattice::Hexagonal => {
+                let shift = y & 1;
+                match row {
+                    1 => Some([col - 1, 0]),
+                    _ => Some([shift - 1 + col, 1 - row]),
+                }
+            },
+            Lattice::Triangular => match row {
+                1 => Some([col - 1, 0]),
+                _ => Some([0, if (x + y) & 1 == 0 { 1 } else { -1 }]),
+            },
+        }
+    }
+
+    /// Size of the grid simulated for the requested `size`. The hexagonal rows alternate their shift and the triangles
+    /// their orientation, the dimensions along which they alternate are rounded up to even so that the lattice does not tear
+    /// where the grid wraps.
+    pub fn grid_size(&self, size: [u32; 2]) -> [u32; 2] {
+        let [width, height] = size;
+        match self {
+            Lattice::Square => size,
+            Lattice::Hexagonal => [width, height.next_multiple_of(2)],
+            Lattice::Triangular => [width.next_multiple_of(2), height.next_multiple_of(2)],
+        }
+    }
+
+    /// Horizontal shift of a row of the kernel editor, in cells
+    pub fn row_indent(&self, row: usize) -> f32 {
+        match (self, row) {
+            (Lattice::Hexagonal, 0 | 2) => 0.5,
+            (Lattice::Triangular, 2) => 1.0,
+            _ => 0.0,
+        }
+    }
+
+    /// Zero the kernel cells without neighbour on this lattice
+    pub fn mask(&self, kernel: &mut [f32; KERNEL_SIZE * KERNEL_SIZE]) {
+        for row in 0..KERNEL_SIZE {
+            for col in 0..KERNEL_SIZE {
+                if !self.is_used(col, row) {
+                    kernel[row * KERNEL_SIZE + col] = 0.0;
+                }
+            }
+        }
+    }
+
+    /// Symmetry modes matching the geometry of the lattice
+    pub fn symmetry_modes(&self) -> &'static [KernelSymmetryMode] {
+        match self {
+            Lattice::Square => &[
+                KernelSymmetryMode::Any,
+                KernelSymmetryMode::Vertical,
+                KernelSymmetryMode::Horizontal,
+                KernelSymmetryMode::Full,
+                KernelSymmetryMode::Diagonal,
+                KernelSymmetryMode::AntiDiagonal,
+                KernelSymmetryMode::Rotational90,
+                KernelSymmetryMode::Rotational180,
+                KernelSymmetryMode::Radial,
+            ],
+            Lattice::Hexagonal => &[
+                KernelSymmetryMode::Any,
+                KernelSymmetryMode::Vertical,
+                KernelSymmetryMode::Horizontal,
+                KernelSymmetryMode::Full,
+                KernelSymmetryMode::Rotational60,
+                KernelSymmetryMode::Rotational180,
+                KernelSymmetryMode::Radial,
+            ],
+            Lattice::Triangular => &[KernelSymmetryMode::Any, KernelSymmetryMode::Vertical, KernelSymmetryMode::Radial],
+        }
+    }
+
+    /// Partition of the used kernel cells in groups sharing the same value, each sorted in row-major order.
+    /// Modes that do not match the lattice leave every cell alone.
+    pub fn orbits(&self, mode: KernelSymmetryMode) -> Vec<Vec<(usize, usize)>> {
+        let ring = HEXAGONAL_RING;
+        let mut orbits: Vec<Vec<(usize, usize)>> = match (self, mode) {
+            (Lattice::Square, _) => return mode.orbits(KERNEL_SIZE),
+            (Lattice::Hexagonal, KernelSymmetryMode::Vertical) => vec![vec![ring[0], ring[1]], vec![ring[5], ring[2]], vec![ring[4], ring[3]]],
+            (Lattice::Hexagonal, KernelSymmetryMode::Horizontal) => vec![vec![ring[0], ring[4]], vec![ring[1], ring[3]]],
+            (Lattice::Hexagonal, KernelSymmetryMode::Full) => vec![vec![ring[0], ring[1], ring[3], ring[4]], vec![ring[2], ring[5]]],
+            (Lattice::Hexagonal, KernelSymmetryMode::Rotational180) => vec![vec![ring[0], ring[3]], vec![ring[1], ring[4]], vec![ring[2], ring[5]]],
+            (Lattice::Hexagonal, KernelSymmetryMode::Rotational60 | KernelSymmetryMode::Radial) => vec![ring.to_vec()],
+            (Lattice::Triangular, KernelSymmetryMode::Vertical) => vec![vec![(0, 1), (2, 1)]],
+            (Lattice::Triangular, KernelSymmetryMode::Radial) => vec![vec![(0, 1), (2, 1), (1, 2)]],
+            _ => Vec::new(),
+        };
+
+        // The remaining used cells are alone in their orbit
+        let cells: &[(usize, usize)] = if *self == Lattice::Hexagonal { &HEXAGONAL_CELLS } else { &TRIANGULAR_CELLS };
+        for cell in cells {
+            if !orbits.iter().any(|orbit| orbit.contains(cell)) {
+                orbits.push(vec![*cell]);
+            }
+        }
+
+        for orbit in orbits.iter_mut() {
+            orbit.sort_by_key(|&(col, row)| (row, col));
+        }
+        orbits.sort_by_key(|orbit| (orbit[0].1, orbit[0].0));
+        orbits
+    }
+
+    /// All the cells sharing their value with (col, row), an unused cell is alone
+    pub fn orbit(&self, mode: KernelSymmetryMode, col: usize, row: usize) -> Vec<(usize, usize)> {
+        self.orbits(mode).into_iter().find(|orbit| orbit.contains(&(col, row))).unwrap_or_else(|| vec![(col, row)])
+    }
+
+    /// Copy the value of each orbit representative to the rest of its orbit
+    pub fn apply_symmetry(&self, kernel: &mut [f32; KERNEL_SIZE * KERNEL_SIZE], mode: KernelSymmetryMode) {
+        for orbit in self.orbits(mode) {
+            let (col, row) = orbit[0];
+            let value = kernel[row * KERNEL_SIZE + col];
+            for (col, row) in orbit {
+                kernel[row * KERNEL_SIZE + col] = value;
+            }
+        }
+    }
+
+    /// Propagate the value at (col, row) to its whole orbit
+    pub fn apply_symmetry_at(&self, kernel: &mut [f32; KERNEL_SIZE * KERNEL_SIZE], col: usize, row: usize, mode: KernelSymmetryMode) {
+        let value = kernel[row * KERNEL_SIZE + col];
+        for (col, row) in self.orbit(mode, col, row) {
+            kernel[row * KERNEL_SIZE + col] = value;
+        }
+    }
+
+    pub fn is_symmetric(&self, kernel: &[f32; KERNEL_SIZE * KERNEL_SIZE], mode: KernelSymmetryMode) -> bool {
+        self.orbits(mode).iter().all(|orbit| {
+            let (col, row) = orbit[0];
+            orbit.iter().all(|&(other_col, other_row)| kernel[other_row * KERNEL_SIZE + other_col] == kernel[row * KERNEL_SIZE + col])
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KERNEL_CELLS: usize = KERNEL_SIZE * KERNEL_SIZE;
+
+    fn used_cells(lattice: Lattice) -> Vec<(usize, usize)> {
+        (0..KERNEL_SIZE).flat_map(|row| (0..KERNEL_SIZE).map(move |col| (col, row))).filter(|&(col, row)| lattice.is_used(col, row)).collect()
+    }
+
+    fn distinct_kernel() -> [f32; KERNEL_CELLS] { std::array::from_fn(|index| index as f32 + 1.0) }
+
+    #[test]
+    fn orbits_partition_the_used_cells() {
+        for lattice in Lattice::ALL {
+            for &mode in lattice.symmetry_modes() {
+                let mut cells: Vec<(usize, usize)> = lattice.orbits(mode).into_iter().flatten().collect();
+                cells.sort_by_key(|&(col, row)| (row, col));
+                assert_eq!(cells, used_cells(lattice), "{} orbits of {}", mode, lattice);
+            }
+        }
+    }
+
+    #[test]
+    fn apply_symmetry_is_symmetric() {
+        for lattice in Lattice::ALL {
+            for &mode in lattice.symmetry_modes() {
+                let mut kernel = distinct_kernel();
+                lattice.apply_symmetry(&mut kernel, mode);
+                assert!(lattice.is_symmetric(&kernel, mode), "{} on {}: {:?}", mode, lattice, kernel);
+            }
+        }
+    }
+
+    #[test]
+    fn apply_symmetry_at_writes_the_whole_orbit() {
+        for lattice in Lattice::ALL {
+            for &mode in lattice.symmetry_modes() {
+                for (col, row) in used_cells(lattice) {
+                    let mut kernel = distinct_kernel();
+                    kernel[row * KERNEL_SIZE + col] = -1.0;
+                    lattice.apply_symmetry_at(&mut kernel, col, row, mode);
+
+                    let orbit = lattice.orbit(mode, col, row);
+                    for (index, value) in kernel.iter().enumerate() {
+                        let cell = (index % KERNEL_SIZE, index / KERNEL_SIZE);
+                        let expected = if orbit.contains(&cell) { -1.0 } else { distinct_kernel()[index] };
+                        assert_eq!(*value, expected, "{} on {} at {:?}, cell {:?}", mode, lattice, (col, row), cell);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn kernel_layout_is_shared_by_the_lattices() {
+        for lattice in Lattice::ALL {
+            for (col, row) in used_cells(lattice) {
+                for (x, y) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let [dx, dy] = lattice.neighbour_offset(col, row, x, y).unwrap();
+                    match (lattice, row) {
+                        // The side neighbours, and the cell itself
+                        (_, 1) => assert_eq!([dx, dy], [col as i32 - 1, 0], "{} at {:?}", lattice, (col, row)),
+                        // The base of the triangle is above or below depending on its orientation
+                        (Lattice::Triangular, _) => assert_eq!(dx, 0),
+                        _ => {
+                            assert_eq!(dy, 1 - row as i32, "{} at {:?}", lattice, (col, row));
+                            assert!((dx - (col as i32 - 1)).abs() <= 1, "{} at {:?}", lattice, (col, row));
+                        },
+                    }
+                }
+            }
+        }
+    }
+
+    // Whether every cell of the wrapping grid is a neighbour of its own neighbours
+    fn is_neighbourhood_symmetric(lattice: Lattice, size: [u32; 2]) -> bool {
+        let wrap = |[x, y]: [i32; 2]| [x.rem_euclid(size[0] as i32), y.rem_euclid(size[1] as i32)];
+        let neighbours = |x: i32, y: i32| -> Vec<[i32; 2]> {
+            used_cells(lattice).into_iter().filter_map(|(col, row)| lattice.neighbour_offset(col, row, x, y)).map(|[dx, dy]| wrap([x + dx, y + dy])).collect()
+        };
+        (0..size[1] as i32).all(|y| (0..size[0] as i32).all(|x| neighbours(x, y).into_iter().all(|[nx, ny]| neighbours(nx, ny).contains(&[x, y]))))
+    }
+
+    #[test]
+    fn grid_size_keeps_the_lattice_whole_across_the_seams() {
+        for lattice in Lattice::ALL {
+            for size in [[7, 5], [8, 6], [5, 8]] {
+                let grid_size = lattice.grid_size(size);
+                assert!(grid_size[0] >= size[0] && grid_size[1] >= size[1]);
+                assert!(is_neighbourhood_symmetric(lattice, grid_size), "{} on a {:?} grid", lattice, grid_size);
+            }
+        }
+        assert_eq!(Lattice::Hexagonal.grid_size([7, 5]), [7, 6]);
+        assert_eq!(Lattice::Triangular.grid_size([7, 5]), [8, 6]);
+        // Odd hexagonal rows on both sides of the seam
+        assert!(!is_neighbourhood_symmetric(Lattice::Hexagonal, [7, 5]));
+    }
+
+    #[test]
+    fn hexagonal_rotations() {
+        let ring = HEXAGONAL_RING.to_vec();
+        let mut sorted_ring = ring.clone();
+        sorted_ring.sort_by_key(|&(col, row)| (row, col));
+        assert_eq!(Lattice::Hexagonal.orbits(KernelSymmetryMode::Rotational60), vec![sorted_ring.clone(), vec![(1, 1)]]);
+        assert_eq!(Lattice::Hexagonal.orbits(KernelSymmetryMode::Radial), vec![sorted_ring, vec![(1, 1)]]);
+
+        // A half turn pairs opposite neighbours of the ring
+        for (index, &cell) in ring.iter().enumerate() {
+            let opposite = ring[(index + 3) % 6];
+            let mut expected = vec![cell, opposite];
+            expected.sort_by_key(|&(col, row)| (row, col));
+            assert_eq!(Lattice::Hexagonal.orbit(KernelSymmetryMode::Rotational180, cell.0, cell.1), expected);
+        }
+
+        // The modes of the other lattices leave every cell alone
+        assert!(Lattice::Hexagonal.orbits(KernelSymmetryMode::Rotational90).iter().all(|orbit| orbit.len() == 1));
+        assert!(Lattice::Square.orbits(KernelSymmetryMode::Rotational60).iter().all(|orbit| orbit.len() == 1));
+    }
+}
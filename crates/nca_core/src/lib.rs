@@ -0,0 +1,58 @@
+#[macro_use]
+extern crate lazy_static;
+
+pub mod aspect_mode;
+pub mod boundary_condition;
+pub mod cell_probe;
+pub mod color_map;
+pub mod cpu_activation;
+pub mod cpu_simulation;
+pub mod kernel_format;
+pub mod kernel_randomizer;
+pub mod kernel_symmetry;
+pub mod lattice;
+pub mod pipeline_helpers;
+pub mod post_process;
+pub mod preset;
+pub mod simulation_data;
+pub mod statistics;
+pub mod view_data;
+mod simulation;
+
+use serde::{Deserialize, Serialize};
+
+pub use oxyde::wgpu;
+
+pub use aspect_mode::AspectMode;
+pub use boundary_condition::BoundaryCondition;
+pub use color_map::{ColorMap, ColorMapKind};
+pub use cpu_simulation::CpuSimulation;
+pub use lattice::Lattice;
+pub use post_process::{PostEffect, PostProcessing, PostProcessor};
+pub use preset::Preset;
+pub use simulation::{read_texture, read_texture_bytes, ResizedTextures, Simulation, StateReadback, StateSnapshot};
+pub use simulation_data::InitSimulationUniforms;
+pub use view_data::{ViewParameters, MAX_TILED_ZOOM_LEVEL};
+
+pub const INIT_SIMULATION_SHADER: &str = include_str!("shaders/init_simulation.wgsl");
+
+/// Which generations of the ping pong textures are displayed
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+pub enum DisplayFramesMode {
+    All,
+    Evens,
+    Odd,
+}
+
+pub fn generate_simulation_shader(activation_code: &str) -> String {
+    include_str!("shaders/simulationBase.wgsl").replace("[functionTemplate]", activation_code)
+}
+
+/// Display through the color map of the view, `colorMap` and the other functions of Screen.wgsl can be called from the display code
+pub const DEFAULT_DISPLAY_CODE: &str = "fn colorize(state: vec4<f32>, uv: vec2<f32>) -> vec4<f32> {
+    return vec4<f32>(colorMap(state.x), 1.0);
+}";
+
+pub fn generate_screen_shader(display_code: &str) -> String {
+    include_str!("shaders/Screen.wgsl").replace("[colorizeTemplate]", display_code)
+}
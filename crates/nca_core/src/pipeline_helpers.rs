@@ -1,11 +1,24 @@
-use oxyde::wgpu_utils::PingPongTexture;
 use oxyde::wgpu as wgpu;
 
-use super::{
+use crate::{
+    boundary_condition::BoundaryCondition,
     simulation_data::{InitSimulationData, SimulationData},
     view_data::ViewData,
 };
 
+pub const SIMULATION_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Bgra8Unorm;
+
+/// Run `f` in a validation error scope: the first validation error (an invalid shader, a too large texture...)
+/// is returned instead of reaching the uncaptured error handler of the device
+pub fn capture_validation_error<T, F: FnOnce() -> T>(device: &wgpu::Device, f: F) -> Result<T, wgpu::Error> {
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let value = f();
+    match pollster::block_on(device.pop_error_scope()) {
+        Some(error) => Err(error),
+        None => Ok(value),
+    }
+}
+
 pub fn get_texture_descriptor(size: &[u32; 2]) -> wgpu::TextureDescriptor<'static> {
     let width = size[0];
     let height = size[1];
@@ -18,30 +31,44 @@ pub fn get_texture_descriptor(size: &[u32; 2]) -> wgpu::TextureDescriptor<'stati
         mip_level_count: 1,
         sample_count: 1,
         dimension: wgpu::TextureDimension::D2,
-        format: wgpu::TextureFormat::Bgra8Unorm,
-        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        format: SIMULATION_TEXTURE_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::COPY_DST,
         label: None,
         view_formats: &[wgpu::TextureFormat::Bgra8UnormSrgb],
     }
 }
 
-pub fn get_simulation_textures_and_bind_groups(
-    device: &mut wgpu::Device,
-    texture_descriptor: &wgpu::TextureDescriptor,
-) -> Result<(PingPongTexture, wgpu::BindGroup, wgpu::BindGroup, wgpu::BindGroup, wgpu::BindGroup), wgpu::Error> {
-    let simulation_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-        address_mode_u: wgpu::AddressMode::Repeat,
-        address_mode_v: wgpu::AddressMode::Repeat,
-        address_mode_w: wgpu::AddressMode::Repeat,
-        mag_filter: wgpu::FilterMode::Linear,
-        min_filter: wgpu::FilterMode::Linear,
+pub fn fullscreen_primitive_state() -> wgpu::PrimitiveState {
+    wgpu::PrimitiveState {
+        topology: wgpu::PrimitiveTopology::TriangleList,
+        strip_index_format: None,
+        front_face: wgpu::FrontFace::Ccw,
+        cull_mode: None,
+        polygon_mode: wgpu::PolygonMode::Fill,
+        ..Default::default()
+    }
+}
+
+// Addressing outside of the grid follows the boundary condition.
+// The simulation shader loads exact texels, the sampler is only there for the activation functions.
+pub fn create_simulation_sampler(device: &wgpu::Device, boundary_condition: BoundaryCondition) -> wgpu::Sampler {
+    let address_mode = boundary_condition.address_mode();
+    device.create_sampler(&wgpu::SamplerDescriptor {
+        address_mode_u: address_mode,
+        address_mode_v: address_mode,
+        address_mode_w: address_mode,
+        mag_filter: wgpu::FilterMode::Nearest,
+        min_filter: wgpu::FilterMode::Nearest,
         mipmap_filter: wgpu::FilterMode::Nearest,
         lod_min_clamp: 0.0,
         lod_max_clamp: 0.0,
         ..Default::default()
-    });
+    })
+}
 
-    let display_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+// Nearest texel lookup used to display the simulation
+pub fn create_display_sampler(device: &wgpu::Device) -> wgpu::Sampler {
+    device.create_sampler(&wgpu::SamplerDescriptor {
         address_mode_u: wgpu::AddressMode::ClampToEdge,
         address_mode_v: wgpu::AddressMode::ClampToEdge,
         address_mode_w: wgpu::AddressMode::ClampToEdge,
@@ -51,37 +78,72 @@ pub fn get_simulation_textures_and_bind_groups(
         lod_min_clamp: 0.0,
         lod_max_clamp: 0.0,
         ..Default::default()
-    });
-
-    let simulation_textures = PingPongTexture::from_descriptor(device, &texture_descriptor, Some("simulation"))?;
+    })
+}
 
-    let (bind_group_display_ping, bind_group_display_pong) = simulation_textures.create_binding_group(device, &display_sampler);
-    let (bind_group_simulation_ping, bind_group_simulation_pong) = simulation_textures.create_binding_group(device, &simulation_sampler);
+// Texture and sampler layout expected at group 0 by the simulation and screen shaders
+pub fn create_texture_bind_group_layout(device: &wgpu::Device, label: Option<&str>) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ],
+        label,
+    })
+}
 
-    Ok((
-        simulation_textures,
-        bind_group_display_ping,
-        bind_group_display_pong,
-        bind_group_simulation_ping,
-        bind_group_simulation_pong,
-    ))
+pub fn create_texture_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    view: &wgpu::TextureView,
+    sampler: &wgpu::Sampler,
+    label: Option<&str>,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+        ],
+        label,
+    })
 }
 
 pub fn build_simulation_pipeline(
-    device: &mut wgpu::Device,
-    surface_configuration: &wgpu::SurfaceConfiguration,
+    device: &wgpu::Device,
+    target_format: wgpu::TextureFormat,
     primitive_state: &wgpu::PrimitiveState,
     multisample_state: &wgpu::MultisampleState,
     screen_shader: &wgpu::ShaderModule,
     simulation_shader: &wgpu::ShaderModule,
-    simulation_textures: &PingPongTexture,
+    texture_bind_group_layout: &wgpu::BindGroupLayout,
     simulation_data: &SimulationData,
 ) -> wgpu::RenderPipeline {
     device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
         label: Some("Simulation Render Pipeline"),
         layout: Some(&device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("Simulation Pipeline Layout"),
-            bind_group_layouts: &[&simulation_textures.bind_group_layout.layout, &simulation_data.bind_group_layout],
+            bind_group_layouts: &[texture_bind_group_layout, &simulation_data.bind_group_layout],
             push_constant_ranges: &[],
         })),
         vertex: wgpu::VertexState {
@@ -94,7 +156,7 @@ pub fn build_simulation_pipeline(
             entry_point: "fs_main",
             targets: &[
                 Some(wgpu::ColorTargetState {
-                    format: surface_configuration.format,
+                    format: target_format,
                     blend: Some(wgpu::BlendState::REPLACE),
                     write_mask: wgpu::ColorWrites::ALL,
                 })
@@ -108,19 +170,19 @@ pub fn build_simulation_pipeline(
 }
 
 pub fn build_screen_pipeline(
-    device: &mut wgpu::Device,
-    surface_configuration: &wgpu::SurfaceConfiguration,
+    device: &wgpu::Device,
+    target_format: wgpu::TextureFormat,
     primitive_state: &wgpu::PrimitiveState,
     multisample_state: &wgpu::MultisampleState,
     screen_shader: &wgpu::ShaderModule,
-    simulation_textures: &PingPongTexture,
+    texture_bind_group_layout: &wgpu::BindGroupLayout,
     view_data: &ViewData,
 ) -> wgpu::RenderPipeline {
     device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
         label: Some("Screen Render Pipeline"),
         layout: Some(&device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("Screen Pipeline Layout"),
-            bind_group_layouts: &[&simulation_textures.bind_group_layout.layout, &view_data.bind_group_layout],
+            bind_group_layouts: &[texture_bind_group_layout, &view_data.bind_group_layout],
             push_constant_ranges: &[],
         })),
         vertex: wgpu::VertexState {
@@ -133,7 +195,7 @@ pub fn build_screen_pipeline(
             entry_point: "fs_main",
             targets: &[
                 Some(wgpu::ColorTargetState {
-                    format: surface_configuration.format,
+                    format: target_format,
                     blend: Some(wgpu::BlendState::REPLACE),
                     write_mask: wgpu::ColorWrites::ALL,
                 })
@@ -147,8 +209,8 @@ pub fn build_screen_pipeline(
 }
 
 pub fn build_init_simulation_pipeline(
-    device: &mut wgpu::Device,
-    surface_configuration: &wgpu::SurfaceConfiguration,
+    device: &wgpu::Device,
+    target_format: wgpu::TextureFormat,
     primitive_state: &wgpu::PrimitiveState,
     multisample_state: &wgpu::MultisampleState,
     screen_shader: &wgpu::ShaderModule,
@@ -172,7 +234,7 @@ pub fn build_init_simulation_pipeline(
             entry_point: "fs_main",
             targets: &[
                 Some(wgpu::ColorTargetState {
-                    format: surface_configuration.format,
+                    format: target_format,
                     blend: Some(wgpu::BlendState::REPLACE),
                     write_mask: wgpu::ColorWrites::ALL,
                 })
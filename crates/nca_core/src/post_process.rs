@@ -0,0 +1,632 @@
+use crevice::std140::{AsStd140, Std140};
+use glam::{Vec2, Vec4};
+use oxyde::wgpu as wgpu;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "egui")]
+use oxyde::egui as egui;
+
+use crate::pipeline_helpers::fullscreen_primitive_state;
+
+/// Format of the intermediate images, precise enough for the trails to fade out completely
+pub const POST_PROCESS_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Effect of the post processing stack, applied in order to the displayed image
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub enum PostEffect {
+    /// Exponential moving average of the displayed frames, `persistence` is the weight of the previous ones
+    Trails { persistence: f32 },
+    /// Gaussian blur, `radius` in screen pixels
+    Blur { radius: f32 },
+    /// Glow of the colors brighter than `threshold`
+    Bloom { threshold: f32, intensity: f32, radius: f32 },
+    /// Edges of the luminance drawn with `color`
+    EdgeHighlight { strength: f32, color: [f32; 3] },
+    /// Darkens the image from `radius` to `radius + softness`, as fractions of the half diagonal
+    Vignette { strength: f32, radius: f32, softness: f32 },
+}
+
+impl std::fmt::Display for PostEffect {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PostEffect::Trails { .. } => write!(f, "Trails"),
+            PostEffect::Blur { .. } => write!(f, "Blur"),
+            PostEffect::Bloom { .. } => write!(f, "Bloom"),
+            PostEffect::EdgeHighlight { .. } => write!(f, "Edge highlight"),
+            PostEffect::Vignette { .. } => write!(f, "Vignette"),
+        }
+    }
+}
+
+impl PostEffect {
+    pub const DEFAULTS: [PostEffect; 5] = [
+        PostEffect::Trails { persistence: 0.8 },
+        PostEffect::Blur { radius: 2.0 },
+        PostEffect::Bloom { threshold: 0.6, intensity: 1.0, radius: 12.0 },
+        PostEffect::EdgeHighlight { strength: 1.0, color: [1.0, 1.0, 1.0] },
+        PostEffect::Vignette { strength: 0.6, radius: 0.5, softness: 0.6 },
+    ];
+
+    /// Names of the scalar parameters, in the order of `parameter_mut`
+    pub fn parameter_names(&self) -> &'static [&'static str] {
+        match self {
+            PostEffect::Trails { .. } => &["persistence"],
+            PostEffect::Blur { .. } => &["radius"],
+            PostEffect::Bloom { .. } => &["threshold", "intensity", "radius"],
+            PostEffect::EdgeHighlight { .. } => &["strength"],
+            PostEffect::Vignette { .. } => &["strength", "radius", "softness"],
+        }
+    }
+
+    pub fn parameter_mut(&mut self, index: usize) -> Option<&mut f32> {
+        match (self, index) {
+            (PostEffect::Trails { persistence }, 0) => Some(persistence),
+            (PostEffect::Blur { radius }, 0) => Some(radius),
+            (PostEffect::Bloom { threshold, .. }, 0) => Some(threshold),
+            (PostEffect::Bloom { intensity, .. }, 1) => Some(intensity),
+            (PostEffect::Bloom { radius, .. }, 2) => Some(radius),
+            (PostEffect::EdgeHighlight { strength, .. }, 0) => Some(strength),
+            (PostEffect::Vignette { strength, .. }, 0) => Some(strength),
+            (PostEffect::Vignette { radius, .. }, 1) => Some(radius),
+            (PostEffect::Vignette { softness, .. }, 2) => Some(softness),
+            _ => None,
+        }
+    }
+
+    pub fn parameter(&self, index: usize) -> Option<f32> {
+        let mut effect = *self;
+        effect.parameter_mut(index).copied()
+    }
+
+    #[cfg(feature = "egui")]
+    pub fn ui_control(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed: bool = false;
+
+        ui.horizontal(|ui| match self {
+            PostEffect::Trails { persistence } => {
+                changed |= ui.add(egui::DragValue::new(persistence).speed(0.005).range(0.0..=0.99).prefix("persistence: ")).changed();
+            },
+            PostEffect::Blur { radius } => {
+                changed |= ui.add(egui::DragValue::new(radius).speed(0.1).range(0.0..=64.0).prefix("radius: ")).changed();
+            },
+            PostEffect::Bloom { threshold, intensity, radius } => {
+                changed |= ui.add(egui::DragValue::new(threshold).speed(0.01).range(0.0..=1.0).prefix("threshold: ")).changed();
+                changed |= ui.add(egui::DragValue::new(intensity).speed(0.01).range(0.0..=10.0).prefix("intensity: ")).changed();
+                changed |= ui.add(egui::DragValue::new(radius).speed(0.1).range(0.0..=64.0).prefix("radius: ")).changed();
+            },
+            PostEffect::EdgeHighlight { strength, color } => {
+                changed |= ui.add(egui::DragValue::new(strength).speed(0.01).range(0.0..=10.0).prefix("strength: ")).changed();
+                changed |= egui::color_picker::color_edit_button_rgb(ui, color).changed();
+            },
+            PostEffect::Vignette { strength, radius, softness } => {
+                changed |= ui.add(egui::DragValue::new(strength).speed(0.01).range(0.0..=1.0).prefix("strength: ")).changed();
+                changed |= ui.add(egui::DragValue::new(radius).speed(0.01).range(0.0..=1.5).prefix("radius: ")).changed();
+                changed |= ui.add(egui::DragValue::new(softness).speed(0.01).range(0.01..=1.5).prefix("softness: ")).changed();
+            },
+        });
+
+        changed
+    }
+}
+
+/// Effects applied to the display after the screen pass, saved in the presets
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct PostProcessing {
+    pub enabled: bool,
+    pub effects: Vec<PostEffect>,
+}
+
+impl PostProcessing {
+    pub fn is_active(&self) -> bool { self.enabled && !self.effects.is_empty() }
+
+    #[cfg(feature = "egui")]
+    pub fn ui_control(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed: bool = ui.checkbox(&mut self.enabled, "Enabled").changed();
+
+        let effect_count = self.effects.len();
+        let mut moved: Option<(usize, usize)> = None;
+        let mut removed: Option<usize> = None;
+        for (index, effect) in self.effects.iter_mut().enumerate() {
+            ui.push_id(index, |ui| {
+                ui.group(|ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(effect.to_string());
+                        if ui.add_enabled(index > 0, egui::Button::new("⏶")).on_hover_text("Apply earlier").clicked() {
+                            moved = Some((index, index - 1));
+                        }
+                        if ui.add_enabled(index + 1 < effect_count, egui::Button::new("⏷")).on_hover_text("Apply later").clicked() {
+                            moved = Some((index, index + 1));
+                        }
+                        if ui.button("🗑").on_hover_text("Remove the effect").clicked() {
+                            removed = Some(index);
+                        }
+                    });
+                    changed |= effect.ui_control(ui);
+                });
+            });
+        }
+
+        if let Some((from, to)) = moved {
+            self.effects.swap(from, to);
+            changed = true;
+        }
+        if let Some(index) = removed {
+            self.effects.remove(index);
+            changed = true;
+        }
+
+        ui.menu_button("Add effect", |ui| {
+            for effect in PostEffect::DEFAULTS {
+                if ui.button(effect.to_string()).clicked() {
+                    self.effects.push(effect);
+                    changed = true;
+                    ui.close_menu();
+                }
+            }
+        });
+
+        changed
+    }
+}
+
+// Parameters of one pass, see PostParameters in post_process.wgsl
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, AsStd140)]
+struct PostPassParameters {
+    color: Vec4,
+    texel_size: Vec2,
+    direction: Vec2,
+    strength: f32,
+    radius: f32,
+    threshold: f32,
+    softness: f32,
+}
+
+#[derive(Debug, Copy, Clone)]
+enum PostPipeline {
+    Trails,
+    Blur,
+    BloomBright,
+    BloomCombine,
+    Edges,
+    Vignette,
+}
+
+impl PostPipeline {
+    const ALL: [PostPipeline; 6] = [
+        PostPipeline::Trails,
+        PostPipeline::Blur,
+        PostPipeline::BloomBright,
+        PostPipeline::BloomCombine,
+        PostPipeline::Edges,
+        PostPipeline::Vignette,
+    ];
+
+    fn entry_point(&self) -> &'static str {
+        match self {
+            PostPipeline::Trails => "fs_trails",
+            PostPipeline::Blur => "fs_blur",
+            PostPipeline::BloomBright => "fs_bloom_bright",
+            PostPipeline::BloomCombine => "fs_bloom_combine",
+            PostPipeline::Edges => "fs_edges",
+            PostPipeline::Vignette => "fs_vignette",
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum PassTexture {
+    Scene,
+    Ping(usize),
+    Temporary,
+    History(usize),
+}
+
+struct PostPass {
+    pipeline: PostPipeline,
+    input: PassTexture,
+    auxiliary: PassTexture,
+    output: PassTexture,
+    parameters: PostPassParameters,
+    /// Index of the trails history receiving a copy of the output
+    history: Option<usize>,
+}
+
+impl PostPass {
+    fn new(pipeline: PostPipeline, input: PassTexture, auxiliary: PassTexture, output: PassTexture, parameters: PostPassParameters) -> Self {
+        Self { pipeline, input, auxiliary, output, parameters, history: None }
+    }
+}
+
+struct PostTexture {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+}
+
+impl PostTexture {
+    fn new(device: &wgpu::Device, size: [u32; 2], format: wgpu::TextureFormat, usage: wgpu::TextureUsages, label: &str) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: size[0],
+                height: size[1],
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Self { texture, view }
+    }
+}
+
+// Images of one output size, the histories of the trails are kept from one frame to the next
+struct PostTargets {
+    size: [u32; 2],
+    scene: PostTexture,
+    ping: [PostTexture; 2],
+    temporary: PostTexture,
+    histories: Vec<PostTexture>,
+}
+
+impl PostTargets {
+    fn new(device: &wgpu::Device, size: [u32; 2], scene_format: wgpu::TextureFormat) -> Self {
+        let intermediate = |label: &str| {
+            PostTexture::new(
+                device,
+                size,
+                POST_PROCESS_TEXTURE_FORMAT,
+                wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_SRC,
+                label,
+            )
+        };
+
+        Self {
+            size,
+            scene: PostTexture::new(
+                device,
+                size,
+                scene_format,
+                wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+                "Post process scene texture",
+            ),
+            ping: [intermediate("Post process texture 0"), intermediate("Post process texture 1")],
+            temporary: intermediate("Post process temporary texture"),
+            histories: Vec::new(),
+        }
+    }
+
+    fn view(&self, texture: PassTexture) -> &wgpu::TextureView {
+        match texture {
+            PassTexture::Scene => &self.scene.view,
+            PassTexture::Ping(index) => &self.ping[index].view,
+            PassTexture::Temporary => &self.temporary.view,
+            PassTexture::History(index) => &self.histories[index].view,
+        }
+    }
+}
+
+/// Fullscreen passes applying a PostProcessing stack to the image rendered by the screen pass into `scene_view`
+pub struct PostProcessor {
+    output_format: wgpu::TextureFormat,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    // Indexed by PostPipeline
+    pipelines: Vec<wgpu::RenderPipeline>,
+    output_pipeline: wgpu::RenderPipeline,
+    parameters_buffer: wgpu::Buffer,
+    parameters_stride: wgpu::BufferAddress,
+    parameters_capacity: usize,
+    targets: Option<PostTargets>,
+}
+
+impl PostProcessor {
+    /// `output_format` is the format of the screen pass and of the view given to `encode`
+    pub fn new(device: &wgpu::Device, output_format: wgpu::TextureFormat) -> Self {
+        let texture_entry = |binding: u32| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                texture_entry(0),
+                texture_entry(1),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<Std140PostPassParameters>() as u64),
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("Post process bind group layout"),
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            lod_min_clamp: 0.0,
+            lod_max_clamp: 0.0,
+            ..Default::default()
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Post process Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/post_process.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Post process Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let build_pipeline = |entry_point: &str, format: wgpu::TextureFormat| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Post process Render Pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point,
+                    targets: &[
+                        Some(wgpu::ColorTargetState {
+                            format,
+                            blend: Some(wgpu::BlendState::REPLACE),
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })
+                    ],
+                }),
+                primitive: fullscreen_primitive_state(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+
+        let pipelines = PostPipeline::ALL.iter().map(|pipeline| build_pipeline(pipeline.entry_point(), POST_PROCESS_TEXTURE_FORMAT)).collect();
+        let output_pipeline = build_pipeline("fs_output", output_format);
+
+        let alignment = device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+        let parameters_stride = (std::mem::size_of::<Std140PostPassParameters>() as wgpu::BufferAddress).div_ceil(alignment) * alignment;
+        let parameters_capacity = 8;
+
+        Self {
+            output_format,
+            bind_group_layout,
+            sampler,
+            pipelines,
+            output_pipeline,
+            parameters_buffer: Self::create_parameters_buffer(device, parameters_stride, parameters_capacity),
+            parameters_stride,
+            parameters_capacity,
+            targets: None,
+        }
+    }
+
+    fn create_parameters_buffer(device: &wgpu::Device, stride: wgpu::BufferAddress, capacity: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Post process uniforms Buffer"),
+            size: stride * capacity as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Texture the screen pass renders into, in the output format. Recreated when `size` changes, which also clears the trails.
+    pub fn scene_view(&mut self, device: &wgpu::Device, size: [u32; 2]) -> &wgpu::TextureView {
+        let size = [size[0].max(1), size[1].max(1)];
+        if self.targets.as_ref().is_none_or(|targets| targets.size != size) {
+            self.targets = Some(PostTargets::new(device, size, self.output_format));
+        }
+        &self.targets.as_ref().expect("The post process targets have just been created").scene.view
+    }
+
+    // Passes applying `effects` to the scene and the number of trails histories, the result is in the returned texture
+    fn plan(effects: &[PostEffect], size: [u32; 2]) -> (Vec<PostPass>, usize, PassTexture) {
+        let texel_size = Vec2::new(1.0 / size[0] as f32, 1.0 / size[1] as f32);
+        let parameters = PostPassParameters { texel_size, ..Default::default() };
+
+        let mut passes: Vec<PostPass> = Vec::new();
+        let mut current = PassTexture::Scene;
+        let mut trails: usize = 0;
+        for (index, effect) in effects.iter().enumerate() {
+            // Alternate between the ping pong textures, the output is never the input of the effect
+            let output = PassTexture::Ping(index % 2);
+            match *effect {
+                PostEffect::Trails { persistence } => {
+                    // A persistence of 1 would freeze the display
+                    let parameters = PostPassParameters { strength: persistence.clamp(0.0, 0.99), ..parameters };
+                    passes.push(PostPass {
+                        history: Some(trails),
+                        ..PostPass::new(PostPipeline::Trails, current, PassTexture::History(trails), output, parameters)
+                    });
+                    trails += 1;
+                },
+                PostEffect::Blur { radius } => {
+                    passes.push(PostPass::new(PostPipeline::Blur, current, current, PassTexture::Temporary, PostPassParameters { radius, direction: Vec2::X, ..parameters }));
+                    passes.push(PostPass::new(PostPipeline::Blur, PassTexture::Temporary, current, output, PostPassParameters { radius, direction: Vec2::Y, ..parameters }));
+                },
+                PostEffect::Bloom { threshold, intensity, radius } => {
+                    passes.push(PostPass::new(
+                        PostPipeline::BloomBright,
+                        current,
+                        current,
+                        PassTexture::Temporary,
+                        PostPassParameters { radius, threshold, direction: Vec2::X, ..parameters },
+                    ));
+                    passes.push(PostPass::new(
+                        PostPipeline::BloomCombine,
+                        PassTexture::Temporary,
+                        current,
+                        output,
+                        PostPassParameters { radius, strength: intensity, direction: Vec2::Y, ..parameters },
+                    ));
+                },
+                PostEffect::EdgeHighlight { strength, color } => {
+                    passes.push(PostPass::new(PostPipeline::Edges, current, current, output, PostPassParameters { strength, color: Vec4::new(color[0], color[1], color[2], 1.0), ..parameters }));
+                },
+                PostEffect::Vignette { strength, radius, softness } => {
+                    passes.push(PostPass::new(PostPipeline::Vignette, current, current, output, PostPassParameters { strength, radius, softness, ..parameters }));
+                },
+            }
+            current = output;
+        }
+
+        (passes, trails, current)
+    }
+
+    fn bind_group(&self, device: &wgpu::Device, input: &wgpu::TextureView, auxiliary: &wgpu::TextureView) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(input),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(auxiliary),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: &self.parameters_buffer,
+                        offset: 0,
+                        size: wgpu::BufferSize::new(std::mem::size_of::<Std140PostPassParameters>() as u64),
+                    }),
+                },
+            ],
+            label: Some("Post process bind group"),
+        })
+    }
+
+    /// Record the effects applied to the scene rendered since the last `scene_view` call.
+    /// The result is drawn into `viewport` (x, y, width, height in pixels) of `output`, or all of it if None.
+    pub fn encode(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        effects: &[PostEffect],
+        output: &wgpu::TextureView,
+        viewport: Option<[f32; 4]>,
+    ) {
+        let Some(size) = self.targets.as_ref().map(|targets| targets.size) else {
+            return;
+        };
+        let (passes, history_count, result) = Self::plan(effects, size);
+
+        if passes.len() > self.parameters_capacity {
+            self.parameters_capacity = passes.len().next_power_of_two();
+            self.parameters_buffer = Self::create_parameters_buffer(device, self.parameters_stride, self.parameters_capacity);
+        }
+        let mut parameters_bytes = vec![0_u8; self.parameters_stride as usize * passes.len()];
+        for (pass, bytes) in passes.iter().zip(parameters_bytes.chunks_exact_mut(self.parameters_stride as usize)) {
+            let std140 = pass.parameters.as_std140();
+            bytes[..std140.as_bytes().len()].copy_from_slice(std140.as_bytes());
+        }
+        if !parameters_bytes.is_empty() {
+            queue.write_buffer(&self.parameters_buffer, 0, &parameters_bytes);
+        }
+
+        let targets = self.targets.as_mut().expect("The size comes from the targets");
+        while targets.histories.len() < history_count {
+            targets.histories.push(PostTexture::new(
+                device,
+                size,
+                POST_PROCESS_TEXTURE_FORMAT,
+                wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                "Post process history texture",
+            ));
+        }
+        targets.histories.truncate(history_count);
+
+        let targets = self.targets.as_ref().expect("The size comes from the targets");
+        for (index, pass) in passes.iter().enumerate() {
+            let bind_group = self.bind_group(device, targets.view(pass.input), targets.view(pass.auxiliary));
+            {
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Post process Render Pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: targets.view(pass.output),
+                        resolve_target: None,
+                        ops: wgpu::Operations { load: wgpu::LoadOp::Clear(wgpu::Color::BLACK), store: wgpu::StoreOp::Store },
+                        depth_slice: None,
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+                render_pass.set_pipeline(&self.pipelines[pass.pipeline as usize]);
+                render_pass.set_bind_group(0, &bind_group, &[(index as wgpu::BufferAddress * self.parameters_stride) as u32]);
+                render_pass.draw(0..3, 0..1);
+            }
+
+            if let (Some(history), PassTexture::Ping(ping)) = (pass.history, pass.output) {
+                encoder.copy_texture_to_texture(
+                    targets.ping[ping].texture.as_image_copy(),
+                    targets.histories[history].texture.as_image_copy(),
+                    wgpu::Extent3d {
+                        width: size[0],
+                        height: size[1],
+                        depth_or_array_layers: 1,
+                    },
+                );
+            }
+        }
+
+        let bind_group = self.bind_group(device, targets.view(result), targets.view(result));
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Post process output Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output,
+                resolve_target: None,
+                ops: wgpu::Operations { load: wgpu::LoadOp::Load, store: wgpu::StoreOp::Store },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        if let Some([x, y, width, height]) = viewport {
+            render_pass.set_viewport(x, y, width, height, 0.0, 1.0);
+        }
+        render_pass.set_pipeline(&self.output_pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[0]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
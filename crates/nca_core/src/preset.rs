@@ -0,0 +1,448 @@
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::{fs::File, path::Path};
+
+use crate::{boundary_condition::BoundaryCondition, color_map::ColorMap, kernel_randomizer::KernelRandomizer, kernel_symmetry::KernelSymmetryMode, lattice::Lattice, post_process::PostProcessing, DisplayFramesMode, DEFAULT_DISPLAY_CODE};
+use egui_widgets::IqGradient;
+
+/// How `Preset::kernel` is laid out
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Copy, Clone)]
+pub enum KernelLayout {
+    /// Presets saved before the layout was fixed: their kernel was loaded as the columns of the uniform matrix
+    Legacy,
+    /// Row-major, like `SimulationUniforms::get_kernel_as_slice` and the kernel text formats
+    RowMajor,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct Preset {
+    pub kernel: [f32; 9],
+    /// Missing from the presets saved before the layout was fixed, they are converted when loaded
+    #[serde(default = "legacy_kernel_layout")]
+    pub kernel_layout: KernelLayout,
+    pub kernel_symmetry_mode: KernelSymmetryMode,
+    pub activation_code: String,
+    pub display_frames_mode: DisplayFramesMode,
+    pub gradient: IqGradient,
+    #[serde(default)]
+    pub color_map: ColorMap,
+    /// colorize function of the screen shader
+    #[serde(default = "default_display_code")]
+    pub display_code: String,
+    #[serde(default)]
+    pub post_processing: PostProcessing,
+    /// Randomizer settings and seed that generated the kernel, if any
+    #[serde(default)]
+    pub kernel_randomizer: Option<KernelRandomizer>,
+    #[serde(default)]
+    pub boundary_condition: BoundaryCondition,
+    #[serde(default)]
+    pub lattice: Lattice,
+}
+
+impl Default for Preset {
+    fn default() -> Self {
+        Preset {
+            kernel: [1., 1., 1., 1., 9., 1., 1., 1., 1.],
+            kernel_layout: KernelLayout::RowMajor,
+            kernel_symmetry_mode: KernelSymmetryMode::Any,
+            activation_code: "fn activationFunction(kernelOutput: vec4<f32>) -> vec4<f32> {
+                return vec4<f32>(kernelOutput.x, kernelOutput.x, kernelOutput.x, 1.0);
+            }"
+            .to_owned(),
+            display_frames_mode: DisplayFramesMode::All,
+            gradient: IqGradient::default(),
+            color_map: ColorMap::default(),
+            display_code: default_display_code(),
+            post_processing: PostProcessing::default(),
+            kernel_randomizer: None,
+            boundary_condition: BoundaryCondition::Wrap,
+            lattice: Lattice::Square,
+        }
+    }
+}
+
+impl Preset {
+    /// Convert a kernel saved in the legacy layout to the row-major one, the weights of the neighbours are unchanged.
+    /// The legacy kernels are the row-major ones turned by a half turn.
+    pub fn upgrade_kernel_layout(&mut self) {
+        if self.kernel_layout == KernelLayout::Legacy {
+            self.kernel.reverse();
+            self.kernel_layout = KernelLayout::RowMajor;
+        }
+    }
+}
+
+fn default_display_code() -> String { DEFAULT_DISPLAY_CODE.to_owned() }
+
+fn legacy_kernel_layout() -> KernelLayout { KernelLayout::Legacy }
+
+/// Parse a json preset, its kernel is converted to the current layout
+pub fn parse_preset(text: &str) -> anyhow::Result<Preset> {
+    let mut preset: Preset = serde_json::from_str(text).context("Unable to parse the preset")?;
+    preset.upgrade_kernel_layout();
+    Ok(preset)
+}
+
+pub fn load_preset<P: AsRef<Path>>(path: P) -> anyhow::Result<Preset> {
+    fn inner(path: &Path) -> anyhow::Result<Preset>  {
+        let string_path: &str = path.to_str().unwrap_or("");
+        let file = File::open(path).with_context(|| format!("Could not open file `{}`", string_path))?;
+        serde_json::from_reader(std::io::BufReader::new(file)).with_context(|| format!("Unable to Parse the file `{}`", string_path))
+    }
+
+    let mut preset = inner(path.as_ref())?;
+    preset.upgrade_kernel_layout();
+    Ok(preset)
+}
+
+pub fn save_preset<P: AsRef<Path>>(path: P, preset: &Preset) -> std::io::Result<()> { std::fs::write(path, serde_json::to_string_pretty(preset)?) }
+
+lazy_static! {
+    pub static ref PRESETS: std::collections::HashMap<&'static str, Preset> = std::collections::HashMap::from([
+        (
+            "Game Of life",
+            Preset {
+                kernel: [1., 1., 1., 1., 9., 1., 1., 1., 1.],
+                kernel_symmetry_mode: KernelSymmetryMode::Any,
+                activation_code: "
+fn activationFunction(kernelOutput: vec4<f32>) -> vec4<f32> {
+var condition: bool = kernelOutput.x == 3.0 || kernelOutput.x == 11.0 || kernelOutput.x == 12.0;
+var r: f32 = select(0.0, 1.0, condition);
+return vec4<f32>(r, r, r, 1.0);
+}"
+                .to_owned(),
+                display_frames_mode: DisplayFramesMode::All,
+                gradient: IqGradient::default(),
+                ..Default::default()
+            },
+        ),
+        (
+            "Slime",
+            Preset {
+                kernel: [0.8, -0.85, 0.8, -0.85, -0.2, -0.85, 0.8, -0.85, 0.8],
+                kernel_symmetry_mode: KernelSymmetryMode::Any,
+                activation_code: "
+// an inverted gaussian function, 
+// where f(0) = 0. 
+// Graph: https://www.desmos.com/calculator/torawryxnq
+fn activationFunction(kernelOutput: vec4<f32>) -> vec4<f32> {
+var r: f32 = -1./(0.89*pow(kernelOutput.x, 2.)+1.)+1.;
+return vec4<f32>(r, r, r, 1.0);
+}"
+                .to_owned(),
+                display_frames_mode: DisplayFramesMode::Evens,
+                gradient: IqGradient::default(),
+                ..Default::default()
+            },
+        ),
+        (
+            "Waves",
+            Preset {
+                kernel: [0.564599, -0.715900, 0.564599, -0.715900, 0.626900, -0.715900, 0.564599, -0.715900, 0.564599,],
+                kernel_symmetry_mode: KernelSymmetryMode::Any,
+                activation_code: "
+fn activationFunction(kernelOutput: vec4<f32>) -> vec4<f32> {
+var r: f32 = abs(1.2*kernelOutput.x);
+return vec4<f32>(r, r, r, 1.0);
+}"
+                .to_owned(),
+                display_frames_mode: DisplayFramesMode::All,
+                gradient: IqGradient::default(),
+                ..Default::default()
+            },
+        ),
+        (
+            "Stars",
+            Preset {
+                kernel: [0.56459, -0.71590, 0.56459, -0.75859, 0.62690, -0.75859, 0.56459, -0.71590, 0.56459],
+                kernel_symmetry_mode: KernelSymmetryMode::Any,
+                activation_code: "
+fn activationFunction(kernelOutput: vec4<f32>) -> vec4<f32> {
+var r: f32 = abs(kernelOutput.x);
+return vec4<f32>(r, r, r, 1.0);
+}"
+                .to_owned(),
+                display_frames_mode: DisplayFramesMode::All,
+                gradient: IqGradient::default(),
+                ..Default::default()
+            },
+        ),
+        (
+            "Pathways",
+            Preset {
+                kernel: [0., 1., 0., 1., 1., 1., 0., 1., 0.],
+                kernel_symmetry_mode: KernelSymmetryMode::Any,
+                activation_code: "
+fn gaussian(x: f32, b: f32) -> f32{
+return 1./pow(2., (pow(x-b, 2.)));
+}
+
+fn activationFunction(kernelOutput: vec4<f32>) -> vec4<f32> {
+var r: f32 = gaussian(kernelOutput.x, 3.5);
+return vec4<f32>(r, r, r, 1.0);
+}"
+                .to_owned(),
+                display_frames_mode: DisplayFramesMode::All,
+                gradient: IqGradient::default(),
+                ..Default::default()
+            },
+        ),
+        (
+            "Mitosis",
+            Preset {
+                kernel: [-0.939, 0.879, -0.939, 0.879, 0.4, 0.879, -0.939, 0.879, -0.939],
+                kernel_symmetry_mode: KernelSymmetryMode::Any,
+                activation_code: "
+// an inverted gaussian function, 
+// where f(0) = 0. 
+// Graph: https://www.desmos.com/calculator/torawryxnqfn
+fn activationFunction(kernelOutput: vec4<f32>) -> vec4<f32> {
+var r: f32 = -1. / (0.9*pow(kernelOutput.x, 2.)+1.)+1.;
+return vec4<f32>(r, r, r, 1.0);
+}"
+                .to_owned(),
+                display_frames_mode: DisplayFramesMode::All,
+                gradient: IqGradient::default(),
+                ..Default::default()
+            },
+        ),
+        (
+            "Blob",
+            Preset {
+                kernel: [
+                    0.7795687913894653,
+                    -0.7663648128509521,
+                    0.7795687913894653,
+                    -0.7663648128509521,
+                    -0.29899999499320984,
+                    -0.7663648128509521,
+                    0.7795687913894653,
+                    -0.7663648128509521,
+                    0.7795687913894653,
+                ],
+                kernel_symmetry_mode: KernelSymmetryMode::Any,
+                activation_code: "
+fn activationFunction(kernelOutput: vec4<f32>) -> vec4<f32> {
+var r: f32 = -1. / pow(2., (pow(kernelOutput.x, 2.)))+1.;
+return vec4<f32>(r, r, r, 1.0);
+}"
+                .to_owned(),
+                display_frames_mode: DisplayFramesMode::All,
+                gradient: IqGradient::default(),
+                ..Default::default()
+            },
+        ),
+        (
+            "test",
+            Preset {
+                kernel: [
+                    0.5669999718666077,
+                    -0.7149999737739563,
+                    0.5669999718666077,
+                    -0.7149999737739563,
+                    0.6370000243186951,
+                    -0.7149999737739563,
+                    0.5669999718666077,
+                    -0.7149999737739563,
+                    0.5669999718666077,
+                ],
+                kernel_symmetry_mode: KernelSymmetryMode::Any,
+                activation_code: "
+fn activationFunction(kernelOutput: vec4<f32>) -> vec4<f32> {
+var r: f32 = abs(kernelOutput.x);
+return vec4<f32>(r, r, r, 1.0);
+}"
+                .to_owned(),
+                display_frames_mode: DisplayFramesMode::Evens,
+                gradient: IqGradient::default(),
+                ..Default::default()
+            },
+        ),
+        (
+            "test2",
+            Preset {
+                kernel: [
+                    91.627685546875,
+                    -59.281097412109375,
+                    91.627685546875,
+                    -59.281097412109375,
+                    -42.35920715332031,
+                    -59.281097412109375,
+                    91.627685546875,
+                    -59.281097412109375,
+                    91.627685546875,
+                ],
+                kernel_symmetry_mode: KernelSymmetryMode::Any,
+                activation_code: "
+fn activationFunction(kernelOutput: vec4<f32>) -> vec4<f32> {
+var r: f32 = (exp(2.*kernelOutput.x) - 1.) / (exp(2.*kernelOutput.x) + 1.);
+return vec4<f32>(r, r, r, 1.0);
+}"
+                .to_owned(),
+                display_frames_mode: DisplayFramesMode::Evens,
+                gradient: IqGradient::default(),
+                ..Default::default()
+            },
+        ),
+    ]);
+}
+
+lazy_static! {
+    pub static ref ACTIVATION_FUNCTIONS_PRESETS: std::collections::HashMap<&'static str, &'static str> = std::collections::HashMap::from([
+        (
+            "Identity",
+            "
+fn activationFunction(kernelOutput: vec4<f32>) -> vec4<f32> {
+var r: f32 = kernelOutput.x;
+return vec4<f32>(r, r, r, 1.0);
+}"
+        ),
+        (
+            "Sin",
+            "
+fn activationFunction(kernelOutput: vec4<f32>) -> vec4<f32> {
+var r: f32 = sin(kernelOutput.x);
+return vec4<f32>(r, r, r, 1.0);
+}"
+        ),
+        (
+            "Abs",
+            "
+fn activationFunction(kernelOutput: vec4<f32>) -> vec4<f32> {
+var r: f32 = abs(kernelOutput.x);
+return vec4<f32>(r, r, r, 1.0);
+}"
+        ),
+        (
+            "Power",
+            "
+fn activationFunction(kernelOutput: vec4<f32>) -> vec4<f32> {
+var r: f32 = pow(kernelOutput.x, 2.0);
+return vec4<f32>(r, r, r, 1.0);
+}"
+        ),
+        (
+            "Tanh",
+            "
+fn activationFunction(kernelOutput: vec4<f32>) -> vec4<f32> {
+var r: f32 = (exp(2. * kernelOutput.x) -1.) / (exp(2. * kernelOutput.x) + 1.);
+return vec4<f32>(r, r, r, 1.0);
+}"
+        ),
+        (
+            " inverted gaussian",
+            "
+// an inverted gaussian function, 
+// where f(0) = 0. 
+// Graph: https://www.desmos.com/calculator/torawryxnq
+fn activationFunction(kernelOutput: vec4<f32>) -> vec4<f32> {
+var r: f32 = -1./(0.89*pow(kernelOutput.x, 2.)+1.)+1.;
+return vec4<f32>(r, r, r, 1.0);
+}"
+        ),
+    ]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation_data::SimulationUniforms;
+
+    // Kernels of the built-in presets before the layout was fixed
+    const BASELINE_KERNELS: [(&str, [f32; 9]); 9] = [
+        ("Game Of life", [1., 1., 1., 1., 9., 1., 1., 1., 1.]),
+        ("Slime", [0.8, -0.85, 0.8, -0.85, -0.2, -0.85, 0.8, -0.85, 0.8]),
+        ("Waves", [0.564599, -0.715900, 0.564599, -0.715900, 0.626900, -0.715900, 0.564599, -0.715900, 0.564599]),
+        ("Stars", [0.56459, -0.71590, 0.56459, -0.75859, 0.62690, -0.75859, 0.56459, -0.71590, 0.56459]),
+        ("Pathways", [0., 1., 0., 1., 1., 1., 0., 1., 0.]),
+        ("Mitosis", [-0.939, 0.879, -0.939, 0.879, 0.4, 0.879, -0.939, 0.879, -0.939]),
+        (
+            "Blob",
+            [
+                0.7795687913894653,
+                -0.7663648128509521,
+                0.7795687913894653,
+                -0.7663648128509521,
+                -0.29899999499320984,
+                -0.7663648128509521,
+                0.7795687913894653,
+                -0.7663648128509521,
+                0.7795687913894653,
+            ],
+        ),
+        (
+            "test",
+            [
+                0.5669999718666077,
+                -0.7149999737739563,
+                0.5669999718666077,
+                -0.7149999737739563,
+                0.6370000243186951,
+                -0.7149999737739563,
+                0.5669999718666077,
+                -0.7149999737739563,
+                0.5669999718666077,
+            ],
+        ),
+        (
+            "test2",
+            [
+                91.627685546875,
+                -59.281097412109375,
+                91.627685546875,
+                -59.281097412109375,
+                -42.35920715332031,
+                -59.281097412109375,
+                91.627685546875,
+                -59.281097412109375,
+                91.627685546875,
+            ],
+        ),
+    ];
+
+    // Weight of the texel at (dx, dy) with a kernel in the legacy layout, loaded as the columns of the uniform matrix
+    fn legacy_weight(kernel: &[f32; 9], [dx, dy]: [i32; 2]) -> f32 { kernel[((dy + 1) * 3 + 1 - dx) as usize] }
+
+    // Weight of the texel at (dx, dy) once the kernel is in the uniforms
+    fn weight(kernel: [f32; 9], offset: [i32; 2]) -> f32 {
+        let mut uniforms = SimulationUniforms::new(&[16, 16]);
+        uniforms.set_kernel_from_slice(kernel);
+        let (col, row) = (0..3)
+            .flat_map(|row| (0..3).map(move |col| (col, row)))
+            .find(|&(col, row)| Lattice::Square.neighbour_offset(col, row, 0, 0) == Some(offset))
+            .expect("Every offset is weighted by a kernel cell");
+        uniforms.get_kernel_at(col, row)
+    }
+
+    fn assert_same_weights(kernel: [f32; 9], legacy_kernel: &[f32; 9], name: &str) {
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                assert_eq!(weight(kernel, [dx, dy]), legacy_weight(legacy_kernel, [dx, dy]), "{} at {:?}", name, [dx, dy]);
+            }
+        }
+    }
+
+    #[test]
+    fn built_in_presets_keep_their_orientation() {
+        assert_eq!(BASELINE_KERNELS.len(), PRESETS.len());
+        for (name, baseline_kernel) in BASELINE_KERNELS {
+            assert_same_weights(PRESETS[name].kernel, &baseline_kernel, name);
+        }
+    }
+
+    #[test]
+    fn legacy_presets_are_converted() {
+        let kernel = [1., 2., 3., 4., 5., 6., 7., 8., 9.];
+        let mut json = serde_json::to_value(Preset { kernel, ..Preset::default() }).unwrap();
+        json.as_object_mut().unwrap().remove("kernel_layout");
+
+        let preset = parse_preset(&json.to_string()).unwrap();
+        assert_eq!(preset.kernel_layout, KernelLayout::RowMajor);
+        assert_same_weights(preset.kernel, &kernel, "legacy preset");
+
+        // Presets saved with their layout are loaded as is
+        let saved = Preset { kernel, ..Preset::default() };
+        assert_eq!(parse_preset(&serde_json::to_string(&saved).unwrap()).unwrap(), saved);
+    }
+}
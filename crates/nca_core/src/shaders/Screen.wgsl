@@ -0,0 +1,334 @@
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+struct IqGradient {
+    a: vec3<f32>,
+    b: vec3<f32>,
+    c: vec3<f32>,
+    d: vec3<f32>,
+};
+
+const pi: f32 = 3.1415926538;
+
+fn ColorFromGradient(gradient: IqGradient, t: f32) -> vec3<f32> {
+    return gradient.a + gradient.b * cos(2.0 * pi * (gradient.c * t + gradient.d));
+}
+
+// Color in rgb and position in w of each stop
+struct ColorMap {
+    kind: u32,
+    log_scale: u32,
+    range: vec2<f32>,
+    gamma: f32,
+    stop_count: u32,
+    stops: array<vec4<f32>, 8>,
+};
+
+// Color map kinds, see ColorMapKind::shader_mode
+const COLOR_MAP_COSINE: u32 = 0u;
+const COLOR_MAP_STOPS: u32 = 1u;
+const COLOR_MAP_VIRIDIS: u32 = 2u;
+const COLOR_MAP_MAGMA: u32 = 3u;
+const COLOR_MAP_TURBO: u32 = 4u;
+
+const LOG_SCALE_MIN: f32 = 1e-6;
+
+struct ViewParameters {
+    center: vec2<f32>,
+    zoom_level: f32,
+    gradient: IqGradient,
+    lattice: u32,
+    grid_color: vec4<f32>,
+    grid_width: f32,
+    grid_min_cell_size: f32,
+    show_grid: u32,
+    color_map: ColorMap,
+    // Repeat the toroidal grid outside of the texture instead of clamping it
+    tile: u32,
+    aspect_mode: u32,
+    pixel_perfect: u32,
+    // In screen pixels
+    viewport_size: vec2<f32>,
+    // In texels
+    texture_size: vec2<f32>,
+};
+
+// Lattices, see Lattice::shader_mode
+const LATTICE_SQUARE: u32 = 0u;
+const LATTICE_HEXAGONAL: u32 = 1u;
+const LATTICE_TRIANGULAR: u32 = 2u;
+
+const ASPECT_FIT: u32 = 0u;
+const ASPECT_FILL: u32 = 1u;
+const ASPECT_STRETCH: u32 = 2u;
+
+var<private> positions: array<vec2<f32>, 3> = array<vec2<f32>, 3>(
+    vec2<f32>(-1.0, -1.0),
+    vec2<f32>(3.0, -1.0),
+    vec2<f32>(-1.0, 3.0)
+);
+
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(positions[in_vertex_index], 0.0, 1.0);
+    out.uv = (out.clip_position.xy+vec2<f32>(1.0))/vec2<f32>(2.0);
+    return out;
+}
+
+@group(0) @binding(0) var simulation_texture: texture_2d<f32>;
+@group(0) @binding(1) var simulation_tex_sampler: sampler;
+
+@group(1) @binding(0) var<uniform> view_parameters: ViewParameters;
+
+// Position of `value` in the color map, in [0, 1]
+fn colorMapPosition(color_map: ColorMap, value: f32) -> f32 {
+    var t: f32;
+    if (color_map.log_scale != 0u) {
+        let range: vec2<f32> = log(max(color_map.range, vec2<f32>(LOG_SCALE_MIN)));
+        t = (log(max(value, LOG_SCALE_MIN)) - range.x) / (range.y - range.x);
+    } else {
+        t = (value - color_map.range.x) / (color_map.range.y - color_map.range.x);
+    }
+    // max also maps a NaN from an empty range to 0
+    return pow(max(clamp(t, 0.0, 1.0), 0.0), color_map.gamma);
+}
+
+// Polynomial fits of the matplotlib colormaps by Matt Zucker (https://www.shadertoy.com/view/WlfXRN)
+fn viridis(t: f32) -> vec3<f32> {
+    let c0: vec3<f32> = vec3<f32>(0.2777273272234177, 0.005407344544966578, 0.3340998053353061);
+    let c1: vec3<f32> = vec3<f32>(0.1050930431085774, 1.404613529898575, 1.384590162594685);
+    let c2: vec3<f32> = vec3<f32>(-0.3308618287255563, 0.214847559468213, 0.09509516302823659);
+    let c3: vec3<f32> = vec3<f32>(-4.634230498983486, -5.799100973351585, -19.33244095627987);
+    let c4: vec3<f32> = vec3<f32>(6.228269936347081, 14.17993336680509, 56.69055260068105);
+    let c5: vec3<f32> = vec3<f32>(4.776384997670288, -13.74514537774601, -65.35303263337234);
+    let c6: vec3<f32> = vec3<f32>(-5.435455855934631, 4.645852612178535, 26.3124352495832);
+    return c0 + t * (c1 + t * (c2 + t * (c3 + t * (c4 + t * (c5 + t * c6)))));
+}
+
+fn magma(t: f32) -> vec3<f32> {
+    let c0: vec3<f32> = vec3<f32>(-0.002136485053939582, -0.000749655052795221, -0.005386127855323933);
+    let c1: vec3<f32> = vec3<f32>(0.2516605407371642, 0.6775232436837668, 2.494026599312351);
+    let c2: vec3<f32> = vec3<f32>(8.353717279216625, -3.577719514958484, 0.3144679030132573);
+    let c3: vec3<f32> = vec3<f32>(-27.66873308576866, 14.26473078096533, -13.64921318813922);
+    let c4: vec3<f32> = vec3<f32>(52.17613981234068, -27.94360607168351, 12.94416944238394);
+    let c5: vec3<f32> = vec3<f32>(-50.76852536473588, 29.04658282127291, 4.23415299384598);
+    let c6: vec3<f32> = vec3<f32>(18.65570506591883, -11.48977351997711, -5.601961508734096);
+    return c0 + t * (c1 + t * (c2 + t * (c3 + t * (c4 + t * (c5 + t * c6)))));
+}
+
+// Polynomial approximation of Turbo by Ruofei Du (https://www.shadertoy.com/view/3lBXR3)
+fn turbo(t: f32) -> vec3<f32> {
+    let c0: vec3<f32> = vec3<f32>(0.13572138, 0.09140261, 0.10667330);
+    let c1: vec3<f32> = vec3<f32>(4.61539260, 2.19418839, 12.64194608);
+    let c2: vec3<f32> = vec3<f32>(-42.66032258, 4.84296658, -60.58204836);
+    let c3: vec3<f32> = vec3<f32>(132.13108234, -14.18503333, 110.36276771);
+    let c4: vec3<f32> = vec3<f32>(-152.94239396, 4.27729857, -89.90310912);
+    let c5: vec3<f32> = vec3<f32>(59.28637943, 2.82956604, 27.34824973);
+    return c0 + t * (c1 + t * (c2 + t * (c3 + t * (c4 + t * c5))));
+}
+
+// Piecewise linear interpolation between the stops, sorted by position.
+// The stops are indexed in the uniform buffer, arrays held in values can only be indexed by constants.
+fn colorFromStops(t: f32) -> vec3<f32> {
+    var color: vec3<f32> = view_parameters.color_map.stops[0].rgb;
+    for (var i: u32 = 1u; i < min(view_parameters.color_map.stop_count, 8u); i++) {
+        let previous: vec4<f32> = view_parameters.color_map.stops[i - 1u];
+        let next: vec4<f32> = view_parameters.color_map.stops[i];
+        let width: f32 = next.w - previous.w;
+        var blend: f32 = select(0.0, 1.0, t >= next.w);
+        if (width > 0.0) {
+            blend = clamp((t - previous.w) / width, 0.0, 1.0);
+        }
+        color = mix(color, next.rgb, blend);
+    }
+    return color;
+}
+
+// Mirrors ColorMapParameters::color
+fn colorMap(value: f32) -> vec3<f32> {
+    let color_map: ColorMap = view_parameters.color_map;
+    let t: f32 = colorMapPosition(color_map, value);
+    switch color_map.kind {
+        case COLOR_MAP_STOPS: {
+            return colorFromStops(t);
+        }
+        case COLOR_MAP_VIRIDIS: {
+            return viridis(t);
+        }
+        case COLOR_MAP_MAGMA: {
+            return magma(t);
+        }
+        case COLOR_MAP_TURBO: {
+            return turbo(t);
+        }
+        default: {
+            return ColorFromGradient(view_parameters.gradient, t);
+        }
+    }
+}
+
+// Center of the hexagonal cell containing `position` (in texels): the nearest cell center, odd rows being shifted by half a cell
+fn hexagonalCellCenter(position: vec2<f32>) -> vec2<f32> {
+    let row: f32 = floor(position.y);
+    var center: vec2<f32> = position;
+    var center_distance: f32 = 1e9;
+    for (var offset: f32 = -1.0; offset <= 1.0; offset += 1.0) {
+        let candidate_row: f32 = row + offset;
+        let shift: f32 = 0.5 * abs(candidate_row % 2.0);
+        let candidate: vec2<f32> = vec2<f32>(floor(position.x - shift) + 0.5 + shift, candidate_row + 0.5);
+        let candidate_distance: f32 = distance(position, candidate);
+        if (candidate_distance < center_distance) {
+            center = candidate;
+            center_distance = candidate_distance;
+        }
+    }
+    return center;
+}
+
+// Center of the texel of the triangle containing `position` (in texels).
+// Triangles with an even x + y have their base on the side of the next row, the others on the side of the previous one.
+fn triangularCellCenter(position: vec2<f32>) -> vec2<f32> {
+    let row: i32 = i32(floor(position.y));
+    let height: f32 = position.y - floor(position.y);
+    let column: i32 = i32(floor(position.x));
+    var cell: i32 = column;
+    for (var x: i32 = column - 1; x <= column + 1; x++) {
+        let half_width: f32 = select(1.0 - height, height, ((x + row) & 1) == 0);
+        if (abs(position.x - (f32(x) + 0.5)) <= half_width) {
+            cell = x;
+            break;
+        }
+    }
+    return vec2<f32>(f32(cell) + 0.5, f32(row) + 0.5);
+}
+
+// Distance (in screen pixels) from `position` (in texels) to the border of its cell
+fn squareBorderDistance(position: vec2<f32>, texels_per_pixel: vec2<f32>) -> f32 {
+    let cell_position: vec2<f32> = fract(position);
+    let border: vec2<f32> = min(cell_position, 1.0 - cell_position) / texels_per_pixel;
+    return min(border.x, border.y);
+}
+
+// The hexagonal cells are the Voronoi cells of their centers, the border is the closest bisector with a neighbouring center
+fn hexagonalBorderDistance(position: vec2<f32>, texels_per_pixel: vec2<f32>) -> f32 {
+    let center: vec2<f32> = hexagonalCellCenter(position);
+    let center_distance: f32 = dot(position - center, position - center);
+    var border: f32 = 1e9;
+    for (var row: f32 = center.y - 1.5; row <= center.y + 0.5; row += 1.0) {
+        let shift: f32 = 0.5 * abs(row % 2.0);
+        for (var offset: f32 = -1.0; offset <= 1.0; offset += 1.0) {
+            let neighbour: vec2<f32> = vec2<f32>(floor(center.x - shift) + offset + 0.5 + shift, row + 0.5);
+            let centers_distance: f32 = distance(neighbour, center);
+            if (centers_distance > 0.5) {
+                border = min(border, (dot(position - neighbour, position - neighbour) - center_distance) / (2.0 * centers_distance));
+            }
+        }
+    }
+    return border / max(texels_per_pixel.x, texels_per_pixel.y);
+}
+
+// Rows are separated by the bases of the triangles, the sides have a slope of one texel per row
+fn triangularBorderDistance(position: vec2<f32>, texels_per_pixel: vec2<f32>) -> f32 {
+    let center: vec2<f32> = triangularCellCenter(position);
+    let row: i32 = i32(floor(position.y));
+    let height: f32 = position.y - floor(position.y);
+    let half_width: f32 = select(1.0 - height, height, ((i32(floor(center.x)) + row) & 1) == 0);
+    let base: f32 = min(height, 1.0 - height) / texels_per_pixel.y;
+    let side: f32 = (half_width - abs(position.x - center.x)) / sqrt(2.0) / max(texels_per_pixel.x, texels_per_pixel.y);
+    return min(base, side);
+}
+
+fn cellBorderDistance(position: vec2<f32>, texels_per_pixel: vec2<f32>) -> f32 {
+    switch view_parameters.lattice {
+        case LATTICE_HEXAGONAL: {
+            return hexagonalBorderDistance(position, texels_per_pixel);
+        }
+        case LATTICE_TRIANGULAR: {
+            return triangularBorderDistance(position, texels_per_pixel);
+        }
+        default: {
+            return squareBorderDistance(position, texels_per_pixel);
+        }
+    }
+}
+
+// Texture coordinates of the center of the cell displayed at `uv`
+fn cellUv(uv: vec2<f32>) -> vec2<f32> {
+    let size: vec2<f32> = vec2<f32>(textureDimensions(simulation_texture));
+    switch view_parameters.lattice {
+        case LATTICE_HEXAGONAL: {
+            return hexagonalCellCenter(uv * size) / size;
+        }
+        case LATTICE_TRIANGULAR: {
+            return triangularCellCenter(uv * size) / size;
+        }
+        default: {
+            return uv;
+        }
+    }
+}
+
+// Texels per screen pixel along each axis, see ViewParameters::texel_scale
+fn texelScale() -> vec2<f32> {
+    var scale: vec2<f32> = vec2<f32>(view_parameters.zoom_level);
+    if (view_parameters.aspect_mode == ASPECT_STRETCH) {
+        let fitted_scale: vec2<f32> = view_parameters.texture_size / view_parameters.viewport_size;
+        scale = view_parameters.zoom_level * fitted_scale / fitted_scale.x;
+    }
+    if (view_parameters.pixel_perfect != 0u) {
+        scale = select(ceil(scale), 1.0 / floor(1.0 / scale), scale <= vec2<f32>(1.0));
+    }
+    return scale;
+}
+
+// Texel coordinates (first row at the bottom) of the bottom left corner of the viewport, see ViewParameters::bottom_left
+fn viewBottomLeft(scale: vec2<f32>) -> vec2<f32> {
+    let center: vec2<f32> = vec2<f32>(view_parameters.center.x, 1.0 - view_parameters.center.y) * view_parameters.texture_size;
+    let bottom_left: vec2<f32> = center - 0.5 * view_parameters.viewport_size * scale;
+    if (view_parameters.pixel_perfect != 0u) {
+        return round(bottom_left / scale) * scale;
+    }
+    return bottom_left;
+}
+
+// User colorize(state: vec4<f32>, uv: vec2<f32>) -> vec4<f32> function, turning the state of the cell at the texture coordinates `uv` into a color
+[colorizeTemplate]
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let scale: vec2<f32> = texelScale();
+    // in.uv spans the viewport from its bottom left corner
+    let position: vec2<f32> = viewBottomLeft(scale) + in.uv * view_parameters.viewport_size * scale;
+    let uv: vec2<f32> = position / vec2<f32>(textureDimensions(simulation_texture));
+    // Derivatives are only defined in uniform control flow
+    let texels_per_pixel: vec2<f32> = fwidth(position);
+
+    // The cell is found before wrapping, so that the cells and their borders continue across the edges
+    var cell_uv: vec2<f32> = cellUv(uv);
+    if (view_parameters.tile != 0u) {
+        cell_uv = fract(cell_uv);
+    }
+    let sample: vec4<f32> = textureSample(simulation_texture, simulation_tex_sampler, cell_uv);
+    let color: vec4<f32> = colorize(sample, cell_uv);
+    var grad: vec3<f32> = color.rgb;
+
+    // Cell borders once the cells are large enough on screen, faded in up to 1.5 times the threshold
+    let cell_size: f32 = 1.0 / max(texels_per_pixel.x, texels_per_pixel.y);
+    if (view_parameters.show_grid != 0u && cell_size >= view_parameters.grid_min_cell_size) {
+        let half_width: f32 = 0.5 * view_parameters.grid_width;
+        let line: f32 = 1.0 - smoothstep(half_width - 0.5, half_width + 0.5, cellBorderDistance(position, texels_per_pixel));
+        let fade: f32 = smoothstep(view_parameters.grid_min_cell_size, 1.5 * view_parameters.grid_min_cell_size, cell_size);
+        grad = mix(grad, view_parameters.grid_color.rgb, view_parameters.grid_color.a * line * fade);
+    }
+
+    // Bars around the grid when it does not cover the viewport
+    let outside: bool = any(uv < vec2<f32>(0.0)) || any(uv >= vec2<f32>(1.0));
+    if (view_parameters.tile == 0u && outside) {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+
+    return vec4<f32>(grad.r, grad.g, grad.b, color.a);
+}
\ No newline at end of file
@@ -0,0 +1,21 @@
+// Copy of the values of a region of a simulation texture, the coordinates wrap around the borders
+
+struct ProbeRegion {
+    origin: vec2<i32>,
+    size: vec2<u32>,
+};
+
+@group(0) @binding(0) var simulation_texture: texture_2d<f32>;
+@group(0) @binding(1) var<uniform> region: ProbeRegion;
+@group(0) @binding(2) var<storage, read_write> cells: array<vec4<f32>>;
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if (any(global_id.xy >= region.size)) {
+        return;
+    }
+
+    let size: vec2<i32> = vec2<i32>(textureDimensions(simulation_texture));
+    let coords: vec2<i32> = ((region.origin + vec2<i32>(global_id.xy)) % size + size) % size;
+    cells[global_id.y * region.size.x + global_id.x] = textureLoad(simulation_texture, coords, 0);
+}
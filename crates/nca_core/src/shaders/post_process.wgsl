@@ -0,0 +1,122 @@
+// Effects applied to the displayed image after the screen pass, one fullscreen pass per entry point
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+// See PostPassParameters, the meaning of the fields depends on the effect
+struct PostParameters {
+    color: vec4<f32>,
+    texel_size: vec2<f32>,
+    // Blur direction, (1, 0) or (0, 1)
+    direction: vec2<f32>,
+    strength: f32,
+    radius: f32,
+    threshold: f32,
+    softness: f32,
+};
+
+var<private> positions: array<vec2<f32>, 3> = array<vec2<f32>, 3>(
+    vec2<f32>(-1.0, -1.0),
+    vec2<f32>(3.0, -1.0),
+    vec2<f32>(-1.0, 3.0)
+);
+
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(positions[in_vertex_index], 0.0, 1.0);
+    // Texture coordinates, the first row of the texture is at the top
+    out.uv = vec2<f32>(0.5 * (out.clip_position.x + 1.0), 0.5 * (1.0 - out.clip_position.y));
+    return out;
+}
+
+@group(0) @binding(0) var input_texture: texture_2d<f32>;
+// Second input of the effects that need one (trails history, image before the bloom)
+@group(0) @binding(1) var auxiliary_texture: texture_2d<f32>;
+@group(0) @binding(2) var post_sampler: sampler;
+@group(0) @binding(3) var<uniform> parameters: PostParameters;
+
+const BLUR_TAPS: i32 = 8;
+
+fn sampleInput(uv: vec2<f32>) -> vec4<f32> {
+    return textureSampleLevel(input_texture, post_sampler, uv, 0.0);
+}
+
+// One direction of a separable gaussian blur reaching `radius` pixels (at two standard deviations),
+// of the colors above the threshold (0 to blur everything)
+fn gaussianBlur(uv: vec2<f32>, threshold: f32) -> vec4<f32> {
+    let tap_step: vec2<f32> = parameters.radius / f32(BLUR_TAPS) * parameters.direction * parameters.texel_size;
+    var color: vec4<f32> = vec4<f32>(0.0);
+    var total: f32 = 0.0;
+    for (var i: i32 = -BLUR_TAPS; i <= BLUR_TAPS; i++) {
+        let offset: f32 = f32(i) / f32(BLUR_TAPS);
+        let weight: f32 = exp(-2.0 * offset * offset);
+        let tap: vec4<f32> = sampleInput(uv + f32(i) * tap_step);
+        color += vec4<f32>(max(tap.rgb - threshold, vec3<f32>(0.0)), tap.a) * weight;
+        total += weight;
+    }
+    return color / total;
+}
+
+// Exponential moving average of the frames, `strength` is the weight of the history
+@fragment
+fn fs_trails(in: VertexOutput) -> @location(0) vec4<f32> {
+    let history: vec4<f32> = textureSampleLevel(auxiliary_texture, post_sampler, in.uv, 0.0);
+    return mix(sampleInput(in.uv), history, parameters.strength);
+}
+
+@fragment
+fn fs_blur(in: VertexOutput) -> @location(0) vec4<f32> {
+    return gaussianBlur(in.uv, 0.0);
+}
+
+// First (horizontal) pass of the bloom: blurred bright parts of the image
+@fragment
+fn fs_bloom_bright(in: VertexOutput) -> @location(0) vec4<f32> {
+    return gaussianBlur(in.uv, parameters.threshold);
+}
+
+// Second (vertical) pass of the bloom, added to the image before the bloom
+@fragment
+fn fs_bloom_combine(in: VertexOutput) -> @location(0) vec4<f32> {
+    let image: vec4<f32> = textureSampleLevel(auxiliary_texture, post_sampler, in.uv, 0.0);
+    let bloom: vec4<f32> = gaussianBlur(in.uv, 0.0);
+    return vec4<f32>(image.rgb + parameters.strength * bloom.rgb, image.a);
+}
+
+fn luminance(color: vec3<f32>) -> f32 {
+    return dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
+// Sobel filter of the luminance, the edges are drawn with `color`
+@fragment
+fn fs_edges(in: VertexOutput) -> @location(0) vec4<f32> {
+    var gradient: vec2<f32> = vec2<f32>(0.0);
+    for (var y: i32 = -1; y <= 1; y++) {
+        for (var x: i32 = -1; x <= 1; x++) {
+            let value: f32 = luminance(sampleInput(in.uv + vec2<f32>(f32(x), f32(y)) * parameters.texel_size).rgb);
+            let weight: vec2<f32> = vec2<f32>(f32(x), f32(y)) * select(1.0, 2.0, x == 0 || y == 0);
+            gradient += value * weight;
+        }
+    }
+    let color: vec4<f32> = sampleInput(in.uv);
+    let edge: f32 = clamp(length(gradient) * parameters.strength, 0.0, 1.0);
+    return vec4<f32>(mix(color.rgb, parameters.color.rgb, edge * parameters.color.a), color.a);
+}
+
+// Darkens the image from `radius` to `radius + softness`, distances relative to the half diagonal
+@fragment
+fn fs_vignette(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color: vec4<f32> = sampleInput(in.uv);
+    let distance_to_center: f32 = length(in.uv - 0.5) / length(vec2<f32>(0.5));
+    let darkening: f32 = parameters.strength * smoothstep(parameters.radius, parameters.radius + parameters.softness, distance_to_center);
+    return vec4<f32>(color.rgb * (1.0 - darkening), color.a);
+}
+
+// Copy of the result to the output
+@fragment
+fn fs_output(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(sampleInput(in.uv).rgb, 1.0);
+}
@@ -0,0 +1,123 @@
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+struct SimulationUniforms {
+    pixel_size: vec2<f32>,
+    kernel_first_row: vec3<f32>,
+    kernel_second_row: vec3<f32>,
+    kernel_third_row: vec3<f32>,
+    // std140 layout of the uniforms: the members after the kernel matrix start on a new 16 bytes row
+    @align(16) boundary_mode: u32,
+    boundary_value: f32,
+    lattice: u32,
+};
+
+// Boundary conditions, see BoundaryCondition::shader_mode
+const BOUNDARY_WRAP: u32 = 0u;
+const BOUNDARY_CLAMP: u32 = 1u;
+const BOUNDARY_MIRROR: u32 = 2u;
+const BOUNDARY_CONSTANT: u32 = 3u;
+
+// Lattices, see Lattice::shader_mode
+const LATTICE_SQUARE: u32 = 0u;
+const LATTICE_HEXAGONAL: u32 = 1u;
+const LATTICE_TRIANGULAR: u32 = 2u;
+
+@group(0) @binding(0) var simulation_texture: texture_2d<f32>;
+// Not used by the simulation itself (exact texel loads), kept for the activation functions
+@group(0) @binding(1) var simulation_tex_sampler: sampler;
+
+@group(1) @binding(0)
+var<uniform> simulation_uniforms: SimulationUniforms;
+
+// Texel of the neighbour at `offset` once the boundary condition is applied, it stays outside of the grid for BOUNDARY_CONSTANT.
+// Integer arithmetic only, so that no cell is ever blended with its neighbours.
+fn getCoords(cell: vec2<i32>, offset: vec2<i32>) -> vec2<i32> {
+    let size: vec2<i32> = vec2<i32>(textureDimensions(simulation_texture));
+    let coords: vec2<i32> = cell + offset;
+    switch simulation_uniforms.boundary_mode {
+        case BOUNDARY_CLAMP: {
+            return clamp(coords, vec2<i32>(0), size - vec2<i32>(1));
+        }
+        case BOUNDARY_MIRROR: {
+            // The border texel is repeated once, like with AddressMode::MirrorRepeat
+            let reflected: vec2<i32> = select(coords, -coords - vec2<i32>(1), coords < vec2<i32>(0));
+            return select(reflected, 2 * size - reflected - vec2<i32>(1), reflected >= size);
+        }
+        case BOUNDARY_CONSTANT: {
+            return coords;
+        }
+        default: {
+            return ((coords % size) + size) % size;
+        }
+    }
+}
+
+fn getNeighbour(cell: vec2<i32>, offset: vec2<i32>) -> vec4<f32> {
+    let coords: vec2<i32> = getCoords(cell, offset);
+    let size: vec2<i32> = vec2<i32>(textureDimensions(simulation_texture));
+    if (any(coords < vec2<i32>(0)) || any(coords >= size)) {
+        return vec4<f32>(vec3<f32>(simulation_uniforms.boundary_value), 1.0);
+    }
+    return textureLoad(simulation_texture, coords, 0);
+}
+
+// The kernel is laid out as displayed, the next texture row being above the cell (kernel_first_row holds the first column of the kernel).
+// Every lattice sums its kernel in row-major order.
+fn squareSum(cell: vec2<i32>) -> vec4<f32> {
+    return getNeighbour(cell, vec2<i32>(-1, 1)) * simulation_uniforms.kernel_first_row[0]
+        + getNeighbour(cell, vec2<i32>( 0, 1)) * simulation_uniforms.kernel_second_row[0]
+        + getNeighbour(cell, vec2<i32>( 1, 1)) * simulation_uniforms.kernel_third_row[0]
+        + getNeighbour(cell, vec2<i32>(-1, 0)) * simulation_uniforms.kernel_first_row[1]
+        + getNeighbour(cell, vec2<i32>( 0, 0)) * simulation_uniforms.kernel_second_row[1]
+        + getNeighbour(cell, vec2<i32>( 1, 0)) * simulation_uniforms.kernel_third_row[1]
+        + getNeighbour(cell, vec2<i32>(-1,-1)) * simulation_uniforms.kernel_first_row[2]
+        + getNeighbour(cell, vec2<i32>( 0,-1)) * simulation_uniforms.kernel_second_row[2]
+        + getNeighbour(cell, vec2<i32>( 1,-1)) * simulation_uniforms.kernel_third_row[2];
+}
+
+// Odd rows are shifted by half a cell to the right
+fn hexagonalSum(cell: vec2<i32>) -> vec4<f32> {
+    let shift: i32 = cell.y & 1;
+    return getNeighbour(cell, vec2<i32>(shift - 1, 1)) * simulation_uniforms.kernel_first_row[0]
+        + getNeighbour(cell, vec2<i32>(shift, 1)) * simulation_uniforms.kernel_second_row[0]
+        + getNeighbour(cell, vec2<i32>(-1, 0)) * simulation_uniforms.kernel_first_row[1]
+        + getNeighbour(cell, vec2<i32>( 0, 0)) * simulation_uniforms.kernel_second_row[1]
+        + getNeighbour(cell, vec2<i32>( 1, 0)) * simulation_uniforms.kernel_third_row[1]
+        + getNeighbour(cell, vec2<i32>(shift - 1, -1)) * simulation_uniforms.kernel_first_row[2]
+        + getNeighbour(cell, vec2<i32>(shift, -1)) * simulation_uniforms.kernel_second_row[2];
+}
+
+// Triangles with an even x + y share their base with the cell of the next row, the others with the cell of the previous row
+fn triangularSum(cell: vec2<i32>) -> vec4<f32> {
+    let base: i32 = select(-1, 1, ((cell.x + cell.y) & 1) == 0);
+    return getNeighbour(cell, vec2<i32>(-1, 0)) * simulation_uniforms.kernel_first_row[1]
+        + getNeighbour(cell, vec2<i32>( 0, 0)) * simulation_uniforms.kernel_second_row[1]
+        + getNeighbour(cell, vec2<i32>( 1, 0)) * simulation_uniforms.kernel_third_row[1]
+        + getNeighbour(cell, vec2<i32>(0, base)) * simulation_uniforms.kernel_second_row[2];
+}
+
+[functionTemplate]
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+
+    // The target has the size of the simulation texture, the fragment position is the cell (row 0 at the top)
+    let cell: vec2<i32> = vec2<i32>(floor(in.clip_position.xy));
+    var sum: vec4<f32>;
+    switch simulation_uniforms.lattice {
+        case LATTICE_HEXAGONAL: {
+            sum = hexagonalSum(cell);
+        }
+        case LATTICE_TRIANGULAR: {
+            sum = triangularSum(cell);
+        }
+        default: {
+            sum = squareSum(cell);
+        }
+    }
+
+    return activationFunction(sum);
+}
@@ -0,0 +1,63 @@
+// Histogram of the 8 bits quantized first channel, number of cells with a channel changed since the previous generation
+// and a position weighted hash of the four 8 bits quantized channels (wrapping sums, so independent of the invocations order)
+
+struct Statistics {
+    changed: atomic<u32>,
+    hash: array<atomic<u32>, 2>,
+    histogram: array<atomic<u32>, 256>,
+};
+
+@group(0) @binding(0) var current_texture: texture_2d<f32>;
+@group(0) @binding(1) var previous_texture: texture_2d<f32>;
+@group(0) @binding(2) var<storage, read_write> statistics: Statistics;
+
+// One bin per invocation of the 16x16 workgroup
+var<workgroup> local_histogram: array<atomic<u32>, 256>;
+var<workgroup> local_changed: atomic<u32>;
+var<workgroup> local_hash: array<atomic<u32>, 2>;
+
+fn pcg_hash(input: u32) -> u32 {
+    let state: u32 = input * 747796405u + 2891336453u;
+    let word: u32 = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+@compute @workgroup_size(16, 16)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>, @builtin(local_invocation_index) local_index: u32) {
+    atomicStore(&local_histogram[local_index], 0u);
+    if (local_index == 0u) {
+        atomicStore(&local_changed, 0u);
+        atomicStore(&local_hash[0], 0u);
+        atomicStore(&local_hash[1], 0u);
+    }
+    workgroupBarrier();
+
+    let size: vec2<u32> = textureDimensions(current_texture);
+    if (all(global_id.xy < size)) {
+        let value: vec4<f32> = textureLoad(current_texture, global_id.xy, 0);
+        let previous_value: vec4<f32> = textureLoad(previous_texture, global_id.xy, 0);
+
+        let quantized: vec4<u32> = min(vec4<u32>(round(value * 255.0)), vec4<u32>(255u));
+        atomicAdd(&local_histogram[quantized.x], 1u);
+        if (any(abs(value - previous_value) > vec4<f32>(0.5 / 255.0))) {
+            atomicAdd(&local_changed, 1u);
+        }
+
+        // The channels packed in one word, mixed with the position so that equal sums of different states stay unlikely
+        let packed: u32 = quantized.x | (quantized.y << 8u) | (quantized.z << 16u) | (quantized.w << 24u);
+        let index: u32 = global_id.y * size.x + global_id.x;
+        atomicAdd(&local_hash[0], pcg_hash(packed ^ pcg_hash(index)));
+        atomicAdd(&local_hash[1], pcg_hash(packed ^ pcg_hash(index ^ 0x9e3779b9u)));
+    }
+    workgroupBarrier();
+
+    let count: u32 = atomicLoad(&local_histogram[local_index]);
+    if (count > 0u) {
+        atomicAdd(&statistics.histogram[local_index], count);
+    }
+    if (local_index == 0u) {
+        atomicAdd(&statistics.changed, atomicLoad(&local_changed));
+        atomicAdd(&statistics.hash[0], atomicLoad(&local_hash[0]));
+        atomicAdd(&statistics.hash[1], atomicLoad(&local_hash[1]));
+    }
+}
@@ -0,0 +1,661 @@
+use std::sync::mpsc;
+
+use glam::Vec2;
+use oxyde::wgpu as wgpu;
+use rand::{rngs::StdRng, SeedableRng};
+use rand_distr::{Distribution, Normal};
+
+use crate::{
+    boundary_condition::BoundaryCondition,
+    generate_screen_shader,
+    generate_simulation_shader,
+    lattice::Lattice,
+    pipeline_helpers::{
+        build_init_simulation_pipeline,
+        build_screen_pipeline,
+        build_simulation_pipeline,
+        capture_validation_error,
+        create_display_sampler,
+        create_simulation_sampler,
+        create_texture_bind_group,
+        create_texture_bind_group_layout,
+        fullscreen_primitive_state,
+        get_texture_descriptor,
+        SIMULATION_TEXTURE_FORMAT,
+    },
+    preset::Preset,
+    simulation_data::{InitSimulationData, InitSimulationUniforms, SimulationData},
+    statistics::{GenerationStatistics, StatisticsPass},
+    view_data::{ViewData, ViewParameters},
+    DisplayFramesMode,
+    INIT_SIMULATION_SHADER,
+};
+
+/// CPU copy of a simulation state, texels in RGBA order
+#[derive(Debug, Clone)]
+pub struct StateSnapshot {
+    pub size: [u32; 2],
+    pub rgba: Vec<u8>,
+}
+
+impl StateSnapshot {
+    pub fn texel_count(&self) -> usize { (self.size[0] * self.size[1]) as usize }
+
+    // The displayed (and most used) channel is the first one
+    pub fn value_at(&self, x: u32, y: u32) -> f32 { self.rgba[((y * self.size[0] + x) * 4) as usize] as f32 / 255.0 }
+
+    pub fn values(&self) -> Vec<f32> { self.rgba.chunks_exact(4).map(|texel| texel[0] as f32 / 255.0).collect() }
+}
+
+/// Self-contained simulation rendering in its own textures.
+/// It holds its own handles on the device and the queue, so it can be embedded without the application.
+pub struct Simulation {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+
+    size: [u32; 2],
+    textures: [wgpu::Texture; 2],
+    views: [wgpu::TextureView; 2],
+    texture_bind_group_layout: wgpu::BindGroupLayout,
+    // bind_groups[i] samples textures[i]
+    bind_groups: [wgpu::BindGroup; 2],
+    display_bind_groups: [wgpu::BindGroup; 2],
+    current: usize,
+
+    pub simulation_data: SimulationData,
+    pub init_simulation_data: InitSimulationData,
+    view_data: ViewData,
+
+    screen_shader: wgpu::ShaderModule,
+    activation_code: String,
+
+    init_simulation_render_pipeline: wgpu::RenderPipeline,
+    simulation_render_pipeline: wgpu::RenderPipeline,
+    // Built on the first render, for the format of its target
+    screen_render_pipeline: Option<(wgpu::TextureFormat, wgpu::RenderPipeline)>,
+
+    pub generation: u32,
+}
+
+/// Textures of a simulation at a new size, created before replacing the current ones,
+/// so that several simulations can be resized together or not at all
+pub struct ResizedTextures {
+    size: [u32; 2],
+    textures: [wgpu::Texture; 2],
+    views: [wgpu::TextureView; 2],
+    bind_groups: [wgpu::BindGroup; 2],
+    display_bind_groups: [wgpu::BindGroup; 2],
+}
+
+impl Simulation {
+    /// Shader or texture creation errors are returned, they never reach the uncaptured error handler of the device.
+    /// `size` is rounded up to a size supported by the lattice of the preset, see `Lattice::grid_size`.
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, size: [u32; 2], preset: &Preset) -> Result<Self, wgpu::Error> {
+        capture_validation_error(device, || Self::build(device, queue, preset.lattice.grid_size(size), preset))
+    }
+
+    fn build(device: &wgpu::Device, queue: &wgpu::Queue, size: [u32; 2], preset: &Preset) -> Self {
+        let texture_bind_group_layout = create_texture_bind_group_layout(device, Some("Simulation texture bind group layout"));
+        let (textures, views, bind_groups, display_bind_groups) = create_textures(device, &texture_bind_group_layout, size, preset.boundary_condition);
+
+        let mut simulation_data = SimulationData::new(device, &size);
+        simulation_data.uniform.set_kernel_from_slice(preset.kernel);
+        simulation_data.uniform.set_boundary_condition(preset.boundary_condition);
+        simulation_data.uniform.set_lattice(preset.lattice);
+        simulation_data.need_update = true;
+
+        let init_simulation_data = InitSimulationData::new(device);
+
+        let mut view_data = ViewData::new(device);
+        view_data.uniform.gradient = preset.gradient;
+        view_data.uniform.color_map = preset.color_map.parameters();
+        view_data.uniform.lattice = preset.lattice.shader_mode();
+        view_data.need_update = true;
+
+        let screen_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Screne Shader"),
+            source: wgpu::ShaderSource::Wgsl(generate_screen_shader(&preset.display_code).into()),
+        });
+
+        let simulation_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Simulation Shader"),
+            source: wgpu::ShaderSource::Wgsl(generate_simulation_shader(&preset.activation_code).into()),
+        });
+
+        let init_simulation_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Init Simulation Shader"),
+            source: wgpu::ShaderSource::Wgsl(INIT_SIMULATION_SHADER.into()),
+        });
+
+        let primitive_state = fullscreen_primitive_state();
+        let multisample_state = wgpu::MultisampleState::default();
+
+        let init_simulation_render_pipeline = build_init_simulation_pipeline(
+            device,
+            SIMULATION_TEXTURE_FORMAT,
+            &primitive_state,
+            &multisample_state,
+            &screen_shader,
+            &init_simulation_shader,
+            &init_simulation_data,
+        );
+
+        let simulation_render_pipeline = build_simulation_pipeline(
+            device,
+            SIMULATION_TEXTURE_FORMAT,
+            &primitive_state,
+            &multisample_state,
+            &screen_shader,
+            &simulation_shader,
+            &texture_bind_group_layout,
+            &simulation_data,
+        );
+
+        Self {
+            device: device.clone(),
+            queue: queue.clone(),
+            size,
+            textures,
+            views,
+            texture_bind_group_layout,
+            bind_groups,
+            display_bind_groups,
+            current: 0,
+            simulation_data,
+            init_simulation_data,
+            view_data,
+            screen_shader,
+            activation_code: preset.activation_code.clone(),
+            init_simulation_render_pipeline,
+            simulation_render_pipeline,
+            screen_render_pipeline: None,
+            generation: 0,
+        }
+    }
+
+    pub fn size(&self) -> [u32; 2] { self.size }
+
+    /// Recreate the textures with the new size, the state must be initialised again
+    pub fn resize(&mut self, size: [u32; 2]) -> Result<(), wgpu::Error> {
+        let resized = self.create_resized_textures(size)?;
+        self.apply_resize(resized);
+        Ok(())
+    }
+
+    /// Textures for `size` (rounded for the lattice), the simulation is unchanged until they are given to `apply_resize`
+    pub fn create_resized_textures(&self, size: [u32; 2]) -> Result<ResizedTextures, wgpu::Error> {
+        let size = self.lattice().grid_size(size);
+        let (textures, views, bind_groups, display_bind_groups) =
+            capture_validation_error(&self.device, || create_textures(&self.device, &self.texture_bind_group_layout, size, self.boundary_condition()))?;
+        Ok(ResizedTextures {
+            size,
+            textures,
+            views,
+            bind_groups,
+            display_bind_groups,
+        })
+    }
+
+    /// Replace the textures by resized ones, the state is lost
+    pub fn apply_resize(&mut self, resized: ResizedTextures) {
+        self.size = resized.size;
+        self.textures = resized.textures;
+        self.views = resized.views;
+        self.bind_groups = resized.bind_groups;
+        self.display_bind_groups = resized.display_bind_groups;
+        self.current = 0;
+        self.generation = 0;
+        self.simulation_data.set_simulation_size(&resized.size);
+    }
+
+    /// Layout of the bind groups sampling the simulation textures, for pipelines drawing them
+    pub fn texture_bind_group_layout(&self) -> &wgpu::BindGroupLayout { &self.texture_bind_group_layout }
+
+    /// View of the current state
+    pub fn current_view(&self) -> &wgpu::TextureView { &self.views[self.current] }
+
+    /// View of the state of the previous generation
+    pub fn previous_view(&self) -> &wgpu::TextureView { &self.views[1 - self.current] }
+
+    /// Bind group of the displayed state, with the display sampler.
+    /// Odd and even generations are written in their own texture, which allows to display only one of them.
+    pub fn display_bind_group(&self, display_frames_mode: &DisplayFramesMode) -> &wgpu::BindGroup {
+        match display_frames_mode {
+            DisplayFramesMode::All => &self.display_bind_groups[self.current],
+            DisplayFramesMode::Evens => &self.display_bind_groups[1],
+            DisplayFramesMode::Odd => &self.display_bind_groups[0],
+        }
+    }
+
+    pub fn kernel(&self) -> [f32; 9] { self.simulation_data.uniform.get_kernel_as_slice() }
+
+    pub fn set_kernel(&mut self, kernel: [f32; 9]) {
+        self.simulation_data.uniform.set_kernel_from_slice(kernel);
+        self.simulation_data.need_update = true;
+    }
+
+    pub fn boundary_condition(&self) -> BoundaryCondition { self.simulation_data.uniform.boundary_condition() }
+
+    pub fn set_boundary_condition(&mut self, boundary_condition: BoundaryCondition) {
+        let sampler = create_simulation_sampler(&self.device, boundary_condition);
+        self.bind_groups = [
+            create_texture_bind_group(&self.device, &self.texture_bind_group_layout, &self.views[0], &sampler, Some("Simulation ping bind group")),
+            create_texture_bind_group(&self.device, &self.texture_bind_group_layout, &self.views[1], &sampler, Some("Simulation pong bind group")),
+        ];
+        self.simulation_data.uniform.set_boundary_condition(boundary_condition);
+        self.simulation_data.need_update = true;
+    }
+
+    pub fn lattice(&self) -> Lattice { self.simulation_data.uniform.lattice() }
+
+    /// The kernel cells without neighbour on the new lattice are cleared.
+    /// The grid is resized (and its state lost) when its size is not supported by the lattice.
+    pub fn set_lattice(&mut self, lattice: Lattice) -> Result<(), wgpu::Error> {
+        self.simulation_data.uniform.set_lattice(lattice);
+        self.simulation_data.need_update = true;
+        if lattice.grid_size(self.size) != self.size {
+            self.resize(self.size)?;
+        }
+        Ok(())
+    }
+
+    pub fn activation_code(&self) -> &str { &self.activation_code }
+
+    /// Recompile the simulation shader, the previous one is kept on failure
+    pub fn set_activation(&mut self, activation_code: &str) -> Result<(), wgpu::Error> {
+        let simulation_render_pipeline = capture_validation_error(&self.device, || {
+            let simulation_shader = self.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("Simulation Shader"),
+                source: wgpu::ShaderSource::Wgsl(generate_simulation_shader(activation_code).into()),
+            });
+
+            build_simulation_pipeline(
+                &self.device,
+                SIMULATION_TEXTURE_FORMAT,
+                &fullscreen_primitive_state(),
+                &wgpu::MultisampleState::default(),
+                &self.screen_shader,
+                &simulation_shader,
+                &self.texture_bind_group_layout,
+                &self.simulation_data,
+            )
+        })?;
+
+        self.simulation_render_pipeline = simulation_render_pipeline;
+        self.activation_code = activation_code.to_owned();
+        Ok(())
+    }
+
+    /// Restart from the initial state described by `parameters`
+    pub fn init(&mut self, parameters: InitSimulationUniforms) {
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Simulation init encoder") });
+        self.encode_init(&mut encoder, parameters);
+        self.queue.submit(Some(encoder.finish()));
+    }
+
+    /// Same as `init`, in an encoder submitted by the caller
+    pub fn encode_init(&mut self, encoder: &mut wgpu::CommandEncoder, parameters: InitSimulationUniforms) {
+        self.init_simulation_data.uniform = parameters;
+        self.init_simulation_data.update(&self.queue);
+
+        {
+            let mut init_simulation_render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Init Simulation Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.views[self.current],
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                    depth_slice: None,
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+            init_simulation_render_pass.set_pipeline(&self.init_simulation_render_pipeline);
+            init_simulation_render_pass.set_bind_group(0, &self.init_simulation_data.bind_group, &[]);
+            init_simulation_render_pass.draw(0..3, 0..1);
+        }
+
+        self.generation = 0;
+    }
+
+    pub fn step(&mut self, generations: u32) {
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Simulation encoder") });
+        self.encode_step(&mut encoder, generations);
+        self.queue.submit(Some(encoder.finish()));
+    }
+
+    /// Same as `step`, in an encoder submitted by the caller
+    pub fn encode_step(&mut self, encoder: &mut wgpu::CommandEncoder, generations: u32) {
+        if self.simulation_data.need_update {
+            self.simulation_data.update(&self.queue);
+        }
+
+        for _ in 0..generations {
+            let target = 1 - self.current;
+            {
+                let mut simulation_render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Simulation Render Pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &self.views[target],
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                            store: wgpu::StoreOp::Store,
+                        },
+                        depth_slice: None,
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+
+                simulation_render_pass.set_pipeline(&self.simulation_render_pipeline);
+                simulation_render_pass.set_bind_group(0, &self.bind_groups[self.current], &[]);
+                simulation_render_pass.set_bind_group(1, &self.simulation_data.bind_group, &[]);
+                simulation_render_pass.draw(0..3, 0..1);
+            }
+            self.current = target;
+        }
+
+        self.generation += generations;
+    }
+
+    /// Draw the displayed state into `target` (a view of a texture of `target_size` pixels in `target_format`) through the screen shader,
+    /// with the framing of `view_parameters` kept in the target and the generations shown by the viewport with `display_frames_mode`
+    pub fn render_to(
+        &mut self,
+        target: &wgpu::TextureView,
+        target_size: [u32; 2],
+        target_format: wgpu::TextureFormat,
+        view_parameters: &ViewParameters,
+        display_frames_mode: &DisplayFramesMode,
+    ) {
+        if !matches!(&self.screen_render_pipeline, Some((format, _)) if *format == target_format) {
+            let screen_render_pipeline = build_screen_pipeline(
+                &self.device,
+                target_format,
+                &fullscreen_primitive_state(),
+                &wgpu::MultisampleState::default(),
+                &self.screen_shader,
+                &self.texture_bind_group_layout,
+                &self.view_data,
+            );
+            self.screen_render_pipeline = Some((target_format, screen_render_pipeline));
+        }
+
+        // The cells are always drawn with the lattice of the simulation
+        let resized = view_parameters.resized(Vec2::new(target_size[0] as f32, target_size[1] as f32), Vec2::new(self.size[0] as f32, self.size[1] as f32));
+        self.view_data.uniform = ViewParameters { lattice: self.lattice().shader_mode(), ..resized };
+        self.view_data.update(&self.queue);
+
+        let Some((_, screen_render_pipeline)) = &self.screen_render_pipeline else { return };
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Simulation screen encoder") });
+        {
+            let mut screen_render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Screen Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                    depth_slice: None,
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+            screen_render_pass.set_pipeline(screen_render_pipeline);
+            screen_render_pass.set_bind_group(0, self.display_bind_group(display_frames_mode), &[]);
+            screen_render_pass.set_bind_group(1, &self.view_data.bind_group, &[]);
+            screen_render_pass.draw(0..3, 0..1);
+        }
+        self.queue.submit(Some(encoder.finish()));
+    }
+
+    /// Blocking statistics of the current state compared to the previous generation
+    pub fn measure(&self, statistics_pass: &mut StatisticsPass) -> GenerationStatistics {
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Simulation statistics encoder") });
+        statistics_pass.reserve(&self.device, 1);
+        let (current, previous) = (&self.views[self.current], &self.views[1 - self.current]);
+        statistics_pass.encode(&self.device, &mut encoder, current, previous, self.size, self.generation as u64);
+        self.queue.submit(Some(encoder.finish()));
+        statistics_pass.read(&self.device).pop().expect("One reduction was recorded")
+    }
+
+    /// Blocking copy of the current state to the CPU
+    pub fn read_state(&self) -> StateSnapshot { read_texture(&self.device, &self.queue, &self.textures[self.current], self.size) }
+
+    /// Replace the current state, `state` must have the size of the simulation
+    pub fn write_state(&mut self, state: &StateSnapshot) {
+        let [width, height] = self.size;
+        // Rgba to Bgra
+        let mut bgra = state.rgba.clone();
+        for texel in bgra.chunks_exact_mut(4) {
+            texel.swap(0, 2);
+        }
+
+        self.queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &self.textures[self.current],
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &bgra,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(width * 4),
+                rows_per_image: Some(height),
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    /// Set every channel of the cells within `radius` of `center` (row 0 at the bottom) to `value`.
+    /// The disc wraps around the grid with the Wrap boundary condition and is clipped by its borders otherwise.
+    pub fn paint_disc(&mut self, center: [i32; 2], radius: f32, value: f32) {
+        let mut state = self.read_state();
+        let [width, height] = self.size.map(|x| x as i32);
+        let wrap = self.boundary_condition() == BoundaryCondition::Wrap;
+        let byte = (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+        let radius = radius.max(0.0);
+        let extent = radius.floor() as i32;
+        for dy in -extent..=extent {
+            for dx in -extent..=extent {
+                if (dx * dx + dy * dy) as f32 > radius * radius {
+                    continue;
+                }
+
+                let (mut x, mut y) = (center[0] + dx, center[1] + dy);
+                if wrap {
+                    x = x.rem_euclid(width);
+                    y = y.rem_euclid(height);
+                } else if x < 0 || y < 0 || x >= width || y >= height {
+                    continue;
+                }
+
+                let index = ((y * width + x) * 4) as usize;
+                state.rgba[index..index + 3].fill(byte);
+                state.rgba[index + 3] = 255;
+            }
+        }
+
+        self.write_state(&state);
+    }
+
+    /// Add a gaussian noise of standard deviation `std_dev` to the color channels of every cell, drawn from `seed`
+    pub fn perturb_state(&mut self, std_dev: f32, seed: u64) -> Result<(), rand_distr::NormalError> {
+        let noise = Normal::new(0.0, std_dev.abs() * 255.0)?;
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut state = self.read_state();
+        for texel in state.rgba.chunks_exact_mut(4) {
+            for channel in &mut texel[..3] {
+                *channel = (*channel as f32 + noise.sample(&mut rng)).round().clamp(0.0, 255.0) as u8;
+            }
+        }
+
+        self.write_state(&state);
+        Ok(())
+    }
+
+    /// Copy of the current state to the CPU, received later without blocking
+    pub fn request_state(&self) -> StateReadback { StateReadback::new(&self.device, &self.queue, &self.textures[self.current], self.size) }
+}
+
+// Ping pong textures of a simulation, with their bind groups for the simulation and the display samplers
+fn create_textures(
+    device: &wgpu::Device,
+    texture_bind_group_layout: &wgpu::BindGroupLayout,
+    size: [u32; 2],
+    boundary_condition: BoundaryCondition,
+) -> ([wgpu::Texture; 2], [wgpu::TextureView; 2], [wgpu::BindGroup; 2], [wgpu::BindGroup; 2]) {
+    let texture_descriptor = get_texture_descriptor(&size);
+    let textures = [device.create_texture(&texture_descriptor), device.create_texture(&texture_descriptor)];
+    let views = [
+        textures[0].create_view(&wgpu::TextureViewDescriptor::default()),
+        textures[1].create_view(&wgpu::TextureViewDescriptor::default()),
+    ];
+
+    let sampler = create_simulation_sampler(device, boundary_condition);
+    let display_sampler = create_display_sampler(device);
+    let bind_groups = [
+        create_texture_bind_group(device, texture_bind_group_layout, &views[0], &sampler, Some("Simulation ping bind group")),
+        create_texture_bind_group(device, texture_bind_group_layout, &views[1], &sampler, Some("Simulation pong bind group")),
+    ];
+    let display_bind_groups = [
+        create_texture_bind_group(device, texture_bind_group_layout, &views[0], &display_sampler, Some("Display ping bind group")),
+        create_texture_bind_group(device, texture_bind_group_layout, &views[1], &display_sampler, Some("Display pong bind group")),
+    ];
+
+    (textures, views, bind_groups, display_bind_groups)
+}
+
+/// Blocking read back of a simulation texture (in SIMULATION_TEXTURE_FORMAT)
+pub fn read_texture(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture, size: [u32; 2]) -> StateSnapshot {
+    snapshot_from_bgra(read_texture_bytes(device, queue, texture, size, 4), size)
+}
+
+fn snapshot_from_bgra(mut rgba: Vec<u8>, size: [u32; 2]) -> StateSnapshot {
+    // Bgra to Rgba
+    for texel in rgba.chunks_exact_mut(4) {
+        texel.swap(0, 2);
+    }
+
+    StateSnapshot { size, rgba }
+}
+
+/// Blocking read back of the texels of any texture, rows are tightly packed (without the copy alignment)
+pub fn read_texture_bytes(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture, size: [u32; 2], bytes_per_texel: u32) -> Vec<u8> {
+    let read_back_buffer = ReadBackBuffer::copy(device, queue, texture, size, bytes_per_texel);
+
+    read_back_buffer.buffer.slice(..).map_async(wgpu::MapMode::Read, |result| result.expect("Unable to map the read back buffer"));
+    device.poll(wgpu::PollType::wait_indefinitely()).expect("Unable to wait for the read back");
+
+    read_back_buffer.bytes()
+}
+
+// Copy of a texture in a mappable buffer, with the rows padded to the copy alignment
+struct ReadBackBuffer {
+    buffer: wgpu::Buffer,
+    height: u32,
+    unpadded_bytes_per_row: u32,
+    padded_bytes_per_row: u32,
+}
+
+impl ReadBackBuffer {
+    fn copy(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture, size: [u32; 2], bytes_per_texel: u32) -> Self {
+        let [width, height] = size;
+        let unpadded_bytes_per_row = width * bytes_per_texel;
+        let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT) * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Texture read back buffer"),
+            size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Read back encoder") });
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        queue.submit(Some(encoder.finish()));
+
+        Self { buffer, height, unpadded_bytes_per_row, padded_bytes_per_row }
+    }
+
+    // The buffer must be mapped
+    fn bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity((self.unpadded_bytes_per_row * self.height) as usize);
+        {
+            let data = self.buffer.slice(..).get_mapped_range();
+            for row in data.chunks_exact(self.padded_bytes_per_row as usize) {
+                bytes.extend_from_slice(&row[..self.unpadded_bytes_per_row as usize]);
+            }
+        }
+        self.buffer.unmap();
+
+        bytes
+    }
+}
+
+/// Read back of a simulation state that does not wait for the GPU, see `Simulation::request_state`
+pub struct StateReadback {
+    read_back_buffer: ReadBackBuffer,
+    size: [u32; 2],
+    receiver: mpsc::Receiver<Result<(), wgpu::BufferAsyncError>>,
+}
+
+impl StateReadback {
+    fn new(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture, size: [u32; 2]) -> Self {
+        let read_back_buffer = ReadBackBuffer::copy(device, queue, texture, size, 4);
+        let (sender, receiver) = mpsc::channel();
+        read_back_buffer.buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            // The readback may have been dropped in the meantime
+            let _ = sender.send(result);
+        });
+
+        Self { read_back_buffer, size, receiver }
+    }
+
+    /// The state once the copy is done, None while the GPU is busy.
+    /// The device must be polled (`wgpu::PollType::Poll` is enough) for the copy to complete.
+    pub fn try_receive(&self) -> Option<Result<StateSnapshot, wgpu::BufferAsyncError>> {
+        match self.receiver.try_recv().ok()? {
+            Ok(()) => Some(Ok(snapshot_from_bgra(self.read_back_buffer.bytes(), self.size))),
+            Err(error) => Some(Err(error)),
+        }
+    }
+}
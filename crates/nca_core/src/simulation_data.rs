@@ -1,36 +1,28 @@
 use rand::Rng;
-use serde::{Deserialize, Serialize};
 use crevice::std140::AsStd140;
 use glam::{Vec2, Mat3};
 
 use oxyde::wgpu as wgpu;
 
 use wgpu::util::DeviceExt;
+
+use super::{
+    boundary_condition::BoundaryCondition,
+    kernel_format::{self, KernelFormat},
+    kernel_symmetry::KernelSymmetryMode,
+    lattice::Lattice,
+};
+
+pub const KERNEL_SIZE: usize = 3;
+
 #[repr(C)]
 #[derive(Debug, Copy, Clone, AsStd140)]
 pub struct SimulationUniforms {
     pixel_size: Vec2,
     kernel: Mat3,
-}
-
-#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq)]
-pub enum KernelSymmetryMode {
-    Any,
-    Vertical,
-    Horizontal,
-    Full,
-}
-
-// TODO: learn to make macro for that
-impl std::fmt::Display for KernelSymmetryMode {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        match self {
-            KernelSymmetryMode::Any => write!(f, "Any"),
-            KernelSymmetryMode::Vertical => write!(f, "Vertical"),
-            KernelSymmetryMode::Horizontal => write!(f, "Horizontal"),
-            KernelSymmetryMode::Full => write!(f, "Full"),
-        }
-    }
+    boundary_mode: u32,
+    boundary_value: f32,
+    lattice: u32,
 }
 
 impl SimulationUniforms {
@@ -38,9 +30,30 @@ impl SimulationUniforms {
         Self {
             pixel_size: Vec2::from_slice(&simulation_size.map(|x| 1.0 / x as f32)),
             kernel: Mat3::from_cols_array(&[1.0, 1.0, 1.0, 1.0, 9.0, 1.0, 1.0, 1.0, 1.0]),
+            boundary_mode: BoundaryCondition::Wrap.shader_mode(),
+            boundary_value: 0.0,
+            lattice: Lattice::Square.shader_mode(),
         }
     }
 
+    pub fn lattice(&self) -> Lattice { Lattice::from_shader_mode(self.lattice) }
+
+    /// The kernel cells without neighbour on the new lattice are cleared
+    pub fn set_lattice(&mut self, lattice: Lattice) {
+        self.lattice = lattice.shader_mode();
+        let mut kernel = self.get_kernel_as_slice();
+        lattice.mask(&mut kernel);
+        self.set_kernel_from_slice(kernel);
+    }
+
+    pub fn boundary_condition(&self) -> BoundaryCondition { BoundaryCondition::from_shader_mode(self.boundary_mode, self.boundary_value) }
+
+    // The simulation sampler must be created with the same boundary condition
+    pub fn set_boundary_condition(&mut self, boundary_condition: BoundaryCondition) {
+        self.boundary_mode = boundary_condition.shader_mode();
+        self.boundary_value = boundary_condition.constant_value();
+    }
+
     // row-major order (with transpose)
     pub fn get_kernel_as_slice(&self) -> [f32; 9] { self.kernel.transpose().to_cols_array() }
 
@@ -51,69 +64,43 @@ impl SimulationUniforms {
         *self.get_kernel_at_mut(col, row) = value;
     }
 
+    // row-major order (with transpose), same layout as get_kernel_as_slice
     pub fn set_kernel_from_slice(&mut self, new_kernel: [f32; 9]) {
-        self.kernel = Mat3::from_cols_slice(&new_kernel);
+        self.kernel = Mat3::from_cols_slice(&new_kernel).transpose();
     }
 
+    pub fn kernel_to_string(&self, format: KernelFormat) -> String { format.format(&self.get_kernel_as_slice()) }
+
+    // Parse a kernel in any of the supported formats and reject it if it does not respect the given symmetry mode
+    pub fn set_kernel_from_str(&mut self, text: &str, mode: KernelSymmetryMode) -> anyhow::Result<()> {
+        self.set_kernel_checked(kernel_format::parse_kernel(text)?, mode)
+    }
+
+    pub fn set_kernel_checked(&mut self, new_kernel: [f32; 9], mode: KernelSymmetryMode) -> anyhow::Result<()> {
+        let mut candidate = *self;
+        candidate.set_kernel_from_slice(new_kernel);
+        anyhow::ensure!(candidate.is_kernel_symmetric(mode), "The kernel does not respect the `{}` symmetry mode", mode);
+        *self = candidate;
+        Ok(())
+    }
+
+    pub fn is_kernel_symmetric(&self, mode: KernelSymmetryMode) -> bool { self.lattice().is_symmetric(&self.get_kernel_as_slice(), mode) }
+
     pub fn set_kernel_at_with_symmetry(&mut self, col: usize, row: usize, value: f32, mode: KernelSymmetryMode) {
         self.set_kernel_at(col, row, value);
         self.apply_symmetry_at(col, row, mode);
     }
 
-
     pub fn apply_symmetry(&mut self, mode: KernelSymmetryMode) {
-        const N: usize = 3;
-        const HALF_IDX: usize = (N + 1) / 2 - 1;
-
-        match mode {
-            KernelSymmetryMode::Any => (),
-            KernelSymmetryMode::Vertical =>
-                for row in 0..N {
-                    for col in 0..HALF_IDX {
-                        self.set_kernel_at(N - 1 - col, row, self.get_kernel_at(col, row));
-                    }
-                },
-            KernelSymmetryMode::Horizontal =>
-                for col in 0..N {
-                    for row in 0..HALF_IDX {
-                        self.set_kernel_at(col, N - 1 - row, self.get_kernel_at(col, row));
-                    }
-                },
-            KernelSymmetryMode::Full => {
-                self.apply_symmetry(KernelSymmetryMode::Horizontal);
-                self.apply_symmetry(KernelSymmetryMode::Vertical);
-            },
-        }
+        let mut kernel = self.get_kernel_as_slice();
+        self.lattice().apply_symmetry(&mut kernel, mode);
+        self.set_kernel_from_slice(kernel);
     }
 
     fn apply_symmetry_at(&mut self, col: usize, row: usize, mode: KernelSymmetryMode) {
-        const N: usize = 3;
-        const HALF_IDX: usize = (N + 1) / 2 - 1;
-
-        if row == HALF_IDX && col == HALF_IDX { return; } // center 
-
-        let value = self.get_kernel_at(col, row);
-        match mode {
-            KernelSymmetryMode::Any => (),
-            KernelSymmetryMode::Vertical => 
-            if col != HALF_IDX {
-                *self.get_kernel_at_mut(N - 1 - col, row) = value
-            }
-            KernelSymmetryMode::Horizontal =>
-            if row != HALF_IDX {
-                *self.get_kernel_at_mut(col, N - 1 - row) = value
-            },
-            KernelSymmetryMode::Full => {
-                
-                *self.get_kernel_at_mut(col, N - 1 - row) = value;
-                *self.get_kernel_at_mut(N - 1 - col, row) = value;
-                *self.get_kernel_at_mut(N - 1 - col, N - 1 - row) = value;
-
-                *self.get_kernel_at_mut(row, col) = value;
-                *self.get_kernel_at_mut(N - 1 - row, col) = value;
-                *self.get_kernel_at_mut(row, N - 1 - col) = value;
-            },
-        }
+        let mut kernel = self.get_kernel_as_slice();
+        self.lattice().apply_symmetry_at(&mut kernel, col, row, mode);
+        self.set_kernel_from_slice(kernel);
     }
 }
 
@@ -131,6 +118,13 @@ impl InitSimulationUniforms {
             initialisation_mode: 0,
         }
     }
+
+    pub fn from_seed(seed: f32) -> Self {
+        Self {
+            seed,
+            initialisation_mode: 0,
+        }
+    }
 }
 
 pub fn initialisation_mode_to_string(mode: u32) -> &'static str {
@@ -0,0 +1,233 @@
+use oxyde::wgpu as wgpu;
+
+pub const HISTOGRAM_BINS: usize = 256;
+const WORKGROUP_SIZE: u32 = 16;
+const STATISTICS_BUFFER_SIZE: wgpu::BufferAddress = ((3 + HISTOGRAM_BINS) * std::mem::size_of::<u32>()) as wgpu::BufferAddress;
+
+/// Statistics of the displayed channel of one generation.
+/// Values are stored on 8 bits, so every statistic is exactly derived from the 256 bins histogram.
+#[derive(Debug, Clone)]
+pub struct GenerationStatistics {
+    pub generation: u64,
+    pub min: f32,
+    pub max: f32,
+    pub mean: f32,
+    pub variance: f32,
+    /// Fraction of cells with a channel changed since the previous generation
+    pub changed_fraction: f32,
+    /// Position weighted hash of the four channels of the state, equal for identical states
+    pub hash: u64,
+    pub histogram: [u32; HISTOGRAM_BINS],
+}
+
+impl GenerationStatistics {
+    pub fn from_histogram(generation: u64, histogram: [u32; HISTOGRAM_BINS], changed: u32, hash: u64) -> Self {
+        let bin_value = |bin: usize| bin as f32 / (HISTOGRAM_BINS - 1) as f32;
+        let count: f64 = histogram.iter().map(|&bin| bin as f64).sum();
+
+        let min = histogram.iter().position(|&bin| bin > 0).map_or(0.0, bin_value);
+        let max = histogram.iter().rposition(|&bin| bin > 0).map_or(0.0, bin_value);
+
+        let (mean, variance) = if count > 0.0 {
+            let mean: f64 = histogram.iter().enumerate().map(|(bin, &n)| n as f64 * bin_value(bin) as f64).sum::<f64>() / count;
+            let variance: f64 = histogram.iter().enumerate().map(|(bin, &n)| n as f64 * (bin_value(bin) as f64 - mean).powi(2)).sum::<f64>() / count;
+            (mean as f32, variance as f32)
+        } else {
+            (0.0, 0.0)
+        };
+
+        Self {
+            generation,
+            min,
+            max,
+            mean,
+            variance,
+            changed_fraction: if count > 0.0 { (changed as f64 / count) as f32 } else { 0.0 },
+            hash,
+            histogram,
+        }
+    }
+}
+
+/// Compute pass reducing a simulation texture to its statistics.
+/// Several generations can be reduced in the same submission, each one in its own slot of the buffers.
+pub struct StatisticsPass {
+    pipeline: wgpu::ComputePipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    statistics_buffer: wgpu::Buffer,
+    read_back_buffer: wgpu::Buffer,
+    // Aligned for the storage buffer bindings
+    slot_stride: wgpu::BufferAddress,
+    slot_count: usize,
+    // Generations of the reductions recorded since the last read
+    recorded: Vec<u64>,
+}
+
+fn create_buffers(device: &wgpu::Device, size: wgpu::BufferAddress) -> (wgpu::Buffer, wgpu::Buffer) {
+    let statistics_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Statistics Buffer"),
+        size,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    let read_back_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Statistics read back Buffer"),
+        size,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    (statistics_buffer, read_back_buffer)
+}
+
+impl StatisticsPass {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let texture_entry = |binding: u32| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                texture_entry(0),
+                texture_entry(1),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("Statistics bind group layout"),
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Statistics Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/statistics.wgsl").into()),
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Statistics Pipeline"),
+            layout: Some(&device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Statistics Pipeline Layout"),
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[],
+            })),
+            module: &shader,
+            entry_point: "main",
+        });
+
+        let slot_stride = STATISTICS_BUFFER_SIZE.next_multiple_of(device.limits().min_storage_buffer_offset_alignment as wgpu::BufferAddress);
+        let (statistics_buffer, read_back_buffer) = create_buffers(device, slot_stride);
+
+        Self {
+            pipeline,
+            bind_group_layout,
+            statistics_buffer,
+            read_back_buffer,
+            slot_stride,
+            slot_count: 1,
+            recorded: Vec::new(),
+        }
+    }
+
+    /// Make room for `count` reductions in the next submission, to be called before recording them
+    pub fn reserve(&mut self, device: &wgpu::Device, count: usize) {
+        assert!(self.recorded.is_empty(), "The buffers of the recorded reductions cannot be replaced");
+        if count > self.slot_count {
+            self.slot_count = count.next_power_of_two();
+            (self.statistics_buffer, self.read_back_buffer) = create_buffers(device, self.slot_count as wgpu::BufferAddress * self.slot_stride);
+        }
+    }
+
+    /// Record the reduction of `current` (compared to `previous`) and the copy of its result, to be read with `read` once submitted
+    pub fn encode(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        current: &wgpu::TextureView,
+        previous: &wgpu::TextureView,
+        size: [u32; 2],
+        generation: u64,
+    ) {
+        assert!(self.recorded.len() < self.slot_count, "No slot left for the reduction, `reserve` must be called first");
+        let offset = self.recorded.len() as wgpu::BufferAddress * self.slot_stride;
+        self.recorded.push(generation);
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(current),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(previous),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: &self.statistics_buffer,
+                        offset,
+                        size: wgpu::BufferSize::new(STATISTICS_BUFFER_SIZE),
+                    }),
+                },
+            ],
+            label: Some("Statistics bind group"),
+        });
+
+        encoder.clear_buffer(&self.statistics_buffer, offset, Some(STATISTICS_BUFFER_SIZE));
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Statistics Compute Pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_pipeline(&self.pipeline);
+            compute_pass.set_bind_group(0, &bind_group, &[]);
+            compute_pass.dispatch_workgroups(size[0].div_ceil(WORKGROUP_SIZE), size[1].div_ceil(WORKGROUP_SIZE), 1);
+        }
+        encoder.copy_buffer_to_buffer(&self.statistics_buffer, offset, &self.read_back_buffer, offset, STATISTICS_BUFFER_SIZE);
+    }
+
+    /// Blocking read of the reductions recorded since the last read, once submitted, in recording order
+    pub fn read(&mut self, device: &wgpu::Device) -> Vec<GenerationStatistics> {
+        let recorded = std::mem::take(&mut self.recorded);
+        if recorded.is_empty() {
+            return Vec::new();
+        }
+
+        let buffer_slice = self.read_back_buffer.slice(..recorded.len() as wgpu::BufferAddress * self.slot_stride);
+        buffer_slice.map_async(wgpu::MapMode::Read, |result| result.expect("Unable to map the statistics buffer"));
+        device.poll(wgpu::PollType::wait_indefinitely()).expect("Unable to wait for the statistics");
+
+        let statistics = {
+            let data = buffer_slice.get_mapped_range();
+            recorded
+                .iter()
+                .enumerate()
+                .map(|(slot, &generation)| {
+                    let start = slot * self.slot_stride as usize;
+                    let values: &[u32] = bytemuck::cast_slice(&data[start..start + STATISTICS_BUFFER_SIZE as usize]);
+                    let mut histogram = [0u32; HISTOGRAM_BINS];
+                    histogram.copy_from_slice(&values[3..]);
+                    GenerationStatistics::from_histogram(generation, histogram, values[0], (values[1] as u64) << 32 | values[2] as u64)
+                })
+                .collect()
+        };
+        self.read_back_buffer.unmap();
+
+        statistics
+    }
+}
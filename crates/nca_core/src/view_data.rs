@@ -0,0 +1,208 @@
+use crevice::std140::AsStd140;
+use glam::{Vec2, Vec3, Vec4};
+use oxyde::wgpu as wgpu;
+
+use wgpu::util::DeviceExt;
+
+use egui_widgets::IqGradient;
+
+use crate::{aspect_mode::AspectMode, color_map::ColorMapParameters, lattice::Lattice};
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, AsStd140)]
+pub struct ViewParameters {
+    pub center: Vec2,
+    /// Simulation pixels (texels) per screen pixel, horizontally in the stretch mode
+    pub zoom_level: f32,
+    pub gradient: IqGradient,
+    /// Lattice::shader_mode of the displayed simulation
+    pub lattice: u32,
+    /// Cell borders, drawn once a cell is at least `grid_min_cell_size` screen pixels wide
+    pub grid_color: Vec4,
+    /// In screen pixels
+    pub grid_width: f32,
+    pub grid_min_cell_size: f32,
+    pub show_grid: u32,
+    pub color_map: ColorMapParameters,
+    /// Repeats the toroidal grid around the texture instead of clamping it, allows panning without limit
+    /// and zooming out up to `MAX_TILED_ZOOM_LEVEL`
+    pub tile: u32,
+    /// AspectMode::shader_mode
+    pub aspect_mode: u32,
+    /// Rounds the scale to a whole number of screen pixels per texel (or texels per screen pixel) and aligns the texels on the pixels
+    pub pixel_perfect: u32,
+    /// In screen pixels, set by the renderer
+    pub viewport_size: Vec2,
+    /// In texels, set by the renderer
+    pub texture_size: Vec2,
+}
+
+/// Number of grid repetitions visible along each axis when zoomed out the most in tile mode, relative to the fitted grid
+pub const MAX_TILED_ZOOM_LEVEL: f32 = 8.0;
+
+impl Default for ViewParameters {
+    /// The whole grid fitted in the viewport, once resized to the actual sizes
+    fn default() -> Self {
+        Self {
+            center: Vec2::new(0.5, 0.5),
+            zoom_level: 1.0,
+            gradient: IqGradient::default(),
+            lattice: Lattice::Square.shader_mode(),
+            grid_color: Vec4::new(0.1, 0.1, 0.1, 0.8),
+            grid_width: 1.0,
+            grid_min_cell_size: 12.0,
+            show_grid: 1,
+            color_map: ColorMapParameters::default(),
+            tile: 0,
+            aspect_mode: AspectMode::Fit.shader_mode(),
+            pixel_perfect: 0,
+            viewport_size: Vec2::ONE,
+            texture_size: Vec2::ONE,
+        }
+    }
+}
+
+impl ViewParameters {
+    /// Same framing in a viewport of `viewport_size` screen pixels displaying a texture of `texture_size` texels,
+    /// the zoom level is scaled like the fitted one
+    pub fn resized(&self, viewport_size: Vec2, texture_size: Vec2) -> Self {
+        let mut resized = Self { viewport_size, texture_size, ..*self };
+        resized.zoom_level *= resized.fitted_zoom_level() / self.fitted_zoom_level();
+        resized.constrain();
+        resized
+    }
+
+    /// Texels per screen pixel along each axis at which the whole grid is fitted according to the aspect mode
+    fn fitted_scale(&self) -> Vec2 {
+        let ratio = self.texture_size / self.viewport_size;
+        match AspectMode::from_shader_mode(self.aspect_mode) {
+            AspectMode::Fit => Vec2::splat(ratio.max_element()),
+            AspectMode::Fill => Vec2::splat(ratio.min_element()),
+            AspectMode::Stretch => ratio,
+        }
+    }
+
+    /// Zoom level showing the whole grid according to the aspect mode
+    pub fn fitted_zoom_level(&self) -> f32 { self.fitted_scale().x }
+
+    /// Texels per screen pixel along each axis, mirrors texelScale of the screen shader
+    pub fn texel_scale(&self) -> Vec2 {
+        let mut scale = Vec2::splat(self.zoom_level);
+        if AspectMode::from_shader_mode(self.aspect_mode) == AspectMode::Stretch {
+            let fitted_scale = self.fitted_scale();
+            scale = self.zoom_level * fitted_scale / fitted_scale.x;
+        }
+        if self.pixel_perfect != 0 {
+            // Rounded towards the smaller cells, so that a fitted grid is never cropped
+            scale = scale.map(|x| if x <= 1.0 { 1.0 / (1.0 / x).floor() } else { x.ceil() });
+        }
+        scale
+    }
+
+    /// Texel coordinates (first row at the bottom) of the bottom left corner of the viewport, mirrors viewBottomLeft of the screen shader
+    fn bottom_left(&self, scale: Vec2) -> Vec2 {
+        let center = Vec2::new(self.center.x, 1.0 - self.center.y) * self.texture_size;
+        let bottom_left = center - 0.5 * self.viewport_size * scale;
+        if self.pixel_perfect != 0 {
+            (bottom_left / scale).round() * scale
+        } else {
+            bottom_left
+        }
+    }
+
+    /// Fraction of the texture covered by the viewport along each axis
+    pub fn visible_extent(&self) -> Vec2 { self.viewport_size * self.texel_scale() / self.texture_size }
+
+    /// Texture coordinates displayed at `position`, normalized in the viewport (origin at the top left corner).
+    /// Same transform as the screen shader, which displays the first texture row at the bottom.
+    pub fn texture_uv(&self, position: Vec2) -> Vec2 {
+        let scale = self.texel_scale();
+        let pixel = Vec2::new(position.x, 1.0 - position.y) * self.viewport_size;
+        (self.bottom_left(scale) + pixel * scale) / self.texture_size
+    }
+
+    /// Displayed color of a cell whose first channel is `value`
+    pub fn color(&self, value: f32) -> Vec3 { self.color_map.color(&self.gradient, value) }
+
+    /// Largest zoom level, the whole grid without tile mode
+    pub fn max_zoom_level(&self) -> f32 {
+        let fitted_zoom_level = self.fitted_zoom_level();
+        if self.tile != 0 { MAX_TILED_ZOOM_LEVEL * fitted_zoom_level } else { fitted_zoom_level }
+    }
+
+    /// Clamps the zoom level and keeps the view inside the texture (centered along the axes where the whole texture is visible),
+    /// or wraps the center around the torus in tile mode
+    pub fn constrain(&mut self) {
+        self.zoom_level = self.zoom_level.min(self.max_zoom_level());
+        if self.tile != 0 {
+            self.center = self.center.map(|x| x.rem_euclid(1.0));
+        } else {
+            let extent = self.visible_extent();
+            let clamped_center = self.center.min(1. - 0.5 * extent).max(0.5 * extent);
+            self.center = Vec2::select(extent.cmplt(Vec2::ONE), clamped_center, Vec2::splat(0.5));
+        }
+    }
+
+    /// Normalized viewport position where the texture coordinates `uv` are displayed, inverse of `texture_uv`
+    pub fn viewport_position(&self, uv: Vec2) -> Vec2 {
+        let scale = self.texel_scale();
+        let pixel = (uv * self.texture_size - self.bottom_left(scale)) / scale;
+        Vec2::new(pixel.x, self.viewport_size.y - pixel.y) / self.viewport_size
+    }
+}
+
+pub struct ViewData {
+    pub need_update: bool,
+    pub uniform: ViewParameters,
+    pub buffer: wgpu::Buffer,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl ViewData {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let uniform = ViewParameters::default();
+
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("View uniforms Buffer"),
+            contents: uniform.as_std140().as_bytes(),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+            label: Some("View uniforms bind group layout"),
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+            label: Some("View uniforms bind group"),
+        });
+
+        Self {
+            need_update: false,
+            uniform,
+            buffer,
+            bind_group_layout,
+            bind_group,
+        }
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue) {
+        queue.write_buffer(&self.buffer, 0, self.uniform.as_std140().as_bytes());
+        self.need_update = false;
+    }
+}
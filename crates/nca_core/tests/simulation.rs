@@ -0,0 +1,147 @@
+use oxyde::wgpu as wgpu;
+
+use nca_core::{
+    preset::{Preset, PRESETS},
+    BoundaryCondition, CpuSimulation, InitSimulationUniforms, Lattice, Simulation, StateSnapshot,
+};
+
+// Falls back on a software adapter without GPU, so that the tests never pass without running
+fn create_device() -> (wgpu::Device, wgpu::Queue) {
+    let instance = wgpu::Instance::default();
+    let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))
+        .or_else(|_| {
+            pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+                force_fallback_adapter: true,
+                ..Default::default()
+            }))
+        })
+        .expect("No graphics adapter found, not even a software one (lavapipe, llvmpipe or WARP)");
+    pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default())).expect("Unable to create the device")
+}
+
+const BOUNDARY_CONDITIONS: [BoundaryCondition; 5] = [
+    BoundaryCondition::Wrap,
+    BoundaryCondition::Clamp,
+    BoundaryCondition::Mirror,
+    BoundaryCondition::Constant(0.0),
+    BoundaryCondition::Constant(1.0),
+];
+
+// Non-power-of-two sizes, so that no wrapping is hidden by a texture size aligned on the offsets
+const SIZES: [[u32; 2]; 2] = [[37, 23], [100, 61]];
+
+// activationFunction of the "Game Of life" preset
+fn life(kernel_output: [f32; 4]) -> [f32; 4] {
+    let r = if [3.0, 11.0, 12.0].contains(&kernel_output[0]) { 1.0 } else { 0.0 };
+    [r, r, r, 1.0]
+}
+
+const IDENTITY_ACTIVATION: &str = "fn activationFunction(kernelOutput: vec4<f32>) -> vec4<f32> {
+    return vec4<f32>(kernelOutput.x, kernelOutput.x, kernelOutput.x, 1.0);
+}";
+
+fn identity(kernel_output: [f32; 4]) -> [f32; 4] { [kernel_output[0], kernel_output[0], kernel_output[0], 1.0] }
+
+// Largest difference between the channels of two states of the same size
+fn max_difference(a: &StateSnapshot, b: &StateSnapshot) -> u8 { a.rgba.iter().zip(&b.rgba).map(|(a, b)| a.abs_diff(*b)).max().unwrap_or(0) }
+
+#[test]
+fn default_preset_steps() {
+    let (device, queue) = create_device();
+
+    let mut simulation = Simulation::new(&device, &queue, [32, 32], &Preset::default()).expect("The default preset compiles");
+    simulation.init(InitSimulationUniforms::from_seed(0.5));
+    simulation.step(1);
+
+    assert_eq!(simulation.generation, 1);
+    assert_eq!(simulation.read_state().texel_count(), 32 * 32);
+}
+
+#[test]
+fn gpu_matches_cpu_reference() {
+    let (device, queue) = create_device();
+
+    // The game of life keeps the states at 0 or 1 and its kernel sums exactly, so the GPU and the CPU agree bit for bit
+    for size in SIZES {
+        for lattice in Lattice::ALL {
+            for boundary_condition in BOUNDARY_CONDITIONS {
+                let preset = Preset { boundary_condition, lattice, ..PRESETS["Game Of life"].clone() };
+                let cpu_simulation = CpuSimulation::new(preset.kernel, boundary_condition, lattice);
+                let mut simulation = Simulation::new(&device, &queue, size, &preset).expect("The game of life preset compiles");
+                simulation.init(InitSimulationUniforms::from_seed(0.37));
+
+                let mut expected = simulation.read_state();
+                for generation in 1..=8 {
+                    simulation.step(1);
+                    expected = cpu_simulation.step(&expected, life);
+                    assert!(
+                        simulation.read_state().rgba == expected.rgba,
+                        "The GPU state differs from the CPU reference ({:?}, {}, {}, generation {})",
+                        size,
+                        lattice,
+                        boundary_condition,
+                        generation
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn continuous_kernel_matches_cpu_reference() {
+    let (device, queue) = create_device();
+
+    // Asymmetric weights on continuous states: blending a cell with its neighbours, as linear filtering did,
+    // or reading the kernel in another orientation moves the values by several quantization steps.
+    // The GPU may round the products differently, hence one step of tolerance.
+    let kernel = [0.05, 0.1, 0.15, 0.2, 0.0, 0.1, 0.05, 0.15, 0.2];
+    for size in SIZES {
+        for lattice in Lattice::ALL {
+            for boundary_condition in BOUNDARY_CONDITIONS {
+                let preset = Preset {
+                    kernel,
+                    activation_code: IDENTITY_ACTIVATION.to_owned(),
+                    boundary_condition,
+                    lattice,
+                    ..Preset::default()
+                };
+                let cpu_simulation = CpuSimulation::new(kernel, boundary_condition, lattice);
+                let mut simulation = Simulation::new(&device, &queue, size, &preset).expect("The identity activation compiles");
+                // Unrounded random states
+                simulation.init(InitSimulationUniforms {
+                    seed: 0.37,
+                    initialisation_mode: 1,
+                });
+
+                for generation in 1..=8 {
+                    let expected = cpu_simulation.step(&simulation.read_state(), identity);
+                    simulation.step(1);
+                    let difference = max_difference(&simulation.read_state(), &expected);
+                    assert!(
+                        difference <= 1,
+                        "The GPU state differs from the CPU reference by {} ({:?}, {}, {}, generation {})",
+                        difference,
+                        size,
+                        lattice,
+                        boundary_condition,
+                        generation
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn lattices_round_the_grid_size() {
+    let (device, queue) = create_device();
+
+    for lattice in Lattice::ALL {
+        let preset = Preset { lattice, ..Preset::default() };
+        let mut simulation = Simulation::new(&device, &queue, [37, 23], &preset).expect("The default preset compiles");
+        assert_eq!(simulation.size(), lattice.grid_size([37, 23]));
+        simulation.resize([41, 61]).expect("The textures are created");
+        assert_eq!(simulation.read_state().size, lattice.grid_size([41, 61]));
+    }
+}
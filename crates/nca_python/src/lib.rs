@@ -0,0 +1,165 @@
+use numpy::{ndarray::Array, AllowTypeChange, IntoPyArray, PyArray2, PyArray3, PyArrayLikeDyn};
+use pyo3::{
+    exceptions::{PyRuntimeError, PyValueError},
+    prelude::*,
+};
+use rand::Rng;
+
+use nca_core::{
+    generate_simulation_shader,
+    preset::{self, Preset, PRESETS},
+    simulation_data::KERNEL_SIZE,
+    wgpu,
+    InitSimulationUniforms,
+};
+
+fn create_device(software: bool) -> PyResult<(wgpu::Device, wgpu::Queue)> {
+    let instance = wgpu::Instance::default();
+    let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        force_fallback_adapter: software,
+        ..Default::default()
+    }))
+    .map_err(|error| PyRuntimeError::new_err(format!("No suitable graphics adapter found: {}", error)))?;
+
+    pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default()))
+        .map_err(|error| PyRuntimeError::new_err(format!("Unable to create the device: {}", error)))
+}
+
+// Either the name of a built-in preset or the path of a preset file
+fn find_preset(preset: &str) -> PyResult<Preset> {
+    match PRESETS.get(preset) {
+        Some(preset) => Ok(preset.clone()),
+        None => preset::load_preset(preset).map_err(|error| PyValueError::new_err(format!("{:?}", error))),
+    }
+}
+
+/// Neural cellular automaton simulated on its own wgpu device
+#[pyclass(unsendable)]
+struct Simulation {
+    simulation: nca_core::Simulation,
+}
+
+#[pymethods]
+impl Simulation {
+    /// Simulation((width, height), preset=None, software=False)
+    ///
+    /// `preset` is the name of a built-in preset or the path of a preset file.
+    /// The hexagonal and triangular lattices round the size up to even, see the `size` property.
+    /// `software` requests a fallback (cpu) adapter, for machines without gpu.
+    #[new]
+    #[pyo3(signature = (size, preset = None, software = false))]
+    fn new(size: (u32, u32), preset: Option<&str>, software: bool) -> PyResult<Self> {
+        let preset = match preset {
+            Some(preset) => find_preset(preset)?,
+            None => Preset::default(),
+        };
+        let (device, queue) = create_device(software)?;
+
+        let mut simulation = nca_core::Simulation::new(&device, &queue, [size.0, size.1], &preset).map_err(|error| PyValueError::new_err(error.to_string()))?;
+        simulation.init(InitSimulationUniforms::new());
+        Ok(Self { simulation })
+    }
+
+    #[getter]
+    fn size(&self) -> (u32, u32) {
+        let [width, height] = self.simulation.size();
+        (width, height)
+    }
+
+    #[getter]
+    fn generation(&self) -> u32 { self.simulation.generation }
+
+    /// Use the kernel, the activation, the boundary condition and the lattice of a built-in preset or of a preset file.
+    /// The grid is resized and initialised again when the lattice does not support its size.
+    fn load_preset(&mut self, preset: &str) -> PyResult<()> {
+        let preset = find_preset(preset)?;
+        self.set_activation(&preset.activation_code)?;
+        let size = self.simulation.size();
+        self.simulation.set_lattice(preset.lattice).map_err(|error| PyValueError::new_err(error.to_string()))?;
+        if self.simulation.size() != size {
+            self.simulation.init(InitSimulationUniforms::new());
+        }
+        self.simulation.set_kernel(preset.kernel);
+        self.simulation.set_boundary_condition(preset.boundary_condition);
+        Ok(())
+    }
+
+    /// Kernel as a 3x3 array, indexed by [row, column]
+    #[getter]
+    fn kernel<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray2<f32>> {
+        Array::from_shape_vec((KERNEL_SIZE, KERNEL_SIZE), self.simulation.kernel().to_vec())
+            .expect("The kernel is square")
+            .into_pyarray(py)
+    }
+
+    /// Accepts any array of 9 values (3x3 or flat, in row major order)
+    #[setter]
+    fn set_kernel(&mut self, kernel: PyArrayLikeDyn<'_, f32, AllowTypeChange>) -> PyResult<()> {
+        let values: Vec<f32> = kernel.as_array().iter().copied().collect();
+        let kernel: [f32; KERNEL_SIZE * KERNEL_SIZE] = values
+            .try_into()
+            .map_err(|values: Vec<f32>| PyValueError::new_err(format!("Expected {} kernel values, got {}", KERNEL_SIZE * KERNEL_SIZE, values.len())))?;
+        self.simulation.set_kernel(kernel);
+        Ok(())
+    }
+
+    /// WGSL code of the `activationFunction`, inserted in the simulation shader
+    #[getter]
+    fn activation(&self) -> String { self.simulation.activation_code().to_owned() }
+
+    /// Compile a new activation, a ValueError is raised (and the previous one kept) if it does not compile
+    #[setter]
+    fn set_activation(&mut self, activation_code: &str) -> PyResult<()> {
+        self.simulation.set_activation(activation_code).map_err(|error| PyValueError::new_err(error.to_string()))
+    }
+
+    /// Restart from a random state, `mode` 0 for random values and 1 for random integers
+    #[pyo3(signature = (seed = None, mode = 0))]
+    fn init(&mut self, seed: Option<f32>, mode: u32) {
+        self.simulation.init(InitSimulationUniforms {
+            seed: seed.unwrap_or_else(|| rand::rng().random()),
+            initialisation_mode: mode,
+        });
+    }
+
+    #[pyo3(signature = (generations = 1))]
+    fn step(&mut self, generations: u32) { self.simulation.step(generations); }
+
+    /// Displayed channel of the current state as a (height, width) float array in [0, 1]
+    fn state<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray2<f32>> {
+        let snapshot = self.simulation.read_state();
+        let [width, height] = snapshot.size;
+        Array::from_shape_vec((height as usize, width as usize), snapshot.values())
+            .expect("The snapshot matches its size")
+            .into_pyarray(py)
+    }
+
+    /// Every channel of the current state as a (height, width, 4) uint8 array
+    fn rgba<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray3<u8>> {
+        let snapshot = self.simulation.read_state();
+        let [width, height] = snapshot.size;
+        Array::from_shape_vec((height as usize, width as usize, 4), snapshot.rgba)
+            .expect("The snapshot matches its size")
+            .into_pyarray(py)
+    }
+}
+
+/// Names of the built-in presets
+#[pyfunction]
+fn presets() -> Vec<&'static str> {
+    let mut names: Vec<&'static str> = PRESETS.keys().copied().collect();
+    names.sort();
+    names
+}
+
+/// Full simulation shader generated for an activation, as compiled by `Simulation.activation`
+#[pyfunction]
+fn simulation_shader(activation_code: &str) -> String { generate_simulation_shader(activation_code) }
+
+#[pymodule]
+fn nca(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Simulation>()?;
+    m.add_function(wrap_pyfunction!(presets, m)?)?;
+    m.add_function(wrap_pyfunction!(simulation_shader, m)?)?;
+    Ok(())
+}
@@ -3,10 +3,18 @@ mod nca_app;
 #[macro_use]
 extern crate lazy_static;
 
-use nca_app::NcaApp;
+use nca_app::{CommandLineOptions, NcaApp};
 
 use oxyde::app::{run_application, AppConfig, RenderingConfig};
 fn main() {
+    if let Ok(options @ CommandLineOptions { explore: Some(run_count), .. }) = CommandLineOptions::from_env() {
+        if let Err(error) = nca_app::run_headless_exploration(run_count, &options) {
+            eprintln!("Unable to run the exploration.\n {:?}", error);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     run_application::<NcaApp>(AppConfig {
         is_resizable: true,
         title: "rust NCA",
@@ -1,10 +1,19 @@
-mod pipeline_helpers;
-mod preset;
-mod simulation_data;
-mod view_data;
+mod animation;
+mod cell_inspector;
+mod cell_labels;
+mod command_line;
+mod evolution;
+mod explorer;
+mod paste_import;
+mod recorder;
+mod scoring;
+mod simulation_instance;
+mod state_detection;
+mod statistics;
+mod still_render;
+mod timeline;
 
 use anyhow::Result;
-use rand::Rng;
 
 use oxyde::wgpu as wgpu;
 use oxyde::egui as egui;
@@ -15,28 +24,55 @@ use std::{
     time::{Duration, Instant},
 };
 
-use egui_widgets::{glam_helpers::DisplayableVec2, CodeEditor, IqGradient, UiWidget, IQ_GRADIENT_PRESETS};
+use egui_widgets::{gradient_preview, CodeEditor, IqGradient, UiWidget, IQ_GRADIENT_PRESETS};
 use oxyde::app::{App, AppState};
-use oxyde::wgpu_utils::PingPongTexture;
-
-use glam::Vec2;
 
-use serde::{Deserialize, Serialize};
+use glam::{Vec2, Vec4};
 
-
-use pipeline_helpers::{
-    build_init_simulation_pipeline,
-    build_screen_pipeline,
-    build_simulation_pipeline,
-    get_simulation_textures_and_bind_groups,
-    get_texture_descriptor,
+use rand::Rng;
+use rand_distr::{Distribution, Normal};
+
+pub use command_line::CommandLineOptions;
+pub use explorer::run_headless as run_headless_exploration;
+
+use nca_core::{
+    cell_probe::CellProbe,
+    color_map::MAX_COLOR_STOPS,
+    generate_screen_shader,
+    kernel_format::{self, KernelFormat},
+    kernel_randomizer::KernelRandomizer,
+    kernel_symmetry::KernelSymmetryMode,
+    pipeline_helpers::{build_screen_pipeline, capture_validation_error, fullscreen_primitive_state},
+    preset::{self, KernelLayout, Preset, ACTIVATION_FUNCTIONS_PRESETS, PRESETS},
+    simulation_data::KERNEL_SIZE,
+    statistics::StatisticsPass,
+    view_data::ViewData,
+    AspectMode,
+    BoundaryCondition,
+    ColorMap,
+    ColorMapKind,
+    DisplayFramesMode,
+    InitSimulationUniforms,
+    Lattice,
+    PostProcessing,
+    PostProcessor,
+    DEFAULT_DISPLAY_CODE,
 };
-use preset::{Preset, PRESETS};
 
-use simulation_data::{InitSimulationData, KernelSymmetryMode, SimulationData};
-use view_data::ViewData;
-
-#[derive(Default, Debug)]
+use animation::{AnimatedParameter, Animation, AnimationAction};
+use cell_inspector::CellInspector;
+use cell_labels::CellLabels;
+use evolution::{Evolution, EvolutionAction, Genome};
+use explorer::{Explorer, ExplorerAction};
+use paste_import::PasteImport;
+use recorder::{Recorder, RecorderAction};
+use simulation_instance::{PipelineStates, SimulationInstance};
+use state_detection::{DetectionPolicy, StateDetector, StateEvent};
+use statistics::Statistics;
+use still_render::{StillRenderAction, StillRenderer};
+use timeline::{Timeline, TimelineAction};
+
+#[derive(Default, Debug, Clone)]
 pub struct Viewport {
     pub x: f32,
     pub y: f32,
@@ -46,6 +82,24 @@ pub struct Viewport {
     pub max_depth: f32,
 }
 
+impl Viewport {
+    // Side by side columns of equal width
+    pub fn split_horizontally(&self, count: usize) -> Vec<Viewport> {
+        let width = self.width / count.max(1) as f32;
+        (0..count.max(1))
+            .map(|index| Viewport {
+                x: self.x + index as f32 * width,
+                width,
+                ..self.clone()
+            })
+            .collect()
+    }
+
+    pub fn contains(&self, position: Vec2) -> bool {
+        position.x >= self.x && position.x < self.x + self.width && position.y >= self.y && position.y < self.y + self.height
+    }
+}
+
 pub enum ShaderState {
     Compiled,
     Dirty,
@@ -58,11 +112,14 @@ pub enum SimulationSizeState {
     ToCompile { old: [u32; 2], new: [u32; 2] },
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
-pub enum DisplayFramesMode {
-    All,
-    Evens,
-    Odd,
+impl SimulationSizeState {
+    // Size of the textures currently in use
+    pub fn current(&self) -> [u32; 2] {
+        match *self {
+            SimulationSizeState::Compiled(size) => size,
+            SimulationSizeState::Dirty { old, .. } | SimulationSizeState::ToCompile { old, .. } => old,
+        }
+    }
 }
 
 pub struct NcaApp {
@@ -71,318 +128,703 @@ pub struct NcaApp {
     primitive_state: wgpu::PrimitiveState,
     multisample_state: wgpu::MultisampleState,
 
-    simulation_shader: wgpu::ShaderModule,
     screen_shader: wgpu::ShaderModule,
 
-    init_simulation_render_pipeline: wgpu::RenderPipeline,
-    simulation_render_pipeline: wgpu::RenderPipeline,
     screen_render_pipeline: wgpu::RenderPipeline,
-    simulation_textures: PingPongTexture,
-    init_simulation_data: InitSimulationData,
-    simulation_data: SimulationData,
-    kernel_symmetry_mode: KernelSymmetryMode,
+    // Shared by all the simulations, which restart together from the same state
+    init_parameters: InitSimulationUniforms,
+    // Simulations displayed side by side, the ui edits the selected one
+    instances: Vec<SimulationInstance>,
+    selected_instance: usize,
+    duplicate_instance_requested: bool,
     init: bool,
+    paused: bool,
+    generation: u64,
     reset_on_randomize: bool,
-    kernel_rand_range: DisplayableVec2,
-
-    bind_group_display_ping: wgpu::BindGroup,
-    bind_group_display_pong: wgpu::BindGroup,
-    bind_group_simulation_ping: wgpu::BindGroup,
-    bind_group_simulation_pong: wgpu::BindGroup,
+    kernel_randomizer: KernelRandomizer,
+    kernel_text_format: KernelFormat,
+    kernel_text: String,
+    // Shared by all the simulations, like their size, since they are displayed through the same view
+    lattice: Lattice,
 
     ui_central_viewport: Viewport,
 
     target_delta: Duration,
     last_simulation_end: Instant,
 
-    activation_code: String,
-    shader_state: ShaderState,
+    pending_paste_import: Option<PasteImport>,
+
+    explorer: Explorer,
+    evolution: Evolution,
+    statistics: Statistics,
+    statistics_pass: StatisticsPass,
+    cell_probe: CellProbe,
+    cell_labels: CellLabels,
+    cell_inspector: CellInspector,
+
+    state_detector: StateDetector,
+    detection_policy: DetectionPolicy,
+    state_notice: Option<String>,
+
+    timeline: Option<Timeline>,
+    animation: Animation,
+
+    recorder: Recorder,
+    pending_recorder_action: Option<RecorderAction>,
+    still_renderer: StillRenderer,
+    pending_still_render_action: Option<StillRenderAction>,
 
     display_frames_mode: DisplayFramesMode,
 
+    // Edited here, sent to the screen shader through view_data
+    color_map: ColorMap,
+    // colorize function templated into the screen shader
+    display_code: String,
+    display_shader_state: ShaderState,
+    post_processing: PostProcessing,
+    // Applies post_processing to the central viewport
+    post_processor: PostProcessor,
     view_data: ViewData,
 }
 
-fn generate_simulation_shader(activation_code: &str) -> String {
-    include_str!("shaders/simulationBase.wgsl").replace("[functionTemplate]", activation_code)
-}
-
 impl NcaApp {
+    #[inline(always)]
+    fn instance(&self) -> &SimulationInstance { &self.instances[self.selected_instance] }
+
+    #[inline(always)]
+    fn instance_mut(&mut self) -> &mut SimulationInstance { &mut self.instances[self.selected_instance] }
+
     #[inline(always)]
     pub fn load_preset_from_file<P: AsRef<Path>>(&mut self, filepath: &P) -> Result<()> { self.load_preset(preset::load_preset(filepath)?) }
 
     pub fn load_preset(&mut self, preset: Preset) -> Result<()> {
-        self.simulation_data.uniform.set_kernel_from_slice(preset.kernel);
-        self.simulation_data.need_update = true;
+        self.set_lattice(preset.lattice);
+
+        let instance = self.instance_mut();
+        instance.simulation.simulation_data.uniform.set_kernel_from_slice(preset.kernel);
+        instance.simulation.simulation_data.need_update = true;
+        instance.kernel_symmetry_mode = preset.kernel_symmetry_mode;
+        instance.boundary_condition = preset.boundary_condition;
 
-        self.activation_code = preset.activation_code;
-        self.shader_state = ShaderState::Dirty;
+        instance.activation_code = preset.activation_code;
+        instance.shader_state = ShaderState::Dirty;
 
         self.display_frames_mode = preset.display_frames_mode;
 
         self.view_data.uniform.gradient = preset.gradient;
+        self.view_data.uniform.color_map = preset.color_map.parameters();
+        self.color_map = preset.color_map;
         self.view_data.need_update = true;
 
+        self.display_code = preset.display_code;
+        self.display_shader_state = ShaderState::Dirty;
+        self.post_processing = preset.post_processing;
+
+        if let Some(kernel_randomizer) = preset.kernel_randomizer {
+            self.kernel_randomizer = kernel_randomizer;
+        }
+
+        Ok(())
+    }
+
+    pub fn load_kernel_from_file<P: AsRef<Path>>(&mut self, filepath: &P) -> Result<()> {
+        let instance = self.instance_mut();
+        instance.simulation.simulation_data.uniform.set_kernel_checked(kernel_format::load_kernel(filepath)?, instance.kernel_symmetry_mode)?;
+        instance.simulation.simulation_data.need_update = true;
         Ok(())
     }
 
+    pub fn save_kernel<P: AsRef<Path>>(&self, filepath: &P) -> std::io::Result<()> {
+        kernel_format::save_kernel(filepath, &self.instance().simulation.simulation_data.uniform.get_kernel_as_slice(), self.kernel_text_format)
+    }
+
     pub fn save_preset<P: AsRef<Path>>(&self, filepath: &P) -> std::io::Result<()> {
+        let instance = self.instance();
         let current_preset = Preset {
-            kernel: self.simulation_data.uniform.get_kernel_as_slice(),
-            activation_code: self.activation_code.clone(),
+            kernel: instance.simulation.simulation_data.uniform.get_kernel_as_slice(),
+            kernel_layout: KernelLayout::RowMajor,
+            activation_code: instance.activation_code.clone(),
             display_frames_mode: self.display_frames_mode.clone(),
             gradient: self.view_data.uniform.gradient.clone(),
-            kernel_symmetry_mode: self.kernel_symmetry_mode,
+            color_map: self.color_map.clone(),
+            display_code: self.display_code.clone(),
+            post_processing: self.post_processing.clone(),
+            kernel_symmetry_mode: instance.kernel_symmetry_mode,
+            kernel_randomizer: Some(self.kernel_randomizer),
+            boundary_condition: instance.boundary_condition,
+            lattice: self.lattice,
         };
 
         preset::save_preset(filepath, &current_preset)
     }
 
-    pub fn try_generate_simulation_pipeline(
+    pub fn try_update_simulation_size(
         &mut self,
+        new_simulation_size: [u32; 2],
         device: &mut wgpu::Device,
         surface_configuration: &wgpu::SurfaceConfiguration,
     ) -> Result<(), wgpu::Error> {
-        let (tx, rx) = std::sync::mpsc::channel::<wgpu::Error>();
-        device.on_uncaptured_error(Box::new(move |e: wgpu::Error| {
-            tx.send(e).expect("sending error failed");
-        }));
-
-        let shader_code: String = generate_simulation_shader(&self.activation_code);
-        let simulation_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Simulation Shader"),
-            source: wgpu::ShaderSource::Wgsl(shader_code.into()),
-        });
+        // The simulations round the size the same way.
+        // Everything is created before replacing anything, so that a failure leaves every simulation at its previous size.
+        let new_simulation_size = self.lattice.grid_size(new_simulation_size);
+        let resized_textures = self
+            .instances
+            .iter()
+            .map(|instance| instance.simulation.create_resized_textures(new_simulation_size))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let screen_render_pipeline = capture_validation_error(device, || {
+            build_screen_pipeline(
+                device,
+                surface_configuration.format,
+                &self.primitive_state,
+                &self.multisample_state,
+                &self.screen_shader,
+                self.instances[0].simulation.texture_bind_group_layout(),
+                &self.view_data,
+            )
+        })?;
+
+        for (instance, resized) in self.instances.iter_mut().zip(resized_textures) {
+            instance.simulation.apply_resize(resized);
+        }
+        self.simulation_size_state = SimulationSizeState::Compiled(new_simulation_size);
+        self.init = false;
+        self.screen_render_pipeline = screen_render_pipeline;
+        Ok(())
+    }
 
-        let simulation_render_pipeline = build_simulation_pipeline(
-            device,
-            surface_configuration,
-            &self.primitive_state,
-            &self.multisample_state,
-            &self.screen_shader,
-            &simulation_shader,
-            &self.simulation_textures,
-            &self.simulation_data,
-        );
+    // The simulation and init pipelines keep the previous module for their vertex stage, which does not depend on the display code
+    pub fn try_generate_screen_pipeline(&mut self, device: &mut wgpu::Device, surface_configuration: &wgpu::SurfaceConfiguration) -> Result<(), wgpu::Error> {
+        let (screen_shader, screen_render_pipeline) = capture_validation_error(device, || {
+            let screen_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("Screne Shader"),
+                source: wgpu::ShaderSource::Wgsl(generate_screen_shader(&self.display_code).into()),
+            });
 
-        device.on_uncaptured_error(Box::new(|err| panic!("{}", err)));
+            let screen_render_pipeline = build_screen_pipeline(
+                device,
+                surface_configuration.format,
+                &self.primitive_state,
+                &self.multisample_state,
+                &screen_shader,
+                self.instances[0].simulation.texture_bind_group_layout(),
+                &self.view_data,
+            );
 
-        if let Ok(err) = rx.try_recv() {
-            return Err(err);
-        }
+            (screen_shader, screen_render_pipeline)
+        })?;
 
-        self.simulation_render_pipeline = simulation_render_pipeline;
-        self.shader_state = ShaderState::Compiled;
+        self.screen_shader = screen_shader;
+        self.screen_render_pipeline = screen_render_pipeline;
+        self.display_shader_state = ShaderState::Compiled;
 
         Ok(())
     }
 
-    pub fn try_update_simulation_size(
-        &mut self,
-        new_simulation_size: [u32; 2],
-        device: &mut wgpu::Device,
-        surface_configuration: &wgpu::SurfaceConfiguration,
-    ) -> Result<(), wgpu::Error> {
-        let (tx, rx) = std::sync::mpsc::channel::<wgpu::Error>();
-        device.on_uncaptured_error(Box::new(move |e: wgpu::Error| {
-            tx.send(e).expect("sending error failed");
-        }));
+    // Copy of the selected instance, started again with the others from the same initial state
+    fn try_duplicate_selected_instance(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<(), wgpu::Error> {
+        let selected = &self.instances[self.selected_instance];
+        let instance = SimulationInstance::new(
+            device,
+            queue,
+            format!("Simulation {}", self.instances.len() + 1),
+            self.simulation_size_state.current(),
+            selected.simulation.kernel(),
+            selected.kernel_symmetry_mode,
+            selected.boundary_condition,
+            self.lattice,
+            selected.activation_code.clone(),
+        )?;
+
+        self.instances.push(instance);
+        self.selected_instance = self.instances.len() - 1;
+        self.init = false;
+        Ok(())
+    }
 
-        let texture_desc = get_texture_descriptor(&new_simulation_size);
+    fn apply_command_line_options(&mut self, options: CommandLineOptions) {
+        if let Some(preset_path) = options.preset_path {
+            self.load_preset_from_file(&preset_path).unwrap_or_else(|error| {
+                println!("Unable to load preset from the file at path {}.\n {:?}", preset_path.display(), error);
+            });
+        }
 
-        let (simulation_textures, bind_group_display_ping, bind_group_display_pong, bind_group_simulation_ping, bind_group_simulation_pong) =
-            get_simulation_textures_and_bind_groups(device, &texture_desc)?;
+        if let Some(kernel) = options.kernel {
+            let kernel_path = Path::new(&kernel);
+            let result = if kernel_path.is_file() {
+                self.load_kernel_from_file(&kernel_path)
+            } else {
+                let instance = self.instance_mut();
+                instance.simulation.simulation_data.uniform.set_kernel_from_str(&kernel, instance.kernel_symmetry_mode)
+            };
+            match result {
+                Ok(()) => self.instance_mut().simulation.simulation_data.need_update = true,
+                Err(error) => println!("Unable to load the kernel `{}`.\n {:?}", kernel, error),
+            }
+        }
 
-        let screen_render_pipeline = build_screen_pipeline(
-            device,
-            surface_configuration,
-            &self.primitive_state,
-            &self.multisample_state,
-            &self.screen_shader,
-            &self.simulation_textures,
-            &self.view_data,
-        );
+        if let Some(timeline_path) = options.timeline_path {
+            match Timeline::load(&timeline_path) {
+                Ok(timeline) => self.timeline = Some(timeline),
+                Err(error) => println!("Unable to load timeline from the file at path {}.\n {:?}", timeline_path.display(), error),
+            }
+        }
+    }
 
-        let simulation_render_pipeline = build_simulation_pipeline(
-            device,
-            surface_configuration,
-            &self.primitive_state,
-            &self.multisample_state,
-            &self.screen_shader,
-            &self.simulation_shader,
-            &self.simulation_textures,
-            &self.simulation_data,
-        );
+    pub fn apply_paste_import(&mut self, paste_import: PasteImport) -> Result<()> {
+        match paste_import {
+            PasteImport::Preset(preset) => self.load_preset(preset)?,
+            PasteImport::Kernel(kernel) => {
+                let instance = self.instance_mut();
+                instance.simulation.simulation_data.uniform.set_kernel_checked(kernel, instance.kernel_symmetry_mode)?;
+                instance.simulation.simulation_data.need_update = true;
+            },
+        }
 
-        device.on_uncaptured_error(Box::new(|err| panic!("{}", err)));
+        Ok(())
+    }
 
-        if let Ok(err) = rx.try_recv() {
-            return Err(err);
+    fn apply_explorer_action(&mut self, action: ExplorerAction) {
+        match action {
+            ExplorerAction::Start => {
+                let instance = &self.instances[self.selected_instance];
+                self.explorer.start(
+                    &self.kernel_randomizer,
+                    self.lattice,
+                    instance.kernel_symmetry_mode,
+                    &instance.activation_code,
+                    self.init_parameters.seed,
+                )
+            },
+            ExplorerAction::Open(preset) => {
+                self.load_preset(preset).unwrap_or_else(|error| {
+                    println!("Unable to open the explored preset.\n {:?}", error);
+                });
+                self.init = false;
+            },
+            ExplorerAction::Save(preset) => match nfd2::open_save_dialog(Some("json"), None).expect("Unable to save the file") {
+                nfd2::Response::Okay(file_path) => {
+                    let path: &Path = file_path.as_path();
+                    preset::save_preset(path, &preset).unwrap_or_else(|error| {
+                        println!("Unable to save the preset at path {}.\n {:?}", path.display(), error);
+                    });
+                },
+                nfd2::Response::OkayMultiple(_) => println!("Multiple files selection should not happen here."),
+                nfd2::Response::Cancel => (),
+            },
         }
+    }
 
-        self.simulation_size_state = SimulationSizeState::Compiled(new_simulation_size);
-        self.init = false;
-        self.simulation_textures = simulation_textures;
-        self.bind_group_display_ping = bind_group_display_ping;
-        self.bind_group_display_pong = bind_group_display_pong;
-        self.bind_group_simulation_ping = bind_group_simulation_ping;
-        self.bind_group_simulation_pong = bind_group_simulation_pong;
-        self.screen_render_pipeline = screen_render_pipeline;
-        self.simulation_render_pipeline = simulation_render_pipeline;
-        self.simulation_data.set_simulation_size(&new_simulation_size);
-        Ok(())
+    fn apply_evolution_action(&mut self, action: EvolutionAction) {
+        match action {
+            EvolutionAction::Restart => {
+                let instance = &self.instances[self.selected_instance];
+                self.evolution.restart(
+                    Genome {
+                        kernel: instance.simulation.simulation_data.uniform.get_kernel_as_slice(),
+                        activation_code: instance.activation_code.clone(),
+                    },
+                    self.lattice,
+                    instance.kernel_symmetry_mode,
+                    self.init_parameters.seed,
+                )
+            },
+            EvolutionAction::Open(preset) => {
+                self.load_preset(preset).unwrap_or_else(|error| {
+                    println!("Unable to open the evolved preset.\n {:?}", error);
+                });
+                self.init = false;
+            },
+            EvolutionAction::SaveLineage => match nfd2::open_save_dialog(Some("json"), None).expect("Unable to save the file") {
+                nfd2::Response::Okay(file_path) => {
+                    let path: &Path = file_path.as_path();
+                    self.evolution.save_lineage(path).unwrap_or_else(|error| {
+                        println!("Unable to save the lineage at path {}.\n {:?}", path.display(), error);
+                    });
+                },
+                nfd2::Response::OkayMultiple(_) => println!("Multiple files selection should not happen here."),
+                nfd2::Response::Cancel => (),
+            },
+            EvolutionAction::LoadLineage => match nfd2::open_file_dialog(Some("json"), None).expect("Unable to open the file") {
+                nfd2::Response::Okay(file_path) => {
+                    let path: &Path = file_path.as_path();
+                    self.evolution.load_lineage(path).unwrap_or_else(|error| {
+                        println!("Unable to load the lineage from path {}.\n {:?}", path.display(), error);
+                    });
+                },
+                nfd2::Response::OkayMultiple(_) => println!("Multiple files selection should not happen here."),
+                nfd2::Response::Cancel => (),
+            },
+        }
     }
 
-    fn randomize_kernel(&mut self) {
-        let mut rng = rand::rng();
-        let range: std::ops::Range<f32> = self.kernel_rand_range.x..self.kernel_rand_range.y;
-
-        //define lamdba to get random value with given range
-        let mut get_random_value = || rng.random_range(range.clone());
-        match self.kernel_symmetry_mode {
-            KernelSymmetryMode::Any =>
-                for i in 0..3 {
-                    for j in 0..3 {
-                    self.simulation_data
-                        .uniform
-                        .set_kernel_at(i, j, get_random_value());
+    fn show_paste_import_window(&mut self, ctx: &egui::Context) {
+        let Some(paste_import) = &self.pending_paste_import else {
+            return;
+        };
+
+        let mut accepted: Option<bool> = None;
+        egui::Window::new("Import pasted content")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!("The pasted text looks like a {}. Do you want to import it ?", paste_import.description()));
+                ui.horizontal(|ui| {
+                    if ui.button("Import").clicked() {
+                        accepted = Some(true);
                     }
-                },
-            KernelSymmetryMode::Vertical => {
-                for i in 0..3 {
-                    for j in 0..2 {
-                        self.simulation_data
-                            .uniform
-                            .set_kernel_at(i , j, get_random_value());
-                        if j == 0 {
-                            self.simulation_data
-                                .uniform
-                                .set_kernel_at(i, 2 - j, self.simulation_data.uniform.get_kernel_at(i, j));
-                        }
+                    if ui.button("Dismiss").clicked() {
+                        accepted = Some(false);
                     }
+                });
+            });
+
+        match accepted {
+            Some(true) =>
+                if let Some(paste_import) = self.pending_paste_import.take() {
+                    self.apply_paste_import(paste_import).unwrap_or_else(|error| {
+                        println!("Unable to import pasted content.\n {:?}", error);
+                    });
+                },
+            Some(false) => self.pending_paste_import = None,
+            None => (),
+        }
+    }
+
+    fn apply_state_event(&mut self, event: StateEvent, generation: u64) {
+        self.state_notice = Some(format!("{} detected at generation {}", event, generation));
+        match self.detection_policy {
+            DetectionPolicy::Disabled | DetectionPolicy::Notify => (),
+            DetectionPolicy::Pause => self.paused = true,
+            DetectionPolicy::Reset => {
+                self.init_parameters.seed = rand::rng().random();
+                self.init = false;
+            },
+            DetectionPolicy::Randomize => {
+                self.kernel_randomizer.reseed();
+                self.randomize_kernel();
+                self.init = false;
+            },
+        }
+    }
+
+    fn apply_timeline_action(&mut self, action: TimelineAction) {
+        // Either a built-in preset name or a preset file path
+        fn find_preset(preset: &str) -> Result<Preset> {
+            match PRESETS.get(preset) {
+                Some(preset) => Ok(preset.clone()),
+                None => preset::load_preset(preset),
+            }
+        }
+
+        let result: Result<()> = match &action {
+            TimelineAction::LoadPreset { preset } => find_preset(preset).and_then(|preset| self.load_preset(preset)),
+            TimelineAction::KernelFromPreset { preset } => find_preset(preset).map(|preset| {
+                let instance = self.instance_mut();
+                instance.simulation.simulation_data.uniform.set_kernel_from_slice(preset.kernel);
+                instance.simulation.simulation_data.need_update = true;
+            }),
+            TimelineAction::SetKernel { kernel } => {
+                let instance = self.instance_mut();
+                instance.simulation.simulation_data.uniform.set_kernel_from_slice(*kernel);
+                instance.simulation.simulation_data.need_update = true;
+                Ok(())
+            },
+            TimelineAction::PerturbKernel { std_dev } => Normal::new(0.0, std_dev.abs()).map_err(anyhow::Error::from).map(|noise| {
+                let instance = &mut self.instances[self.selected_instance];
+                let mut kernel = instance.simulation.simulation_data.uniform.get_kernel_as_slice();
+                let mut rng = rand::rng();
+                kernel.iter_mut().for_each(|value| *value += noise.sample(&mut rng));
+                self.lattice.apply_symmetry(&mut kernel, instance.kernel_symmetry_mode);
+                instance.simulation.simulation_data.uniform.set_kernel_from_slice(kernel);
+                instance.simulation.simulation_data.need_update = true;
+            }),
+            TimelineAction::RandomizeKernel => {
+                self.kernel_randomizer.reseed();
+                self.randomize_kernel();
+                Ok(())
+            },
+            TimelineAction::SetActivation { activation_code } => {
+                let instance = self.instance_mut();
+                instance.activation_code = activation_code.clone();
+                instance.shader_state = ShaderState::Dirty;
+                Ok(())
+            },
+            TimelineAction::SetView { center, zoom_level, tile } => {
+                if let Some(tile) = tile {
+                    self.view_data.uniform.tile = *tile as u32;
+                }
+                if let Some(center) = center {
+                    self.view_data.uniform.center = Vec2::from(*center);
                 }
+                if let Some(zoom_level) = zoom_level {
+                    self.view_data.uniform.zoom_level = *zoom_level;
+                }
+                self.view_data.uniform.constrain();
+                self.view_data.need_update = true;
+                Ok(())
             },
-            KernelSymmetryMode::Horizontal => {
-                for j in 0..3 {
-                    for i in 0..2 {
-                        self.simulation_data
-                            .uniform
-                            .set_kernel_at(i , j, get_random_value());
-                        if i == 0 {
-                            self.simulation_data
-                                .uniform
-                                .set_kernel_at(2 - i, j, self.simulation_data.uniform.get_kernel_at(i, j));
-                        }
-                    }
+            TimelineAction::Brush { center, radius, value } => {
+                self.instance_mut().simulation.paint_disc(*center, *radius, *value);
+                Ok(())
+            },
+            TimelineAction::PerturbState { std_dev, seed } => {
+                let seed = seed.unwrap_or_else(|| rand::rng().random());
+                self.instance_mut().simulation.perturb_state(*std_dev, seed).map_err(anyhow::Error::from)
+            },
+            TimelineAction::Init { seed } => {
+                self.init_parameters.seed = seed.unwrap_or_else(|| rand::rng().random());
+                self.init = false;
+                Ok(())
+            },
+            TimelineAction::Pause => {
+                self.paused = true;
+                Ok(())
+            },
+            TimelineAction::SavePreset { path } => self.save_preset(path).map_err(anyhow::Error::from),
+            TimelineAction::SaveKernel { path } => self.save_kernel(path).map_err(anyhow::Error::from),
+            TimelineAction::ExportStatistics { path } => self.statistics.export_csv(path).map_err(anyhow::Error::from),
+        };
+
+        result.unwrap_or_else(|error| {
+            println!("Unable to apply the timeline action `{}`.\n {:?}", action, error);
+        });
+    }
+
+    fn animated_value(&self, parameter: AnimatedParameter) -> f32 {
+        let gradient = &self.view_data.uniform.gradient;
+        match parameter {
+            AnimatedParameter::Kernel(index) => self.instance().simulation.simulation_data.uniform.get_kernel_as_slice()[index],
+            AnimatedParameter::GradientA(channel) => gradient.a[channel],
+            AnimatedParameter::GradientB(channel) => gradient.b[channel],
+            AnimatedParameter::GradientC(channel) => gradient.c[channel],
+            AnimatedParameter::GradientD(channel) => gradient.d[channel],
+            AnimatedParameter::Zoom => self.view_data.uniform.zoom_level,
+            AnimatedParameter::CenterX => self.view_data.uniform.center.x,
+            AnimatedParameter::CenterY => self.view_data.uniform.center.y,
+            AnimatedParameter::ColorMapFrom => self.color_map.range[0],
+            AnimatedParameter::ColorMapTo => self.color_map.range[1],
+            AnimatedParameter::ColorMapGamma => self.color_map.gamma,
+            AnimatedParameter::PostEffect(effect, parameter) => {
+                self.post_processing.effects.get(effect).and_then(|post_effect| post_effect.parameter(parameter)).unwrap_or_default()
+            },
+        }
+    }
+
+    fn set_animated_value(&mut self, parameter: AnimatedParameter, value: f32) {
+        let gradient = &mut self.view_data.uniform.gradient;
+        match parameter {
+            AnimatedParameter::Kernel(index) => {
+                let instance = self.instance_mut();
+                let (col, row) = (index % KERNEL_SIZE, index / KERNEL_SIZE);
+                let kernel_symmetry_mode = instance.kernel_symmetry_mode;
+                instance.simulation.simulation_data.uniform.set_kernel_at_with_symmetry(col, row, value, kernel_symmetry_mode);
+                instance.simulation.simulation_data.need_update = true;
+                return;
+            },
+            AnimatedParameter::PostEffect(effect, parameter) => {
+                // The post processor reads the effects every frame
+                if let Some(target) = self.post_processing.effects.get_mut(effect).and_then(|post_effect| post_effect.parameter_mut(parameter)) {
+                    *target = value;
+                }
+                return;
+            },
+            AnimatedParameter::GradientA(channel) => gradient.a[channel] = value,
+            AnimatedParameter::GradientB(channel) => gradient.b[channel] = value,
+            AnimatedParameter::GradientC(channel) => gradient.c[channel] = value,
+            AnimatedParameter::GradientD(channel) => gradient.d[channel] = value,
+            AnimatedParameter::Zoom => self.view_data.uniform.zoom_level = value,
+            AnimatedParameter::CenterX => self.view_data.uniform.center.x = value,
+            AnimatedParameter::CenterY => self.view_data.uniform.center.y = value,
+            AnimatedParameter::ColorMapFrom => self.color_map.range[0] = value,
+            AnimatedParameter::ColorMapTo => self.color_map.range[1] = value,
+            AnimatedParameter::ColorMapGamma => self.color_map.gamma = value,
+        }
+        if matches!(parameter, AnimatedParameter::ColorMapFrom | AnimatedParameter::ColorMapTo | AnimatedParameter::ColorMapGamma) {
+            self.view_data.uniform.color_map = self.color_map.parameters();
+        }
+        self.view_data.need_update = true;
+    }
+
+    fn apply_animation_action(&mut self, action: AnimationAction) {
+        match action {
+            AnimationAction::AddKeyframe(parameter) => {
+                let value = self.animated_value(parameter);
+                self.animation.add_keyframe(parameter, value);
+            },
+            AnimationAction::Save => match nfd2::open_save_dialog(Some("json"), None).expect("Unable to save the file") {
+                nfd2::Response::Okay(file_path) => {
+                    let path: &Path = file_path.as_path();
+                    self.animation.save(path).unwrap_or_else(|error| {
+                        println!("Unable to save the animation at path {}.\n {:?}", path.display(), error);
+                    });
+                },
+                nfd2::Response::OkayMultiple(_) => println!("Multiple files selection should not happen here."),
+                nfd2::Response::Cancel => (),
+            },
+            AnimationAction::Load => match nfd2::open_file_dialog(Some("json"), None).expect("Unable to open the file") {
+                nfd2::Response::Okay(file_path) => {
+                    let path: &Path = file_path.as_path();
+                    self.animation.load(path).unwrap_or_else(|error| {
+                        println!("Unable to load the animation from path {}.\n {:?}", path.display(), error);
+                    });
+                },
+                nfd2::Response::OkayMultiple(_) => println!("Multiple files selection should not happen here."),
+                nfd2::Response::Cancel => (),
+            },
+        }
+    }
+
+    fn apply_recorder_action(&mut self, action: RecorderAction, device: &wgpu::Device, surface_configuration: &wgpu::SurfaceConfiguration) {
+        match action {
+            RecorderAction::Start => {
+                let extension = self.recorder.settings.format.extension();
+                match nfd2::open_save_dialog(Some(extension), None).expect("Unable to save the file") {
+                    nfd2::Response::Okay(file_path) => {
+                        let path: &Path = file_path.as_path();
+                        let texture_format = Recorder::texture_format(surface_configuration.format);
+                        // Same bind group layout as the view uniforms of the recording
+                        let screen_render_pipeline = build_screen_pipeline(
+                            device,
+                            texture_format,
+                            &self.primitive_state,
+                            &self.multisample_state,
+                            &self.screen_shader,
+                            self.instances[0].simulation.texture_bind_group_layout(),
+                            &self.view_data,
+                        );
+                        self.recorder.start(device, path, texture_format, screen_render_pipeline).unwrap_or_else(|error| {
+                            println!("Unable to start the recording at path {}.\n {:?}", path.display(), error);
+                        });
+                    },
+                    nfd2::Response::OkayMultiple(_) => println!("Multiple files selection should not happen here."),
+                    nfd2::Response::Cancel => (),
                 }
             },
-            KernelSymmetryMode::Full => {
-                self.simulation_data.uniform.set_kernel_at(1 , 1, get_random_value());
-                let corner_value = get_random_value();
-                let edge_value = get_random_value();
-                self.simulation_data.uniform.set_kernel_at(0 , 0, corner_value);
-                self.simulation_data.uniform.set_kernel_at(0 , 2, corner_value);
-                self.simulation_data.uniform.set_kernel_at(2 , 0, corner_value);
-                self.simulation_data.uniform.set_kernel_at(2 , 2, corner_value);
-
-                self.simulation_data.uniform.set_kernel_at(0 , 1, edge_value);
-                self.simulation_data.uniform.set_kernel_at(1 , 0, edge_value);
-                self.simulation_data.uniform.set_kernel_at(1 , 2, edge_value);
-                self.simulation_data.uniform.set_kernel_at(2 , 1, edge_value);
+            RecorderAction::Stop => self.recorder.stop(),
+        }
+    }
 
+    fn apply_still_render_action(&mut self, action: StillRenderAction, device: &wgpu::Device, queue: &wgpu::Queue, surface_configuration: &wgpu::SurfaceConfiguration) {
+        match action {
+            StillRenderAction::Render => {
+                let extension = self.still_renderer.settings.format.extension();
+                match nfd2::open_save_dialog(Some(extension), None).expect("Unable to save the file") {
+                    nfd2::Response::Okay(file_path) => {
+                        let path: &Path = file_path.as_path();
+                        let pipeline_states = PipelineStates {
+                            primitive_state: &self.primitive_state,
+                            multisample_state: &self.multisample_state,
+                            screen_shader: &self.screen_shader,
+                        };
+                        let instance = &self.instances[self.selected_instance];
+                        self.still_renderer
+                            .render(
+                                device,
+                                queue,
+                                path,
+                                surface_configuration.format,
+                                &pipeline_states,
+                                instance.simulation.texture_bind_group_layout(),
+                                instance.simulation.display_bind_group(&self.display_frames_mode),
+                                &self.view_data.uniform,
+                            )
+                            .unwrap_or_else(|error| {
+                                println!("Unable to render the image at path {}.\n {:?}", path.display(), error);
+                            });
+                    },
+                    nfd2::Response::OkayMultiple(_) => println!("Multiple files selection should not happen here."),
+                    nfd2::Response::Cancel => (),
+                }
             },
         }
-        self.simulation_data.need_update = true;
+    }
+
+    fn randomize_kernel(&mut self) {
+        let instance = &mut self.instances[self.selected_instance];
+        let kernel = self.kernel_randomizer.generate(self.lattice, instance.kernel_symmetry_mode);
+        instance.simulation.simulation_data.uniform.set_kernel_from_slice(kernel);
+        instance.simulation.simulation_data.need_update = true;
+    }
+
+    // The symmetry modes the new lattice does not support fall back to Any.
+    // The simulations are resized on the next update when the lattice does not support their size.
+    fn set_lattice(&mut self, lattice: Lattice) {
+        self.lattice = lattice;
+        for instance in self.instances.iter_mut() {
+            instance.simulation.simulation_data.uniform.set_lattice(lattice);
+            if !lattice.symmetry_modes().contains(&instance.kernel_symmetry_mode) {
+                instance.kernel_symmetry_mode = KernelSymmetryMode::Any;
+            }
+            instance.simulation.simulation_data.need_update = true;
+        }
+        self.view_data.uniform.lattice = lattice.shader_mode();
+        self.view_data.need_update = true;
+
+        let size = self.simulation_size_state.current();
+        if lattice.grid_size(size) != size {
+            self.simulation_size_state = SimulationSizeState::ToCompile { old: size, new: lattice.grid_size(size) };
+        }
     }
 }
 
 impl App for NcaApp {
     fn create(_app_state: &mut AppState) -> Self {
         let (_, default_preset) = PRESETS.iter().next().unwrap();
-        let activation_code = default_preset.activation_code.clone();
 
         let size = _app_state.window.inner_size();
 
         let simulation_size: [u32; 2] = [512, 512];
-        // Texture
-        let texture_desc = get_texture_descriptor(&simulation_size);
-        
-        let surface_handle = &_app_state.surface_handle;
-        let mut device = &mut _app_state.render_instance.devices[surface_handle.device_handle_id].device;
 
-        let init_simulation_data = InitSimulationData::new(&device);
-
-        let mut simulation_data = SimulationData::new(&device, &simulation_size);
-        simulation_data.uniform.set_kernel_from_slice(default_preset.kernel);
-        simulation_data.need_update = true;
+        let surface_handle = &_app_state.surface_handle;
+        let device_handle = &_app_state.render_instance.devices[surface_handle.device_handle_id];
+        let device = &device_handle.device;
 
         let view_data = ViewData::new(&device);
-
-        let (simulation_textures, bind_group_display_ping, bind_group_display_pong, bind_group_simulation_ping, bind_group_simulation_pong) =
-            get_simulation_textures_and_bind_groups(&mut device, &texture_desc).expect("");
+        let statistics_pass = StatisticsPass::new(&device);
+        let cell_probe = CellProbe::new(&device);
+        let post_processor = PostProcessor::new(&device, surface_handle.config.format);
 
         // Shaders
         let screen_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Screne Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/Screen.wgsl").into()),
-        });
-
-        let shader_code: String = generate_simulation_shader(&activation_code);
-        let simulation_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Simulation Shader"),
-            source: wgpu::ShaderSource::Wgsl(shader_code.into()),
-        });
-
-        let init_simulation_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Init Simulation Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/init_simulation.wgsl").into()),
+            source: wgpu::ShaderSource::Wgsl(generate_screen_shader(DEFAULT_DISPLAY_CODE).into()),
         });
 
         // Pipeline
-        let primitive_state = wgpu::PrimitiveState {
-            topology: wgpu::PrimitiveTopology::TriangleList,
-            strip_index_format: None,
-            front_face: wgpu::FrontFace::Ccw,
-            cull_mode: None,
-            polygon_mode: wgpu::PolygonMode::Fill,
-            ..Default::default()
-        };
+        let primitive_state = fullscreen_primitive_state();
 
         let multisample_state = wgpu::MultisampleState::default();
 
-        let screen_render_pipeline = build_screen_pipeline(
-            device,
-            &surface_handle.config,
-            &primitive_state,
-            &multisample_state,
-            &screen_shader,
-            &simulation_textures,
-            &view_data,
-        );
-
-        let init_simulation_render_pipeline = build_init_simulation_pipeline(
+        let instance = SimulationInstance::new(
             device,
-            &surface_handle.config,
-            &primitive_state,
-            &multisample_state,
-            &screen_shader,
-            &init_simulation_shader,
-            &init_simulation_data,
-        );
+            &device_handle.queue,
+            "Simulation 1".to_owned(),
+            simulation_size,
+            default_preset.kernel,
+            KernelSymmetryMode::Any,
+            BoundaryCondition::Wrap,
+            Lattice::Square,
+            default_preset.activation_code.clone(),
+        )
+        .expect("Unable to create the simulation");
 
-        let simulation_render_pipeline = build_simulation_pipeline(
+        let screen_render_pipeline = build_screen_pipeline(
             device,
-            &surface_handle.config,
+            surface_handle.config.format,
             &primitive_state,
             &multisample_state,
             &screen_shader,
-            &simulation_shader,
-            &simulation_textures,
-            &simulation_data,
+            instance.simulation.texture_bind_group_layout(),
+            &view_data,
         );
 
         let window_scale_factor = _app_state.window.scale_factor() as f32;
 
+        let mut kernel_randomizer = KernelRandomizer::default();
+        kernel_randomizer.reseed();
+
         let ui_central_viewport = Viewport {
             x: 0.0,
             y: 0.0,
@@ -392,40 +834,66 @@ impl App for NcaApp {
             max_depth: 1.0,
         };
 
-        Self {
+        let mut app = Self {
             clear_color: wgpu::Color { r: 0.1, g: 0.2, b: 0.3, a: 1.0 },
             simulation_size_state: SimulationSizeState::Compiled(simulation_size),
             primitive_state,
             multisample_state,
 
             screen_shader,
-            simulation_shader,
 
-            init_simulation_render_pipeline,
-            simulation_render_pipeline,
             screen_render_pipeline,
-            simulation_textures,
-            init_simulation_data,
-            simulation_data,
+            init_parameters: InitSimulationUniforms::new(),
+            instances: vec![instance],
+            selected_instance: 0,
+            duplicate_instance_requested: false,
             init: false,
+            paused: false,
+            generation: 0,
             reset_on_randomize: true,
-            kernel_rand_range: DisplayableVec2::new(Vec2::new(-1.0, 1.0)),
-            bind_group_display_ping,
-            bind_group_display_pong,
-            bind_group_simulation_ping,
-            bind_group_simulation_pong,
+            kernel_randomizer,
+            kernel_text_format: KernelFormat::JsonArray,
+            kernel_text: String::new(),
+            lattice: Lattice::Square,
 
             ui_central_viewport,
 
             target_delta: Duration::from_secs_f64(1.0 / 30.0),
             last_simulation_end: Instant::now(),
 
-            activation_code,
-            shader_state: ShaderState::Compiled,
+            pending_paste_import: None,
+            explorer: Explorer::new(),
+            evolution: Evolution::new(),
+            statistics: Statistics::new(),
+            statistics_pass,
+            cell_probe,
+            cell_labels: CellLabels::new(),
+            cell_inspector: CellInspector::new(),
+            state_detector: StateDetector::new(),
+            // The detection reads the statistics back every generation, which stalls the GPU pipeline
+            detection_policy: DetectionPolicy::Disabled,
+            state_notice: None,
+            timeline: None,
+            animation: Animation::new(),
+            recorder: Recorder::new(),
+            pending_recorder_action: None,
+            still_renderer: StillRenderer::new(),
+            pending_still_render_action: None,
             display_frames_mode: DisplayFramesMode::All,
+            color_map: ColorMap::default(),
+            display_code: DEFAULT_DISPLAY_CODE.to_owned(),
+            display_shader_state: ShaderState::Compiled,
+            post_processing: PostProcessing::default(),
+            post_processor,
             view_data,
-            kernel_symmetry_mode: KernelSymmetryMode::Any,
+        };
+
+        match CommandLineOptions::from_env() {
+            Ok(options) => app.apply_command_line_options(options),
+            Err(error) => println!("{:?}", error),
         }
+
+        app
     }
 
     fn handle_event<T: 'static>(&mut self, _app_state: &mut AppState, _event: &Event<T>) -> Result<()> {
@@ -433,17 +901,15 @@ impl App for NcaApp {
             Event::WindowEvent { ref event, .. } => match event {
                 WindowEvent::CursorMoved { .. } => {
                     if _app_state.input_state.mouse.is_middle_clicked {
-                        let zoom_level = self.view_data.uniform.zoom_level;
-                        let window_scale_factor = _app_state.window.scale_factor() as f32;
-
+                        // In physical pixels, like the viewport size of the view parameters
                         let mouse_delta = _app_state.input_state.mouse.position_delta;
+                        let view_parameters = &self.view_data.uniform;
+                        let texture_delta = mouse_delta * view_parameters.texel_scale() / view_parameters.texture_size;
 
-                        let viewport_size = Vec2::new(self.ui_central_viewport.width, self.ui_central_viewport.height) * window_scale_factor;
-
-                        let normalized_mouse_delta = mouse_delta / viewport_size;
-
-                        // Shifting using the normalized mouse_delta, scaled using the zoom_level and constrained by the borders (depending on the zoom_level)
-                        self.view_data.uniform.center = (self.view_data.uniform.center - normalized_mouse_delta * zoom_level).map(|x| x.min(1. - 0.5 * zoom_level).max(0.5 * zoom_level));
+                        // Shifting by the texels under the mouse_delta, constrained by the borders (depending on the zoom_level)
+                        // or wrapped around the torus in tile mode
+                        self.view_data.uniform.center -= texture_delta;
+                        self.view_data.uniform.constrain();
                         self.view_data.need_update = true;
                     }
                 },
@@ -454,23 +920,24 @@ impl App for NcaApp {
                     let window_scale_factor = _app_state.window.scale_factor() as f32;
 
                     let mouse_pos = &_app_state.input_state.mouse.position;
-                    let viewport_min_position = Vec2::new(self.ui_central_viewport.x, self.ui_central_viewport.y) * window_scale_factor;
-                    let viewport_size = Vec2::new(self.ui_central_viewport.width, self.ui_central_viewport.height) * window_scale_factor;
+                    // Zoom around the mouse position within the hovered instance viewport
+                    let instance_viewports = self.ui_central_viewport.split_horizontally(self.instances.len());
+                    let instance_viewport = instance_viewports
+                        .iter()
+                        .find(|viewport| viewport.contains(*mouse_pos / window_scale_factor))
+                        .unwrap_or(&instance_viewports[0]);
+                    let viewport_min_position = Vec2::new(instance_viewport.x, instance_viewport.y) * window_scale_factor;
+                    let viewport_size = Vec2::new(instance_viewport.width, instance_viewport.height) * window_scale_factor;
                     let normalized_mouse_pos_within_viewport = (*mouse_pos - viewport_min_position) / viewport_size;
-                    // let mouse_pos_within_simulation = self.view_data.uniform.center + (normalized_mouse_pos_within_viewport - Vec2::new(0.5, 0.5)) * self.view_data.uniform.zoom_level;
 
-                    let old_zoom_level = self.view_data.uniform.zoom_level;
-                    self.view_data.uniform.zoom_level = (self.view_data.uniform.zoom_level * ZOOM_SENSITIVITY.powf(-*y)).min(1.0);
+                    let view_parameters = &mut self.view_data.uniform;
+                    let mouse_uv = view_parameters.texture_uv(normalized_mouse_pos_within_viewport);
+                    view_parameters.zoom_level = (view_parameters.zoom_level * ZOOM_SENSITIVITY.powf(-*y)).min(view_parameters.max_zoom_level());
 
-                    let zoom_delta = old_zoom_level - self.view_data.uniform.zoom_level;
-                    if *y > 0. {
-                        self.view_data.uniform.center += (normalized_mouse_pos_within_viewport - Vec2::new(0.5, 0.5)) * zoom_delta;
-                    } else {
-                        if old_zoom_level != 1.0 {
-                            self.view_data.uniform.center +=
-                                (Vec2::new(0.5, 0.5) - self.view_data.uniform.center) / (old_zoom_level - 1.0) * zoom_delta;
-                        }
-                    }
+                    // Keep the texels under the mouse in place, the center is flipped vertically compared to the texture coordinates
+                    let uv_shift = mouse_uv - view_parameters.texture_uv(normalized_mouse_pos_within_viewport);
+                    view_parameters.center += Vec2::new(uv_shift.x, -uv_shift.y);
+                    view_parameters.constrain();
 
                     self.view_data.need_update = true;
                 },
@@ -493,6 +960,56 @@ impl App for NcaApp {
     fn render_gui(&mut self, app_state: &mut AppState) -> Result<()> {
         let ctx = app_state.egui_renderer.context();
 
+        // Pasted text is inserted by the focused widget itself, we only look for content that could be imported
+        let pasted_import: Option<PasteImport> = ctx.input(|input_state| {
+            input_state.events.iter().find_map(|e| match e {
+                egui::Event::Paste(paste_content) => PasteImport::detect(paste_content),
+                _ => None,
+            })
+        });
+
+        if pasted_import.is_some() {
+            self.pending_paste_import = pasted_import;
+        }
+
+        self.show_paste_import_window(&ctx);
+
+        if let Some(action) = self.explorer.show(&ctx, &self.view_data.uniform, &self.color_map, &self.display_code, &self.post_processing) {
+            self.apply_explorer_action(action);
+        }
+
+        if let Some(action) = self.evolution.show(&ctx, &self.view_data.uniform, &self.color_map, &self.display_code, &self.post_processing) {
+            self.apply_evolution_action(action);
+        }
+
+        if let Some(action) = self.animation.show(&ctx, &self.post_processing) {
+            self.apply_animation_action(action);
+        }
+
+        // Started from update, where the device is available
+        if let Some(action) = self.recorder.show(&ctx) {
+            self.pending_recorder_action = Some(action);
+        }
+
+        if let Some(action) = self.still_renderer.show(&ctx) {
+            self.pending_still_render_action = Some(action);
+        }
+
+        self.cell_inspector.show(&ctx, &self.instances, self.lattice, self.simulation_size_state.current());
+
+        if self.statistics.show(&ctx) {
+            match nfd2::open_save_dialog(Some("csv"), None).expect("Unable to save the file") {
+                nfd2::Response::Okay(file_path) => {
+                    let path: &Path = file_path.as_path();
+                    self.statistics.export_csv(path).unwrap_or_else(|error| {
+                        println!("Unable to export the statistics at path {}.\n {:?}", path.display(), error);
+                    });
+                },
+                nfd2::Response::OkayMultiple(_) => println!("Multiple files selection should not happen here."),
+                nfd2::Response::Cancel => (),
+            }
+        }
+
         egui::TopBottomPanel::top("top_panel").resizable(true).show(&ctx, |ui| {
             egui::menu::bar(ui, |ui| {
                 ui.menu_button("Simulation Presets", |ui| {
@@ -537,10 +1054,41 @@ impl App for NcaApp {
                     });
                 });
 
+                ui.menu_button("Tools", |ui| {
+                    if ui.button("Explorer").clicked() {
+                        self.explorer.open = true;
+                        ui.close_menu();
+                    }
+                    if ui.button("Evolution").clicked() {
+                        self.evolution.open = true;
+                        ui.close_menu();
+                    }
+                    if ui.button("Statistics").clicked() {
+                        self.statistics.open = true;
+                        ui.close_menu();
+                    }
+                    if ui.button("Animation").clicked() {
+                        self.animation.open = true;
+                        ui.close_menu();
+                    }
+                    if ui.button("Recorder").clicked() {
+                        self.recorder.open = true;
+                        ui.close_menu();
+                    }
+                    if ui.button("Render image…").clicked() {
+                        self.still_renderer.open = true;
+                        ui.close_menu();
+                    }
+                    if ui.button("Cell inspector").clicked() {
+                        self.cell_inspector.open = true;
+                        ui.close_menu();
+                    }
+                });
+
                 ui.menu_button("Style Options", |ui| {
                     egui::widgets::global_dark_light_mode_buttons(ui);
                     // TODO: Make the code editor a field of the NcaApp struct instead of recreating it each frame (deal with mutability and lifetime issues)
-                        let mut code_editor = CodeEditor::new(&mut self.activation_code, "rs", Some(15));
+                        let mut code_editor = CodeEditor::new(&mut self.instances[self.selected_instance].activation_code, "rs", Some(15));
                         code_editor.show_theme_selector("Code editor theme", ui);
                 });
             });
@@ -549,24 +1097,51 @@ impl App for NcaApp {
         egui::SidePanel::left("left_panel").resizable(true).show(&ctx, |ui| {
             egui::CollapsingHeader::new("Simulation").default_open(true).show(ui, |ui| {
                 egui::CollapsingHeader::new("Init").default_open(true).show(ui, |ui| {
-                    if ui.button("Init").clicked() {
-                        self.init = false;
-                    }
+                    ui.horizontal(|ui| {
+                        if ui.button("Init").clicked() {
+                            self.init = false;
+                        }
+                        ui.toggle_value(&mut self.paused, "pause");
+                    });
 
                     ui.add(
-                        egui::DragValue::new(&mut self.init_simulation_data.uniform.seed)
+                        egui::DragValue::new(&mut self.init_parameters.seed)
                         .speed(0.1)
                         .prefix("seed: "),
                     );
                     
                     ui.menu_button("Initialisation mode", |ui| {
-                        let initialisation_mode = &mut self.init_simulation_data.uniform.initialisation_mode;
-                        use crate::nca_app::simulation_data::initialisation_mode_to_string;
+                        let initialisation_mode = &mut self.init_parameters.initialisation_mode;
+                        use nca_core::simulation_data::initialisation_mode_to_string;
                         ui.selectable_value(initialisation_mode, 0, initialisation_mode_to_string(0));
                         ui.selectable_value(initialisation_mode, 1, initialisation_mode_to_string(1));
                     });
                 });
 
+                egui::CollapsingHeader::new("Compared simulations").default_open(true).show(ui, |ui| {
+                    ui.label("Kernel and activation edits apply to the selected simulation.");
+                    let mut selection_changed: bool = false;
+                    for (index, instance) in self.instances.iter_mut().enumerate() {
+                        ui.horizontal(|ui| {
+                            selection_changed |= ui.radio_value(&mut self.selected_instance, index, "").changed();
+                            ui.text_edit_singleline(&mut instance.name);
+                        });
+                    }
+                    ui.horizontal(|ui| {
+                        if ui.button("Duplicate").on_hover_text("Compare the selected simulation with a copy of it").clicked() {
+                            self.duplicate_instance_requested = true;
+                        }
+                        if ui.add_enabled(self.instances.len() > 1, egui::Button::new("Remove")).clicked() {
+                            self.instances.remove(self.selected_instance);
+                            self.selected_instance = self.selected_instance.min(self.instances.len() - 1);
+                            selection_changed = true;
+                        }
+                    });
+                    if selection_changed {
+                        self.state_detector.reset();
+                    }
+                });
+
                 if let SimulationSizeState::ToCompile{old, new} = self.simulation_size_state {
                     ui.label(format!("Wait simulation pipeline compilation before changing the simulation size (from {:?} to {:?}).", old, new));
                 } else {
@@ -638,73 +1213,222 @@ impl App for NcaApp {
                     );
                 });
 
-                egui::CollapsingHeader::new("Kernel")
-                .default_open(true)
-                .show(ui, |ui| {
+                egui::CollapsingHeader::new("State detection").show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("dead, static or periodic state:")
+                            .on_hover_text("When enabled, the statistics are read back every generation, which slows the simulation down");
+                        self.detection_policy.ui_control(ui, "detection_policy");
+                    });
+                    self.state_detector.ui_control(ui);
+                });
 
-                    egui::Grid::new("kernel grid").show(ui, |ui| {
-                        for j in 0..3 {
-                            for i in 0..3 {
-                                ui.add(
-                                    egui::DragValue::from_get_set(|optional_value: Option<f64>| {
-                                        if let Some(v) = optional_value {
-                                            self.simulation_data.uniform.set_kernel_at_with_symmetry(i, j, v as f32, self.kernel_symmetry_mode);
-                                            self.simulation_data.need_update = true;
-                                        }
-                                        self.simulation_data.uniform.get_kernel_at(i,j) as f64
-                                    })
-                                    .speed(0.1),
-                                );
+                if let Some(notice) = &self.state_notice {
+                    let mut dismissed: bool = false;
+                    ui.horizontal(|ui| {
+                        ui.colored_label(ui.visuals().warn_fg_color, notice);
+                        dismissed = ui.small_button("dismiss").clicked();
+                    });
+                    if dismissed {
+                        self.state_notice = None;
+                    }
+                }
+
+                egui::CollapsingHeader::new("Timeline").show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        if ui.button("Load").on_hover_text("Json file of scheduled parameter changes").clicked() {
+                            match nfd2::open_file_dialog(Some("json"), None).expect("Unable to open the file") {
+                                nfd2::Response::Okay(file_path) => match Timeline::load(&file_path) {
+                                    Ok(timeline) => self.timeline = Some(timeline),
+                                    Err(error) => println!("Unable to load timeline from the file at path {}.\n {:?}", file_path.display(), error),
+                                },
+                                nfd2::Response::OkayMultiple(_) => println!("Multiple files selection should not happen here."),
+                                nfd2::Response::Cancel => (),
                             }
-                            ui.end_row();
+                        }
+
+                        let mut removed: bool = false;
+                        if let Some(timeline) = &mut self.timeline {
+                            ui.label(&timeline.name);
+                            if ui.button("Restart").clicked() {
+                                timeline.restart();
+                            }
+                            removed = ui.button("Remove").clicked();
+                        }
+                        if removed {
+                            self.timeline = None;
                         }
                     });
+
+                    if let Some(timeline) = &mut self.timeline {
+                        timeline.ui_control(ui);
+                    }
+                });
+
+                egui::CollapsingHeader::new("Kernel")
+                .default_open(true)
+                .show(ui, |ui| {
+
+                    let mut lattice = self.lattice;
+                    ui.horizontal(|ui| {
+                        ui.label("Lattice: ");
+                        egui::ComboBox::from_id_source("lattice")
+                            .selected_text(lattice.to_string())
+                            .show_ui(ui, |ui| {
+                                for mode in Lattice::ALL {
+                                    ui.selectable_value(&mut lattice, mode, mode.to_string());
+                                }
+                            });
+                    });
+                    if lattice != self.lattice {
+                        self.set_lattice(lattice);
+                    }
+
+                    let instance = &mut self.instances[self.selected_instance];
+                    let mut kernel_cell = |ui: &mut egui::Ui, i: usize, j: usize| {
+                        ui.add(
+                            egui::DragValue::from_get_set(|optional_value: Option<f64>| {
+                                if let Some(v) = optional_value {
+                                    instance.simulation.simulation_data.uniform.set_kernel_at_with_symmetry(i, j, v as f32, instance.kernel_symmetry_mode);
+                                    instance.simulation.simulation_data.need_update = true;
+                                }
+                                instance.simulation.simulation_data.uniform.get_kernel_at(i,j) as f64
+                            })
+                            .speed(0.1),
+                        );
+                    };
+                    if lattice == Lattice::Square {
+                        egui::Grid::new("kernel grid").show(ui, |ui| {
+                            for j in 0..KERNEL_SIZE {
+                                for i in 0..KERNEL_SIZE {
+                                    kernel_cell(ui, i, j);
+                                }
+                                ui.end_row();
+                            }
+                        });
+                    } else {
+                        // Staggered rows laid out like the cells, only the weights of actual neighbours are shown
+                        let cell_width = ui.spacing().interact_size.x + ui.spacing().item_spacing.x;
+                        for j in 0..KERNEL_SIZE {
+                            ui.horizontal(|ui| {
+                                ui.add_space(lattice.row_indent(j) * cell_width);
+                                for i in (0..KERNEL_SIZE).filter(|&i| lattice.is_used(i, j)) {
+                                    kernel_cell(ui, i, j);
+                                }
+                            });
+                        }
+                    }
+
+                    let instance = &mut self.instances[self.selected_instance];
                     ui.horizontal(|ui| {
                         ui.label("Symmetry mode: ");
                         egui::ComboBox::from_id_source("Symmetry mode: ")
-                            .selected_text(self.kernel_symmetry_mode.to_string())
+                            .selected_text(instance.kernel_symmetry_mode.to_string())
                             .show_ui(ui, |ui| {
                                 let mut changed: bool = false;
-                                changed |= ui
-                                    .selectable_value(&mut self.kernel_symmetry_mode, KernelSymmetryMode::Any, KernelSymmetryMode::Any.to_string())
-                                    .changed();
-                                changed |= ui
-                                    .selectable_value(
-                                        &mut self.kernel_symmetry_mode,
-                                        KernelSymmetryMode::Vertical,
-                                        KernelSymmetryMode::Vertical.to_string(),
-                                    )
-                                    .changed();
-                                changed |= ui
-                                    .selectable_value(
-                                        &mut self.kernel_symmetry_mode,
-                                        KernelSymmetryMode::Horizontal,
-                                        KernelSymmetryMode::Horizontal.to_string(),
-                                    )
-                                    .changed();
-                                changed |= ui
-                                    .selectable_value(&mut self.kernel_symmetry_mode, KernelSymmetryMode::Full, KernelSymmetryMode::Full.to_string())
-                                    .changed();
+                                for &mode in lattice.symmetry_modes() {
+                                    changed |= ui.selectable_value(&mut instance.kernel_symmetry_mode, mode, mode.to_string()).changed();
+                                }
                                 if changed {
-                                    self.simulation_data.uniform.apply_symmetry(self.kernel_symmetry_mode);
+                                    instance.simulation.simulation_data.uniform.apply_symmetry(instance.kernel_symmetry_mode);
+                                    instance.simulation.simulation_data.need_update = true;
                                 }
                             });
                     });
 
+                    ui.horizontal(|ui| {
+                        ui.label("Boundary: ");
+                        egui::ComboBox::from_id_source("boundary_condition")
+                            .selected_text(instance.boundary_condition.to_string())
+                            .show_ui(ui, |ui| {
+                                for mode in BoundaryCondition::ALL {
+                                    if ui.selectable_label(instance.boundary_condition.shader_mode() == mode.shader_mode(), mode.to_string()).clicked() {
+                                        instance.boundary_condition = mode;
+                                    }
+                                }
+                            });
+                        if let BoundaryCondition::Constant(value) = &mut instance.boundary_condition {
+                            ui.add(egui::DragValue::new(value).speed(0.01).range(0.0..=1.0));
+                        }
+                    });
+
                     ui.checkbox(&mut self.reset_on_randomize, "reset simulation on randomize");
 
-                    if ui.button("randomize").clicked() {
-                        self.randomize_kernel();
+                    ui.horizontal(|ui| {
+                        if ui.button("randomize").clicked() {
+                            self.kernel_randomizer.reseed();
+                            self.randomize_kernel();
 
-                        if self.reset_on_randomize {
-                            self.init = false;
+                            if self.reset_on_randomize {
+                                self.init = false;
+                            }
                         }
-                    }
+
+                        if ui.button("regenerate from seed").clicked() {
+                            self.randomize_kernel();
+
+                            if self.reset_on_randomize {
+                                self.init = false;
+                            }
+                        }
+                    });
 
                     ui.indent("", |ui| {
+                        self.kernel_randomizer.ui_control(ui);
+                    });
+
+                    egui::CollapsingHeader::new("Import / Export").show(ui, |ui| {
                         ui.horizontal(|ui| {
-                            ui.label("Range: ");
-                            ui.add(&mut self.kernel_rand_range);
+                            ui.label("Format: ");
+                            egui::ComboBox::from_id_source("kernel_text_format")
+                                .selected_text(self.kernel_text_format.to_string())
+                                .show_ui(ui, |ui| {
+                                    for format in KernelFormat::ALL {
+                                        ui.selectable_value(&mut self.kernel_text_format, format, format.to_string());
+                                    }
+                                });
+                        });
+
+                        ui.add(egui::TextEdit::multiline(&mut self.kernel_text).code_editor().desired_rows(3));
+
+                        ui.horizontal(|ui| {
+                            if ui.button("Copy kernel").clicked() {
+                                self.kernel_text = self.instance().simulation.simulation_data.uniform.kernel_to_string(self.kernel_text_format);
+                                ctx.copy_text(self.kernel_text.clone());
+                            }
+                            if ui.button("Paste kernel").clicked() {
+                                let instance = &mut self.instances[self.selected_instance];
+                                match instance.simulation.simulation_data.uniform.set_kernel_from_str(&self.kernel_text, instance.kernel_symmetry_mode) {
+                                    Ok(()) => instance.simulation.simulation_data.need_update = true,
+                                    Err(error) => println!("Unable to paste the kernel.\n {:?}", error),
+                                }
+                            }
+                        });
+
+                        ui.horizontal(|ui| {
+                            if ui.button("Load from file").clicked() {
+                                match nfd2::open_file_dialog(None, None).expect("Unable to open the file") {
+                                    nfd2::Response::Okay(file_path) => {
+                                        let path: &Path = file_path.as_path();
+                                        self.load_kernel_from_file(&path).unwrap_or_else(|error| {
+                                            println!("Unable to load kernel from the file at path {}.\n {:?}", path.display(), error);
+                                        });
+                                    },
+                                    nfd2::Response::OkayMultiple(_) => println!("Multiple files selection should not happen here."),
+                                    nfd2::Response::Cancel => (),
+                                }
+                            }
+                            if ui.button("Save to file").clicked() {
+                                match nfd2::open_save_dialog(None, None).expect("Unable to save the file") {
+                                    nfd2::Response::Okay(file_path) => {
+                                        let path: &Path = file_path.as_path();
+                                        self.save_kernel(&path).unwrap_or_else(|error| {
+                                            println!("Unable to save the kernel at path {}.\n {:?}", path.display(), error);
+                                        });
+                                    },
+                                    nfd2::Response::OkayMultiple(_) => println!("Multiple files selection should not happen here."),
+                                    nfd2::Response::Cancel => (),
+                                }
+                            }
                         });
                     });
                 });
@@ -712,22 +1436,11 @@ impl App for NcaApp {
                 ui.separator();
 
                 // TODO
-                let mut code_editor = CodeEditor::new(&mut self.activation_code, "rs", Some(15));
+                let mut code_editor = CodeEditor::new(&mut self.instances[self.selected_instance].activation_code, "rs", Some(15));
                 
                 code_editor.show(ui);
 
-                let code_to_paste: Option<String> = ctx.input(|input_state| {
-                    input_state.events.iter().find_map(|e| match e {
-                        egui::Event::Paste(paste_content) => Some((*paste_content).to_owned()),
-                        _ => None,
-                    })
-                });
-
-                if let Some(new_code) = code_to_paste {
-                    self.activation_code = new_code;
-                }
-
-                if let ShaderState::CompilationFail(error) = &self.shader_state {
+                if let ShaderState::CompilationFail(error) = &self.instance().shader_state {
                     ui.label(format!("Shader compile error:\n {}", error));
                 }
 
@@ -740,15 +1453,16 @@ impl App for NcaApp {
                             }
                         }
                         if let Some(preset) = preset_to_apply {
-                            self.activation_code = preset.to_owned();
-                            self.shader_state = ShaderState::Dirty;
+                            let instance = self.instance_mut();
+                            instance.activation_code = preset.to_owned();
+                            instance.shader_state = ShaderState::Dirty;
                             ui.close_menu();
                         }
                     });
                 });
 
                 if ui.button("Recompile").clicked() {
-                    self.shader_state = ShaderState::Dirty;
+                    self.instance_mut().shader_state = ShaderState::Dirty;
                 }
             });
 
@@ -767,26 +1481,150 @@ impl App for NcaApp {
 
                 ui.separator();
 
-                self.view_data.uniform.gradient.show(ui);
-                if self.view_data.uniform.gradient.ui_control(ui) {
+                let view_parameters = &mut self.view_data.uniform;
+                let mut aspect_mode = AspectMode::from_shader_mode(view_parameters.aspect_mode);
+                let mut pixel_perfect = view_parameters.pixel_perfect != 0;
+                let mut tile = view_parameters.tile != 0;
+                let mut framing_changed = false;
+                ui.horizontal(|ui| {
+                    ui.label("Aspect: ");
+                    egui::ComboBox::from_id_source("aspect_mode").selected_text(aspect_mode.to_string()).show_ui(ui, |ui| {
+                        for mode in AspectMode::ALL {
+                            framing_changed |= ui.selectable_value(&mut aspect_mode, mode, mode.to_string()).changed();
+                        }
+                    });
+                    framing_changed |= ui
+                        .checkbox(&mut pixel_perfect, "Pixel perfect")
+                        .on_hover_text("Whole number of screen pixels per cell, aligned on the pixels")
+                        .changed();
+                });
+                ui.horizontal(|ui| {
+                    let max_zoom_level = view_parameters.max_zoom_level();
+                    framing_changed |= ui
+                        .add(egui::DragValue::new(&mut view_parameters.zoom_level).speed(0.01).range(0.001..=max_zoom_level).prefix("zoom: ").suffix(" cells / px"))
+                        .changed();
+                    if ui.button("Fit").on_hover_text("Whole grid according to the aspect mode").clicked() {
+                        view_parameters.center = Vec2::splat(0.5);
+                        view_parameters.zoom_level = view_parameters.fitted_zoom_level();
+                        framing_changed = true;
+                    }
+                });
+                framing_changed |= ui
+                    .checkbox(&mut tile, "Tile (infinite pan)")
+                    .on_hover_text("Repeats the toroidal grid around itself, to pan without limit and zoom out on several copies")
+                    .changed();
+                if framing_changed {
+                    view_parameters.aspect_mode = aspect_mode.shader_mode();
+                    view_parameters.pixel_perfect = pixel_perfect as u32;
+                    view_parameters.tile = tile as u32;
+                    view_parameters.constrain();
                     self.view_data.need_update = true;
                 }
 
-                ui.menu_button("Gradient Presets", |ui| {
-                    egui::ScrollArea::vertical().show(ui, |ui| {
-                        let mut preset_to_apply: Option<IqGradient> = None;
-                        for (name, preset) in IQ_GRADIENT_PRESETS.iter() {
-                            if ui.button(*name).clicked() {
-                                preset_to_apply = Some(preset.clone());
-                            }
+                let mut show_grid = view_parameters.show_grid != 0;
+                let mut grid_color = view_parameters.grid_color.to_array();
+                let mut grid_changed = false;
+                ui.horizontal(|ui| {
+                    grid_changed |= ui.checkbox(&mut show_grid, "Cell borders").changed();
+                    grid_changed |= egui::color_picker::color_edit_button_rgba_unmultiplied(ui, &mut grid_color).changed();
+                });
+                ui.horizontal(|ui| {
+                    grid_changed |= ui.add(egui::DragValue::new(&mut view_parameters.grid_width).speed(0.1).range(0.5..=8.0).prefix("width: ").suffix(" px")).changed();
+                    grid_changed |= ui.add(egui::DragValue::new(&mut view_parameters.grid_min_cell_size).range(2.0..=256.0).prefix("from cells of ").suffix(" px")).changed();
+                });
+                if grid_changed {
+                    view_parameters.show_grid = show_grid as u32;
+                    view_parameters.grid_color = Vec4::from_array(grid_color);
+                    self.view_data.need_update = true;
+                }
+
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.cell_labels.enabled, "Cell values");
+                    ui.add(egui::DragValue::new(&mut self.cell_labels.min_cell_size).range(16.0..=512.0).prefix("from cells of ").suffix(" pt"));
+                });
+
+                ui.separator();
+
+                let mut color_map_changed = false;
+                ui.horizontal(|ui| {
+                    ui.label("Color map: ");
+                    egui::ComboBox::from_id_source("color_map_kind").selected_text(self.color_map.kind.to_string()).show_ui(ui, |ui| {
+                        for kind in ColorMapKind::ALL {
+                            color_map_changed |= ui.selectable_value(&mut self.color_map.kind, kind, kind.to_string()).changed();
                         }
-                        if let Some(preset) = preset_to_apply {
-                            self.view_data.uniform.gradient = preset;
+                    });
+                });
+
+                match self.color_map.kind {
+                    ColorMapKind::Cosine => {
+                        self.view_data.uniform.gradient.show(ui);
+                        if self.view_data.uniform.gradient.ui_control(ui) {
                             self.view_data.need_update = true;
-                            ui.close_menu();
+                        }
+
+                        ui.menu_button("Gradient Presets", |ui| {
+                            egui::ScrollArea::vertical().show(ui, |ui| {
+                                let mut preset_to_apply: Option<IqGradient> = None;
+                                for (name, preset) in IQ_GRADIENT_PRESETS.iter() {
+                                    if ui.button(*name).clicked() {
+                                        preset_to_apply = Some(preset.clone());
+                                    }
+                                }
+                                if let Some(preset) = preset_to_apply {
+                                    self.view_data.uniform.gradient = preset;
+                                    self.view_data.need_update = true;
+                                    ui.close_menu();
+                                }
+                            });
+                        });
+                    },
+                    ColorMapKind::Stops => {
+                        self.color_map.stops.show(ui);
+                        color_map_changed |= self.color_map.stops.ui_control(ui, MAX_COLOR_STOPS);
+                    },
+                    _ => {
+                        let parameters = self.color_map.parameters();
+                        let gradient = self.view_data.uniform.gradient;
+                        gradient_preview(ui, |t| parameters.color_at(&gradient, t));
+                    },
+                }
+
+                ui.horizontal(|ui| {
+                    color_map_changed |= ui.add(egui::DragValue::new(&mut self.color_map.range[0]).speed(0.01).prefix("from: ")).changed();
+                    color_map_changed |= ui.add(egui::DragValue::new(&mut self.color_map.range[1]).speed(0.01).prefix("to: ")).changed();
+                    color_map_changed |= ui.checkbox(&mut self.color_map.log_scale, "Log scale").changed();
+                });
+                color_map_changed |= ui.add(egui::DragValue::new(&mut self.color_map.gamma).speed(0.01).range(0.05..=10.0).prefix("gamma: ")).changed();
+
+                if color_map_changed {
+                    self.view_data.uniform.color_map = self.color_map.parameters();
+                    self.view_data.need_update = true;
+                }
+
+                ui.separator();
+                ui.collapsing("Display shader", |ui| {
+                    ui.label("colorize(state, uv) turns the state of a cell into its displayed color, colorMap(value) applies the color map above.");
+                    CodeEditor::new(&mut self.display_code, "rs", Some(8)).show(ui);
+
+                    if let ShaderState::CompilationFail(error) = &self.display_shader_state {
+                        ui.label(format!("Shader compile error:\n {}", error));
+                    }
+
+                    ui.horizontal(|ui| {
+                        if ui.button("Recompile").clicked() {
+                            self.display_shader_state = ShaderState::Dirty;
+                        }
+                        if ui.button("Reset").on_hover_text("Display through the color map").clicked() {
+                            self.display_code = DEFAULT_DISPLAY_CODE.to_owned();
+                            self.display_shader_state = ShaderState::Dirty;
                         }
                     });
                 });
+
+                ui.collapsing("Post processing", |ui| {
+                    ui.label("Effects applied in order to the displayed image, trails blend the previous frames to hide flickering.");
+                    self.post_processing.ui_control(ui);
+                });
             });
 
             ui.allocate_space(ui.available_size());
@@ -801,17 +1639,101 @@ impl App for NcaApp {
         self.ui_central_viewport.width = center_size.x;
         self.ui_central_viewport.height = center_size.y;
 
+        self.cell_labels.paint(
+            &ctx,
+            &self.ui_central_viewport.split_horizontally(self.instances.len()),
+            &self.view_data.uniform,
+            self.lattice,
+            self.simulation_size_state.current(),
+        );
+        self.cell_inspector.track_pointer(
+            &ctx,
+            &self.ui_central_viewport.split_horizontally(self.instances.len()),
+            &self.view_data.uniform,
+            self.lattice,
+            self.simulation_size_state.current(),
+        );
+
+        // Name of each simulation on top of its viewport, clicking it selects the simulation
+        if self.instances.len() > 1 {
+            let instance_viewports = self.ui_central_viewport.split_horizontally(self.instances.len());
+            for (index, viewport) in instance_viewports.iter().enumerate() {
+                egui::Area::new(egui::Id::new(("simulation instance label", index)))
+                    .fixed_pos(egui::pos2(viewport.x + 4.0, viewport.y + 4.0))
+                    .show(&ctx, |ui| {
+                        if ui.selectable_label(index == self.selected_instance, &self.instances[index].name).clicked() && index != self.selected_instance {
+                            self.selected_instance = index;
+                            self.state_detector.reset();
+                        }
+                    });
+            }
+        }
+
         Ok(())
     }
 
     fn update(&mut self, _app_state: &mut AppState) -> Result<()> {
+        let timeline_actions = match &mut self.timeline {
+            Some(timeline) => {
+                timeline.tick(self.paused);
+                timeline.due_actions()
+            },
+            None => Vec::new(),
+        };
+        for action in timeline_actions {
+            self.apply_timeline_action(action);
+        }
 
-        let device = &mut _app_state.render_instance.devices[_app_state.surface_handle.device_handle_id].device;
+        self.animation.tick(self.paused);
+        if self.animation.need_apply {
+            self.animation.need_apply = false;
+            for (parameter, value) in self.animation.values() {
+                self.set_animated_value(parameter, value);
+            }
+        }
+
+        let device_handle = &mut _app_state.render_instance.devices[_app_state.surface_handle.device_handle_id];
+        let (device, queue) = (&mut device_handle.device, &device_handle.queue);
         let surface_config = &_app_state.surface_handle.config;
-        if let ShaderState::Dirty = self.shader_state {
-            match self.try_generate_simulation_pipeline(device, surface_config) {
+        for instance in self.instances.iter_mut() {
+            instance.update_boundary_condition();
+
+            if let ShaderState::Dirty = instance.shader_state {
+                match instance.try_generate_simulation_pipeline() {
+                    Err(err) => match err {
+                        wgpu::Error::Validation { description, .. } => instance.shader_state = ShaderState::CompilationFail(description),
+                        wgpu::Error::OutOfMemory { source } => {
+                            anyhow::bail!("wgpu::Error::OutOfMemory: {}", source)
+                        },
+                        wgpu::Error::Internal { source, description } => {
+                            anyhow::bail!("Shader compilation gpu::Error::Internal: {} (details: {})", source, description)
+                        }
+                    },
+                    Ok(()) => {},
+                }
+            }
+        }
+
+        if let ShaderState::Dirty = self.display_shader_state {
+            match self.try_generate_screen_pipeline(device, surface_config) {
+                Err(err) => match err {
+                    wgpu::Error::Validation { description, .. } => self.display_shader_state = ShaderState::CompilationFail(description),
+                    wgpu::Error::OutOfMemory { source } => {
+                        anyhow::bail!("wgpu::Error::OutOfMemory: {}", source)
+                    },
+                    wgpu::Error::Internal { source, description } => {
+                        anyhow::bail!("Shader compilation gpu::Error::Internal: {} (details: {})", source, description)
+                    }
+                },
+                Ok(()) => {},
+            }
+        }
+
+        if self.duplicate_instance_requested {
+            self.duplicate_instance_requested = false;
+            match self.try_duplicate_selected_instance(device, queue) {
                 Err(err) => match err {
-                    wgpu::Error::Validation { description, .. } => self.shader_state = ShaderState::CompilationFail(description),
+                    wgpu::Error::Validation { description, .. } => self.instance_mut().shader_state = ShaderState::CompilationFail(description),
                     wgpu::Error::OutOfMemory { source } => {
                         anyhow::bail!("wgpu::Error::OutOfMemory: {}", source)
                     },
@@ -829,7 +1751,7 @@ impl App for NcaApp {
                     // Reset to dirty state
                     self.simulation_size_state = SimulationSizeState::Dirty { old, new };
                     match err {
-                        wgpu::Error::Validation { description, .. } => self.shader_state = ShaderState::CompilationFail(description),
+                        wgpu::Error::Validation { description, .. } => self.instance_mut().shader_state = ShaderState::CompilationFail(description),
                         wgpu::Error::OutOfMemory { source } => {
                             anyhow::bail!("wgpu::Error::OutOfMemory: {}", source)
                         },
@@ -842,6 +1764,39 @@ impl App for NcaApp {
             }
         }
 
+        if let Some(action) = self.pending_recorder_action.take() {
+            self.apply_recorder_action(action, device, surface_config);
+        }
+
+        if let Some(action) = self.pending_still_render_action.take() {
+            let device_handle = &_app_state.render_instance.devices[_app_state.surface_handle.device_handle_id];
+            self.apply_still_render_action(action, &device_handle.device, &device_handle.queue, &_app_state.surface_handle.config);
+        }
+
+        {
+            let device_handle = &_app_state.render_instance.devices[_app_state.surface_handle.device_handle_id];
+            self.cell_labels.update(
+                &device_handle.device,
+                &device_handle.queue,
+                &self.cell_probe,
+                &self.instances,
+                &self.ui_central_viewport.split_horizontally(self.instances.len()),
+                &self.view_data.uniform,
+                self.simulation_size_state.current(),
+            );
+            self.cell_inspector.update(&device_handle.device, &device_handle.queue, &self.cell_probe, &self.instances);
+        }
+
+        if self.explorer.is_running() {
+            let device_handle = &mut _app_state.render_instance.devices[_app_state.surface_handle.device_handle_id];
+            self.explorer.advance(&mut device_handle.device, &device_handle.queue);
+        }
+
+        if self.evolution.is_active() {
+            let device_handle = &mut _app_state.render_instance.devices[_app_state.surface_handle.device_handle_id];
+            self.evolution.advance(&mut device_handle.device, &device_handle.queue);
+        }
+
         Ok(())
     }
 
@@ -857,189 +1812,202 @@ impl App for NcaApp {
                     .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("encoder") });
 
         
-        if self.last_simulation_end.elapsed() > self.target_delta {
+        let mut sample_statistics: bool = false;
+        let mut capture_frame: bool = false;
+        // While recording, each video frame advances a fixed number of generations whatever the update rate
+        let recording = self.recorder.is_recording();
+        if (recording || self.last_simulation_end.elapsed() > self.target_delta) && (!self.paused || !self.init) {
             // init if needed
             if self.init == false {
                 self.init = true;
+                self.generation = 0;
+                self.state_detector.reset();
 
-                if self.init_simulation_data.need_update {
-                    self.init_simulation_data.update(&device_handle.queue);
+                for instance in self.instances.iter_mut() {
+                    instance.simulation.encode_init(&mut encoder, self.init_parameters);
                 }
-                
-                let mut init_simulation_render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                    label: Some("Init Simulation Render Pass"),
-                    color_attachments: &[
-                        Some(wgpu::RenderPassColorAttachment {
-                            view: &self.simulation_textures.get_rendered_texture_view(),
-                            resolve_target: None,
-                            ops: wgpu::Operations {
-                                load: wgpu::LoadOp::Clear(self.clear_color),
-                                store: wgpu::StoreOp::Store,
-                            },
-                            depth_slice: None,
-                        })
-                    ],
-                    depth_stencil_attachment: None,
-                    timestamp_writes: None, 
-                    occlusion_query_set: None,
-                });
-
-                init_simulation_render_pass.set_pipeline(&self.init_simulation_render_pipeline);
-                init_simulation_render_pass.set_bind_group(0, &self.init_simulation_data.bind_group, &[]);
-                init_simulation_render_pass.draw(0..3, 0..1);
             }
 
             // simulation
-            {
-                if self.simulation_data.need_update {
-                    self.simulation_data.update(&device_handle.queue);
+            let steps = if recording { self.recorder.settings.steps_per_frame.max(1) } else { 1 };
+            self.statistics_pass.reserve(&device_handle.device, steps as usize);
+            for _ in 0..steps {
+                for instance in self.instances.iter_mut() {
+                    instance.simulation.encode_step(&mut encoder, 1);
                 }
 
-                let mut simulation_render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                    label: Some("Simulation Render Pass"),
-                    color_attachments: &[
-                        Some(wgpu::RenderPassColorAttachment {
-                            view: self.simulation_textures.get_target_texture_view(),
-                            resolve_target: None,
-                            ops: wgpu::Operations {
-                                load: wgpu::LoadOp::Clear(self.clear_color),
-                                store: wgpu::StoreOp::Store,
-                            },
-                            depth_slice: None,
-                        })
-                    ],
-                    depth_stencil_attachment: None,
-                    timestamp_writes: None, 
-                    occlusion_query_set: None,
-                });
-
-                simulation_render_pass.set_pipeline(&self.simulation_render_pipeline);
-                let bind_group: &wgpu::BindGroup = if self.simulation_textures.state {
-                    &self.bind_group_simulation_pong
-                } else {
-                    &self.bind_group_simulation_ping
-                };
-                simulation_render_pass.set_bind_group(0, bind_group, &[]);
-                simulation_render_pass.set_bind_group(1, &self.simulation_data.bind_group, &[]);
-                simulation_render_pass.draw(0..3, 0..1);
+                self.generation += 1;
+                if let Some(timeline) = &mut self.timeline {
+                    timeline.on_generation();
+                }
+                self.animation.on_generation();
+
+                // Statistics and state detection follow the selected simulation,
+                // every generation is reduced so that the detected periods are counted in generations
+                if self.statistics.wants_sample(self.generation) || self.detection_policy != DetectionPolicy::Disabled {
+                    let simulation = &self.instances[self.selected_instance].simulation;
+                    self.statistics_pass.encode(
+                        &device_handle.device,
+                        &mut encoder,
+                        simulation.current_view(),
+                        simulation.previous_view(),
+                        self.simulation_size_state.current(),
+                        self.generation,
+                    );
+                    sample_statistics = true;
+                }
             }
 
             self.last_simulation_end = Instant::now();
-            self.simulation_textures.toogle_state();
+            capture_frame = recording;
         };
 
 
         // render simulation on screen
         {
+            // it must be multiplied by window scale factor as render pass use physical pixels screen size
+            let window_scale_factor = _app_state.window.scale_factor() as f32;
+
+            // Every instance viewport has the same size, resizing the viewport or the simulation keeps the framing
+            let instance_viewport = &self.ui_central_viewport.split_horizontally(self.instances.len())[0];
+            let viewport_size = Vec2::new(instance_viewport.width, instance_viewport.height) * window_scale_factor;
+            let [width, height] = self.simulation_size_state.current();
+            let texture_size = Vec2::new(width as f32, height as f32);
+            let view_parameters = &self.view_data.uniform;
+            if viewport_size.min_element() >= 1.0 && (view_parameters.viewport_size != viewport_size || view_parameters.texture_size != texture_size) {
+                self.view_data.uniform = view_parameters.resized(viewport_size, texture_size);
+                self.view_data.need_update = true;
+            }
+
             if self.view_data.need_update {
                 self.view_data.update(&device_handle.queue);
             }
-
-            let mut screen_render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Screen Render Pass"),
-                color_attachments: &[
-                    // This is what [[location(0)]] in the fragment shader targets
-                    Some(wgpu::RenderPassColorAttachment {
-                        view: &_output_view,
-                        resolve_target: None,
-                        ops: wgpu::Operations { load: wgpu::LoadOp::Load, store: wgpu::StoreOp::Store },
-                        depth_slice: None,
-                    }),
-                ],
-                depth_stencil_attachment: None,
-                timestamp_writes: None, 
-                occlusion_query_set: None,
-            });
-
-            // update viewport accordingly to the Ui to display the simulation
-            // it must be multiplied by window scale factor as render pass use physical pixels screen size
-
-            let window_scale_factor = _app_state.window.scale_factor() as f32;
-            screen_render_pass.set_viewport(
+            let central_viewport = [
                 self.ui_central_viewport.x * window_scale_factor,
                 self.ui_central_viewport.y * window_scale_factor,
                 self.ui_central_viewport.width * window_scale_factor,
                 self.ui_central_viewport.height * window_scale_factor,
-                self.ui_central_viewport.min_depth,
-                self.ui_central_viewport.max_depth,
-            );
+            ];
+
+            // With post processing, the simulations are rendered in a texture covering the central viewport
+            let post_processing = self.post_processing.is_active();
+            let (target_view, target_origin) = if post_processing {
+                let scene_size = [central_viewport[2].round() as u32, central_viewport[3].round() as u32];
+                (self.post_processor.scene_view(&device_handle.device, scene_size), Vec2::new(central_viewport[0], central_viewport[1]))
+            } else {
+                (_output_view, Vec2::ZERO)
+            };
+
+            {
+                let mut screen_render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Screen Render Pass"),
+                    color_attachments: &[
+                        // This is what [[location(0)]] in the fragment shader targets
+                        Some(wgpu::RenderPassColorAttachment {
+                            view: target_view,
+                            resolve_target: None,
+                            ops: wgpu::Operations { load: wgpu::LoadOp::Load, store: wgpu::StoreOp::Store },
+                            depth_slice: None,
+                        }),
+                    ],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
 
-            screen_render_pass.set_pipeline(&self.screen_render_pipeline);
+                screen_render_pass.set_pipeline(&self.screen_render_pipeline);
+                screen_render_pass.set_bind_group(1, &self.view_data.bind_group, &[]);
+
+                // update viewport accordingly to the Ui to display the simulations side by side
+                let instance_viewports = self.ui_central_viewport.split_horizontally(self.instances.len());
+                for (instance, viewport) in self.instances.iter().zip(instance_viewports.iter()) {
+                    screen_render_pass.set_viewport(
+                        viewport.x * window_scale_factor - target_origin.x,
+                        viewport.y * window_scale_factor - target_origin.y,
+                        viewport.width * window_scale_factor,
+                        viewport.height * window_scale_factor,
+                        viewport.min_depth,
+                        viewport.max_depth,
+                    );
 
-            let bind_group: &wgpu::BindGroup = match self.display_frames_mode {
-                DisplayFramesMode::All =>
-                    if self.simulation_textures.state {
-                        &self.bind_group_display_pong
-                    } else {
-                        &self.bind_group_display_ping
-                    },
-                DisplayFramesMode::Evens => &self.bind_group_display_pong,
-                DisplayFramesMode::Odd => &self.bind_group_display_ping,
-            };
+                    screen_render_pass.set_bind_group(0, instance.simulation.display_bind_group(&self.display_frames_mode), &[]);
+                    screen_render_pass.draw(0..3, 0..1);
+                }
+            }
 
-            screen_render_pass.set_bind_group(0, bind_group, &[]);
-            screen_render_pass.set_bind_group(1, &self.view_data.bind_group, &[]);
-            screen_render_pass.draw(0..3, 0..1);
+            if post_processing {
+                self.post_processor.encode(
+                    &device_handle.device,
+                    &device_handle.queue,
+                    &mut encoder,
+                    &self.post_processing.effects,
+                    _output_view,
+                    Some(central_viewport),
+                );
+            }
+        }
+
+        // render the selected simulation in the recording texture, at the resolution of the video
+        if capture_frame {
+            // The settings can not change while recording
+            let recording_size = self.recorder.settings.size;
+            if let Some((recording_view, recording_pipeline, recording_view_data, post_processor)) = self.recorder.target() {
+                recording_view_data.uniform = self.view_data.uniform.resized(Vec2::new(recording_size[0] as f32, recording_size[1] as f32), self.view_data.uniform.texture_size);
+                recording_view_data.update(&device_handle.queue);
+
+                let post_processing = self.post_processing.is_active();
+                let target_view = if post_processing { post_processor.scene_view(&device_handle.device, recording_size) } else { recording_view };
+
+                {
+                    let mut recording_render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some("Recording Render Pass"),
+                        color_attachments: &[
+                            Some(wgpu::RenderPassColorAttachment {
+                                view: target_view,
+                                resolve_target: None,
+                                ops: wgpu::Operations { load: wgpu::LoadOp::Clear(self.clear_color), store: wgpu::StoreOp::Store },
+                                depth_slice: None,
+                            }),
+                        ],
+                        depth_stencil_attachment: None,
+                        timestamp_writes: None,
+                        occlusion_query_set: None,
+                    });
+
+                    recording_render_pass.set_pipeline(recording_pipeline);
+                    recording_render_pass.set_bind_group(0, self.instances[self.selected_instance].simulation.display_bind_group(&self.display_frames_mode), &[]);
+                    recording_render_pass.set_bind_group(1, &recording_view_data.bind_group, &[]);
+                    recording_render_pass.draw(0..3, 0..1);
+                }
+
+                if post_processing {
+                    post_processor.encode(&device_handle.device, &device_handle.queue, &mut encoder, &self.post_processing.effects, recording_view, None);
+                }
+            }
         }
 
         device_handle.queue.submit(Some(encoder.finish()));
 
+        if capture_frame {
+            self.recorder.capture(&device_handle.device, &device_handle.queue);
+        }
+
+        if sample_statistics {
+            let mut event = None;
+            for statistics in self.statistics_pass.read(&device_handle.device) {
+                // The generations following an event belong to the run it ends
+                if event.is_none() && self.detection_policy != DetectionPolicy::Disabled {
+                    event = self.state_detector.observe(&statistics).map(|event| (event, statistics.generation));
+                }
+                if self.statistics.wants_sample(statistics.generation) {
+                    self.statistics.push(statistics);
+                }
+            }
+            if let Some((event, generation)) = event {
+                self.apply_state_event(event, generation);
+            }
+        }
+
         Ok(())
     }
 }
-
-lazy_static! {
-    pub static ref ACTIVATION_FUNCTIONS_PRESETS: std::collections::HashMap<&'static str, &'static str> = std::collections::HashMap::from([
-        (
-            "Identity",
-            "
-fn activationFunction(kernelOutput: vec4<f32>) -> vec4<f32> {
-var r: f32 = kernelOutput.x;
-return vec4<f32>(r, r, r, 1.0);
-}"
-        ),
-        (
-            "Sin",
-            "
-fn activationFunction(kernelOutput: vec4<f32>) -> vec4<f32> {
-var r: f32 = sin(kernelOutput.x);
-return vec4<f32>(r, r, r, 1.0);
-}"
-        ),
-        (
-            "Abs",
-            "
-fn activationFunction(kernelOutput: vec4<f32>) -> vec4<f32> {
-var r: f32 = abs(kernelOutput.x);
-return vec4<f32>(r, r, r, 1.0);
-}"
-        ),
-        (
-            "Power",
-            "
-fn activationFunction(kernelOutput: vec4<f32>) -> vec4<f32> {
-var r: f32 = pow(kernelOutput.x, 2.0);
-return vec4<f32>(r, r, r, 1.0);
-}"
-        ),
-        (
-            "Tanh",
-            "
-fn activationFunction(kernelOutput: vec4<f32>) -> vec4<f32> {
-var r: f32 = (exp(2. * kernelOutput.x) -1.) / (exp(2. * kernelOutput.x) + 1.);
-return vec4<f32>(r, r, r, 1.0);
-}"
-        ),
-        (
-            " inverted gaussian",
-            "
-// an inverted gaussian function, 
-// where f(0) = 0. 
-// Graph: https://www.desmos.com/calculator/torawryxnq
-fn activationFunction(kernelOutput: vec4<f32>) -> vec4<f32> {
-var r: f32 = -1./(0.89*pow(kernelOutput.x, 2.)+1.)+1.;
-return vec4<f32>(r, r, r, 1.0);
-}"
-        ),
-    ]);
-}
@@ -0,0 +1,471 @@
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::Display,
+    fs::File,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use oxyde::egui as egui;
+
+use nca_core::{simulation_data::KERNEL_SIZE, PostProcessing};
+
+const TRACK_HEIGHT: f32 = 18.0;
+const KEYFRAME_RADIUS: f32 = 5.0;
+
+/// Value driven by an animation track
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AnimatedParameter {
+    /// Kernel weight at a row major index, propagated to its orbit under the kernel symmetry
+    Kernel(usize),
+    GradientA(usize),
+    GradientB(usize),
+    GradientC(usize),
+    GradientD(usize),
+    Zoom,
+    CenterX,
+    CenterY,
+    /// Values mapped to the start and the end of the color map
+    ColorMapFrom,
+    ColorMapTo,
+    ColorMapGamma,
+    /// Effect at an index of the post processing stack and index of its scalar parameter, see `PostEffect::parameter_names`
+    PostEffect(usize, usize),
+}
+
+impl AnimatedParameter {
+    /// Parameters of the current post processing stack included
+    pub fn all(post_processing: &PostProcessing) -> Vec<AnimatedParameter> {
+        let mut parameters: Vec<AnimatedParameter> = (0..KERNEL_SIZE * KERNEL_SIZE).map(AnimatedParameter::Kernel).collect();
+        for channel in 0..3 {
+            parameters.extend([
+                AnimatedParameter::GradientA(channel),
+                AnimatedParameter::GradientB(channel),
+                AnimatedParameter::GradientC(channel),
+                AnimatedParameter::GradientD(channel),
+            ]);
+        }
+        parameters.extend([AnimatedParameter::Zoom, AnimatedParameter::CenterX, AnimatedParameter::CenterY]);
+        parameters.extend([AnimatedParameter::ColorMapFrom, AnimatedParameter::ColorMapTo, AnimatedParameter::ColorMapGamma]);
+        for (effect, post_effect) in post_processing.effects.iter().enumerate() {
+            parameters.extend((0..post_effect.parameter_names().len()).map(|parameter| AnimatedParameter::PostEffect(effect, parameter)));
+        }
+        parameters
+    }
+
+    /// Name of the post processing parameters taken from the effects, which the tracks only refer to by index
+    pub fn label(&self, post_processing: &PostProcessing) -> String {
+        match self {
+            AnimatedParameter::PostEffect(effect, parameter) => post_processing
+                .effects
+                .get(*effect)
+                .and_then(|post_effect| {
+                    let name = post_effect.parameter_names().get(*parameter)?;
+                    Some(format!("{} {} ({})", post_effect.to_string().to_lowercase(), name, effect))
+                })
+                .unwrap_or_else(|| self.to_string()),
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl Display for AnimatedParameter {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        const CHANNELS: [&str; 3] = ["r", "g", "b"];
+        match self {
+            AnimatedParameter::Kernel(index) => write!(f, "kernel [{}, {}]", index / KERNEL_SIZE, index % KERNEL_SIZE),
+            AnimatedParameter::GradientA(channel) => write!(f, "gradient a.{}", CHANNELS[*channel % 3]),
+            AnimatedParameter::GradientB(channel) => write!(f, "gradient b.{}", CHANNELS[*channel % 3]),
+            AnimatedParameter::GradientC(channel) => write!(f, "gradient c.{}", CHANNELS[*channel % 3]),
+            AnimatedParameter::GradientD(channel) => write!(f, "gradient d.{}", CHANNELS[*channel % 3]),
+            AnimatedParameter::Zoom => write!(f, "zoom"),
+            AnimatedParameter::CenterX => write!(f, "center x"),
+            AnimatedParameter::CenterY => write!(f, "center y"),
+            AnimatedParameter::ColorMapFrom => write!(f, "color map from"),
+            AnimatedParameter::ColorMapTo => write!(f, "color map to"),
+            AnimatedParameter::ColorMapGamma => write!(f, "color map gamma"),
+            AnimatedParameter::PostEffect(effect, parameter) => write!(f, "post effect {} parameter {}", effect, parameter),
+        }
+    }
+}
+
+/// Curve from a keyframe to the next one
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Keep the value until the next keyframe
+    Step,
+    Linear,
+    EaseIn,
+    EaseOut,
+    /// Smoothstep
+    EaseInOut,
+}
+
+impl Interpolation {
+    pub const ALL: [Interpolation; 5] = [
+        Interpolation::Step,
+        Interpolation::Linear,
+        Interpolation::EaseIn,
+        Interpolation::EaseOut,
+        Interpolation::EaseInOut,
+    ];
+
+    /// Eased progression for a linear progression `t` in [0, 1]
+    pub fn ease(&self, t: f32) -> f32 {
+        match self {
+            Interpolation::Step => 0.0,
+            Interpolation::Linear => t,
+            Interpolation::EaseIn => t * t,
+            Interpolation::EaseOut => t * (2.0 - t),
+            Interpolation::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+impl Display for Interpolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Interpolation::Step => write!(f, "Step"),
+            Interpolation::Linear => write!(f, "Linear"),
+            Interpolation::EaseIn => write!(f, "Ease in"),
+            Interpolation::EaseOut => write!(f, "Ease out"),
+            Interpolation::EaseInOut => write!(f, "Ease in out"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct Keyframe {
+    pub time: f32,
+    pub value: f32,
+    pub interpolation: Interpolation,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Track {
+    pub parameter: AnimatedParameter,
+    /// Sorted by time
+    pub keyframes: Vec<Keyframe>,
+}
+
+impl Track {
+    pub fn evaluate(&self, time: f32) -> Option<f32> {
+        let first = self.keyframes.first()?;
+        if time <= first.time {
+            return Some(first.value);
+        }
+
+        let next_index = self.keyframes.iter().position(|keyframe| keyframe.time > time).unwrap_or(self.keyframes.len());
+        let previous = &self.keyframes[next_index - 1];
+        let Some(next) = self.keyframes.get(next_index) else {
+            return Some(previous.value);
+        };
+
+        let t = (time - previous.time) / (next.time - previous.time).max(f32::EPSILON);
+        Some(previous.value + (next.value - previous.value) * previous.interpolation.ease(t.clamp(0.0, 1.0)))
+    }
+
+    fn sort(&mut self) { self.keyframes.sort_by(|a, b| a.time.total_cmp(&b.time)); }
+}
+
+/// Unit of the animation time
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TimeBase {
+    Generations,
+    Seconds,
+}
+
+impl Display for TimeBase {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TimeBase::Generations => write!(f, "Generations"),
+            TimeBase::Seconds => write!(f, "Seconds"),
+        }
+    }
+}
+
+/// Saved content of an animation
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AnimationClip {
+    pub time_base: TimeBase,
+    pub duration: f32,
+    pub looped: bool,
+    pub tracks: Vec<Track>,
+}
+
+impl Default for AnimationClip {
+    fn default() -> Self {
+        Self {
+            time_base: TimeBase::Generations,
+            duration: 1000.0,
+            looped: false,
+            tracks: Vec::new(),
+        }
+    }
+}
+
+pub enum AnimationAction {
+    /// Add a keyframe with the current value of the parameter at the current time
+    AddKeyframe(AnimatedParameter),
+    Save,
+    Load,
+}
+
+/// Keyframed parameters played along with the simulation
+pub struct Animation {
+    pub open: bool,
+    pub clip: AnimationClip,
+    pub playing: bool,
+    pub time: f32,
+    /// The animated values must be applied, set when the time or the keyframes change
+    pub need_apply: bool,
+
+    selected_parameter: AnimatedParameter,
+    // (track, keyframe)
+    selected_keyframe: Option<(usize, usize)>,
+    last_tick: Option<Instant>,
+}
+
+impl Animation {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            clip: AnimationClip::default(),
+            playing: false,
+            time: 0.0,
+            need_apply: false,
+            selected_parameter: AnimatedParameter::Zoom,
+            selected_keyframe: None,
+            last_tick: None,
+        }
+    }
+
+    /// To be called for each simulated generation
+    pub fn on_generation(&mut self) {
+        if self.playing && self.clip.time_base == TimeBase::Generations {
+            self.advance(1.0);
+        }
+    }
+
+    /// To be called once per frame, the time only runs while the simulation is not paused
+    pub fn tick(&mut self, paused: bool) {
+        let now = Instant::now();
+        let elapsed: Duration = self.last_tick.map(|last_tick| now - last_tick).unwrap_or_default();
+        self.last_tick = Some(now);
+        if self.playing && !paused && self.clip.time_base == TimeBase::Seconds {
+            self.advance(elapsed.as_secs_f32());
+        }
+    }
+
+    fn advance(&mut self, delta: f32) {
+        self.time += delta;
+        if self.time > self.clip.duration {
+            if self.clip.looped && self.clip.duration > 0.0 {
+                self.time %= self.clip.duration;
+            } else {
+                self.time = self.clip.duration;
+                self.playing = false;
+            }
+        }
+        self.need_apply = true;
+    }
+
+    /// Value of every animated parameter at the current time
+    pub fn values(&self) -> Vec<(AnimatedParameter, f32)> {
+        self.clip
+            .tracks
+            .iter()
+            .filter_map(|track| track.evaluate(self.time).map(|value| (track.parameter, value)))
+            .collect()
+    }
+
+    pub fn add_keyframe(&mut self, parameter: AnimatedParameter, value: f32) {
+        let track_index = match self.clip.tracks.iter().position(|track| track.parameter == parameter) {
+            Some(index) => index,
+            None => {
+                self.clip.tracks.push(Track { parameter, keyframes: Vec::new() });
+                self.clip.tracks.len() - 1
+            },
+        };
+
+        let track = &mut self.clip.tracks[track_index];
+        match track.keyframes.iter_mut().find(|keyframe| keyframe.time == self.time) {
+            Some(keyframe) => keyframe.value = value,
+            None => track.keyframes.push(Keyframe {
+                time: self.time,
+                value,
+                interpolation: Interpolation::Linear,
+            }),
+        }
+        track.sort();
+        self.selected_keyframe = track.keyframes.iter().position(|keyframe| keyframe.time == self.time).map(|index| (track_index, index));
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> { std::fs::write(path, serde_json::to_string_pretty(&self.clip)?) }
+
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> anyhow::Result<()> {
+        fn inner(path: &Path) -> anyhow::Result<AnimationClip> {
+            let string_path: &str = path.to_str().unwrap_or("");
+            let file = File::open(path).with_context(|| format!("Could not open file `{}`", string_path))?;
+            serde_json::from_reader(std::io::BufReader::new(file)).with_context(|| format!("Unable to Parse the file `{}`", string_path))
+        }
+
+        self.clip = inner(path.as_ref())?;
+        self.clip.tracks.iter_mut().for_each(Track::sort);
+        self.selected_keyframe = None;
+        self.time = 0.0;
+        self.need_apply = true;
+        Ok(())
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, post_processing: &PostProcessing) -> Option<AnimationAction> {
+        let mut action: Option<AnimationAction> = None;
+        let mut open = self.open;
+
+        egui::Window::new("Animation").open(&mut open).default_width(480.0).show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if ui.button(if self.playing { "⏸" } else { "▶" }).clicked() {
+                    self.playing = !self.playing;
+                }
+                if ui.button("⏮").on_hover_text("Back to the start").clicked() {
+                    self.time = 0.0;
+                    self.need_apply = true;
+                }
+                self.need_apply |= ui
+                    .add(egui::DragValue::new(&mut self.time).range(0.0..=self.clip.duration).speed(1.0).prefix("time: "))
+                    .changed();
+                ui.checkbox(&mut self.clip.looped, "loop");
+            });
+
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_source("animation_time_base").selected_text(self.clip.time_base.to_string()).show_ui(ui, |ui| {
+                    for time_base in [TimeBase::Generations, TimeBase::Seconds] {
+                        ui.selectable_value(&mut self.clip.time_base, time_base, time_base.to_string());
+                    }
+                });
+                ui.add(egui::DragValue::new(&mut self.clip.duration).range(1.0..=f32::MAX).prefix("duration: "));
+                if ui.button("Save").clicked() {
+                    action = Some(AnimationAction::Save);
+                }
+                if ui.button("Load").clicked() {
+                    action = Some(AnimationAction::Load);
+                }
+            });
+
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_source("animation_parameter").selected_text(self.selected_parameter.label(post_processing)).show_ui(ui, |ui| {
+                    for parameter in AnimatedParameter::all(post_processing) {
+                        ui.selectable_value(&mut self.selected_parameter, parameter, parameter.label(post_processing));
+                    }
+                });
+                if ui.button("Add keyframe").on_hover_text("Current value of the parameter at the current time").clicked() {
+                    action = Some(AnimationAction::AddKeyframe(self.selected_parameter));
+                }
+            });
+
+            ui.separator();
+            self.timeline_ui(ui, post_processing);
+            self.selected_keyframe_ui(ui, post_processing);
+        });
+
+        self.open = open;
+        action
+    }
+
+    // One row per track with its keyframes, clicking sets the time or selects a keyframe
+    fn timeline_ui(&mut self, ui: &mut egui::Ui, post_processing: &PostProcessing) {
+        let duration = self.clip.duration.max(1.0);
+        let rows = self.clip.tracks.len().max(1);
+        let label_width = 110.0;
+
+        let (rect, response) = ui.allocate_exact_size(egui::vec2(ui.available_width(), rows as f32 * TRACK_HEIGHT), egui::Sense::click_and_drag());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+
+        let track_rect = egui::Rect::from_min_max(egui::pos2(rect.left() + label_width, rect.top()), rect.max);
+        let time_to_x = |time: f32| track_rect.left() + track_rect.width() * (time / duration).clamp(0.0, 1.0);
+
+        let mut clicked_keyframe: Option<(usize, usize)> = None;
+        for (track_index, track) in self.clip.tracks.iter().enumerate() {
+            let y = rect.top() + (track_index as f32 + 0.5) * TRACK_HEIGHT;
+            painter.text(
+                egui::pos2(rect.left() + 4.0, y),
+                egui::Align2::LEFT_CENTER,
+                track.parameter.label(post_processing),
+                egui::FontId::proportional(11.0),
+                ui.visuals().text_color(),
+            );
+            for (keyframe_index, keyframe) in track.keyframes.iter().enumerate() {
+                let center = egui::pos2(time_to_x(keyframe.time), y);
+                let selected = self.selected_keyframe == Some((track_index, keyframe_index));
+                let color = if selected { ui.visuals().selection.stroke.color } else { ui.visuals().selection.bg_fill };
+                painter.circle_filled(center, KEYFRAME_RADIUS, color);
+
+                if response.clicked() && response.interact_pointer_pos().is_some_and(|position| position.distance(center) <= KEYFRAME_RADIUS * 1.5) {
+                    clicked_keyframe = Some((track_index, keyframe_index));
+                }
+            }
+        }
+
+        let playhead = time_to_x(self.time);
+        painter.line_segment([egui::pos2(playhead, rect.top()), egui::pos2(playhead, rect.bottom())], egui::Stroke::new(1.5, egui::Color32::LIGHT_RED));
+
+        if let Some(keyframe) = clicked_keyframe {
+            self.selected_keyframe = Some(keyframe);
+            self.time = self.clip.tracks[keyframe.0].keyframes[keyframe.1].time;
+            self.need_apply = true;
+        } else if response.clicked() || response.dragged() {
+            if let Some(position) = response.interact_pointer_pos() {
+                if position.x >= track_rect.left() {
+                    self.time = (position.x - track_rect.left()) / track_rect.width() * duration;
+                    self.time = self.time.clamp(0.0, duration);
+                    self.need_apply = true;
+                }
+            }
+        }
+    }
+
+    fn selected_keyframe_ui(&mut self, ui: &mut egui::Ui, post_processing: &PostProcessing) {
+        let Some((track_index, keyframe_index)) = self.selected_keyframe else {
+            return;
+        };
+        let Some(track) = self.clip.tracks.get_mut(track_index) else {
+            self.selected_keyframe = None;
+            return;
+        };
+        if keyframe_index >= track.keyframes.len() {
+            self.selected_keyframe = None;
+            return;
+        }
+
+        let mut changed: bool = false;
+        let mut removed: bool = false;
+        ui.horizontal(|ui| {
+            ui.label(track.parameter.label(post_processing));
+            let keyframe = &mut track.keyframes[keyframe_index];
+            changed |= ui.add(egui::DragValue::new(&mut keyframe.time).range(0.0..=f32::MAX).prefix("time: ")).changed();
+            changed |= ui.add(egui::DragValue::new(&mut keyframe.value).speed(0.01).prefix("value: ")).changed();
+            egui::ComboBox::from_id_source("keyframe_interpolation").selected_text(keyframe.interpolation.to_string()).show_ui(ui, |ui| {
+                for interpolation in Interpolation::ALL {
+                    changed |= ui.selectable_value(&mut keyframe.interpolation, interpolation, interpolation.to_string()).changed();
+                }
+            });
+            removed = ui.button("Remove").clicked();
+        });
+
+        if removed {
+            track.keyframes.remove(keyframe_index);
+            if track.keyframes.is_empty() {
+                self.clip.tracks.remove(track_index);
+            }
+            self.selected_keyframe = None;
+            self.need_apply = true;
+        } else if changed {
+            let time = track.keyframes[keyframe_index].time;
+            track.sort();
+            self.selected_keyframe = track.keyframes.iter().position(|keyframe| keyframe.time == time).map(|index| (track_index, index));
+            self.need_apply = true;
+        }
+    }
+}
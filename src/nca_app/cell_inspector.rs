@@ -0,0 +1,199 @@
+use glam::Vec2;
+
+use oxyde::egui as egui;
+use oxyde::wgpu as wgpu;
+
+use nca_core::{
+    cell_probe::{CellProbe, CellRegion},
+    cpu_activation::CpuActivation,
+    simulation_data::KERNEL_SIZE,
+    BoundaryCondition,
+    Lattice,
+    ViewParameters,
+};
+
+use super::{simulation_instance::SimulationInstance, Viewport};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct InspectedCell {
+    instance: usize,
+    x: i32,
+    y: i32,
+}
+
+// Values read back for the inspected cell
+struct CellReadings {
+    cell: InspectedCell,
+    /// Neighbourhood of the cell in the previous generation, the input of the last simulation step
+    previous: CellRegion,
+    /// Value written by the last simulation step
+    current: [f32; 4],
+}
+
+/// Tool window showing the cell under the mouse (or the clicked one) with the kernel sum and the activation recomputed on the CPU,
+/// to check what simulationBase.wgsl computes
+pub struct CellInspector {
+    pub open: bool,
+    hovered: Option<InspectedCell>,
+    pinned: Option<InspectedCell>,
+    readings: Option<CellReadings>,
+    // Parsed activation code, kept while the code does not change
+    activation: Option<(String, Result<CpuActivation, String>)>,
+}
+
+impl CellInspector {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            hovered: None,
+            pinned: None,
+            readings: None,
+            activation: None,
+        }
+    }
+
+    fn inspected_cell(&self) -> Option<InspectedCell> { self.pinned.or(self.hovered) }
+
+    /// Follow the mouse over the simulation viewports, a click pins the hovered cell
+    pub fn track_pointer(&mut self, ctx: &egui::Context, viewports: &[Viewport], view_parameters: &ViewParameters, lattice: Lattice, simulation_size: [u32; 2]) {
+        self.hovered = None;
+        if !self.open || ctx.is_pointer_over_area() {
+            return;
+        }
+        let Some(pointer) = ctx.pointer_hover_pos() else {
+            return;
+        };
+        let pointer = Vec2::new(pointer.x, pointer.y);
+        let Some((instance, viewport)) = viewports.iter().enumerate().find(|(_, viewport)| viewport.contains(pointer)) else {
+            return;
+        };
+
+        let size = Vec2::new(simulation_size[0] as f32, simulation_size[1] as f32);
+        let position = (pointer - Vec2::new(viewport.x, viewport.y)) / Vec2::new(viewport.width, viewport.height);
+        let [mut x, mut y] = lattice.cell_at(view_parameters.texture_uv(position) * size);
+        if view_parameters.tile != 0 {
+            x = x.rem_euclid(simulation_size[0] as i32);
+            y = y.rem_euclid(simulation_size[1] as i32);
+        } else if x < 0 || y < 0 || x >= simulation_size[0] as i32 || y >= simulation_size[1] as i32 {
+            // The display clamps the texture instead of wrapping it
+            return;
+        }
+
+        self.hovered = Some(InspectedCell { instance, x, y });
+        if ctx.input(|input| input.pointer.primary_clicked()) {
+            self.pinned = self.hovered;
+        }
+    }
+
+    pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, cell_probe: &CellProbe, instances: &[SimulationInstance]) {
+        self.readings = None;
+        if !self.open {
+            return;
+        }
+        let Some(cell) = self.inspected_cell().filter(|cell| cell.instance < instances.len()) else {
+            return;
+        };
+
+        let simulation = &instances[cell.instance].simulation;
+        let previous = cell_probe.read(device, queue, simulation.previous_view(), [cell.x - 1, cell.y - 1], [KERNEL_SIZE as u32; 2]);
+        let current = cell_probe.read(device, queue, simulation.current_view(), [cell.x, cell.y], [1, 1]).cells[0];
+        self.readings = Some(CellReadings { cell, previous, current });
+    }
+
+    fn activation(&mut self, activation_code: &str) -> &Result<CpuActivation, String> {
+        if !matches!(&self.activation, Some((code, _)) if code == activation_code) {
+            let activation = CpuActivation::parse(activation_code).map_err(|error| format!("{:#}", error));
+            self.activation = Some((activation_code.to_owned(), activation));
+        }
+        &self.activation.as_ref().expect("The activation has just been parsed").1
+    }
+
+    // Value of the neighbour read by the shader, with the boundary condition applied
+    fn neighbour(readings: &CellReadings, boundary_condition: BoundaryCondition, offset: [i32; 2], simulation_size: [u32; 2]) -> [f32; 4] {
+        let coords = [readings.cell.x + offset[0], readings.cell.y + offset[1]];
+        match boundary_condition.resolve(coords, simulation_size) {
+            Some([x, y]) => readings.previous.cell_wrapped(x, y, simulation_size).unwrap_or_default(),
+            None => {
+                let value = boundary_condition.constant_value();
+                [value, value, value, 1.0]
+            },
+        }
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, instances: &[SimulationInstance], lattice: Lattice, simulation_size: [u32; 2]) {
+        let mut open = self.open;
+        let readings = self.readings.take();
+
+        egui::Window::new("Cell inspector").open(&mut open).default_width(360.0).show(ctx, |ui| {
+            let Some(readings) = readings.as_ref().filter(|readings| readings.cell.instance < instances.len()) else {
+                ui.label("Hover a cell of the simulation, click it to pin it.");
+                return;
+            };
+            let instance = &instances[readings.cell.instance];
+
+            ui.horizontal(|ui| {
+                ui.label(format!("Cell ({}, {}) of {}", readings.cell.x, readings.cell.y, instance.name));
+                if self.pinned.is_some() && ui.button("Unpin").clicked() {
+                    self.pinned = None;
+                }
+            });
+            ui.label(format!("Value: {}", format_rgba(readings.current)));
+
+            ui.separator();
+            ui.label("Neighbourhood in the previous generation (value × weight):");
+            let uniform = &instance.simulation.simulation_data.uniform;
+            let mut sum = [0.0_f32; 4];
+            egui::Grid::new("cell_inspector_neighbourhood").striped(true).show(ui, |ui| {
+                for row in 0..KERNEL_SIZE {
+                    for col in 0..KERNEL_SIZE {
+                        let Some(offset) = lattice.neighbour_offset(col, row, readings.cell.x, readings.cell.y) else {
+                            ui.label("");
+                            continue;
+                        };
+                        let value = Self::neighbour(readings, instance.boundary_condition, offset, simulation_size);
+                        let weight = uniform.get_kernel_at(col, row);
+                        for (channel_sum, channel) in sum.iter_mut().zip(value) {
+                            *channel_sum += channel * weight;
+                        }
+                        ui.monospace(format!("{:.3} × {:.3}", value[0], weight)).on_hover_text(format_rgba(value));
+                    }
+                    ui.end_row();
+                }
+            });
+            ui.label(format!("Kernel sum: {}", format_rgba(sum)));
+
+            ui.separator();
+            match self.activation(&instance.activation_code) {
+                Err(error) => {
+                    ui.colored_label(egui::Color32::RED, format!("Unable to interpret the activation code: {}", error));
+                },
+                Ok(activation) => match activation.evaluate(sum) {
+                    Err(error) => {
+                        ui.colored_label(egui::Color32::RED, format!("Unable to evaluate the activation: {:#}", error));
+                    },
+                    Ok(output) => {
+                        ui.label(format!("CPU activation: {}", format_rgba(output)));
+                        // The simulation textures store 8 bits per channel
+                        let stored = output.map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() / 255.0);
+                        ui.label(format!("Stored: {}", format_rgba(stored)));
+                        let difference = stored.iter().zip(readings.current).map(|(cpu, gpu)| (cpu - gpu).abs()).fold(0.0, f32::max);
+                        if difference > 0.5 / 255.0 {
+                            ui.colored_label(egui::Color32::YELLOW, format!("The GPU value differs by {:.4}", difference))
+                                .on_hover_text("The kernel or the activation may have changed since the last step");
+                        } else {
+                            ui.label("Matches the GPU value");
+                        }
+                    },
+                },
+            }
+        });
+
+        self.readings = readings;
+        self.open = open;
+        if !self.open {
+            self.pinned = None;
+        }
+    }
+}
+
+fn format_rgba(value: [f32; 4]) -> String { format!("({:.4}, {:.4}, {:.4}, {:.4})", value[0], value[1], value[2], value[3]) }
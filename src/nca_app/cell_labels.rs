@@ -0,0 +1,116 @@
+use glam::Vec2;
+
+use oxyde::egui as egui;
+use oxyde::wgpu as wgpu;
+
+use nca_core::{
+    cell_probe::{CellProbe, CellRegion, MAX_PROBED_CELLS},
+    Lattice,
+    ViewParameters,
+};
+
+use super::{simulation_instance::SimulationInstance, Viewport};
+
+/// Values of the displayed channel written over the cells once they are large enough on screen
+pub struct CellLabels {
+    pub enabled: bool,
+    /// Minimum displayed cell width, in points
+    pub min_cell_size: f32,
+    // Visible cells of each simulation instance, read back at each update
+    regions: Vec<Option<CellRegion>>,
+}
+
+impl CellLabels {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            min_cell_size: 48.0,
+            regions: Vec::new(),
+        }
+    }
+
+    // In points, the viewport size of the view parameters is in physical pixels
+    fn cell_size(view_parameters: &ViewParameters, viewport: &Viewport) -> f32 {
+        (Vec2::new(viewport.width, viewport.height) / (view_parameters.viewport_size * view_parameters.texel_scale())).min_element()
+    }
+
+    // Texels covering the viewport (with one more column for the shifted hexagonal rows), None when the cells are too small to be labelled
+    fn visible_cells(&self, view_parameters: &ViewParameters, viewport: &Viewport, simulation_size: [u32; 2]) -> Option<([i32; 2], [u32; 2])> {
+        if !self.enabled || Self::cell_size(view_parameters, viewport) < self.min_cell_size {
+            return None;
+        }
+
+        let size = Vec2::new(simulation_size[0] as f32, simulation_size[1] as f32);
+        // The first texture row is displayed at the bottom of the viewport
+        let top_left = view_parameters.texture_uv(Vec2::ZERO) * size;
+        let bottom_right = view_parameters.texture_uv(Vec2::ONE) * size;
+        let min = Vec2::new(top_left.x - 1.0, bottom_right.y).floor();
+        let max = Vec2::new(bottom_right.x, top_left.y).ceil();
+        let region_size = [(max.x - min.x) as u32, (max.y - min.y) as u32];
+
+        (region_size[0] * region_size[1] <= MAX_PROBED_CELLS).then_some(([min.x as i32, min.y as i32], region_size))
+    }
+
+    pub fn update(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        cell_probe: &CellProbe,
+        instances: &[SimulationInstance],
+        viewports: &[Viewport],
+        view_parameters: &ViewParameters,
+        simulation_size: [u32; 2],
+    ) {
+        let regions: Vec<Option<CellRegion>> = instances
+            .iter()
+            .zip(viewports)
+            .map(|(instance, viewport)| {
+                self.visible_cells(view_parameters, viewport, simulation_size).map(|(origin, size)| {
+                    cell_probe.read(device, queue, instance.simulation.current_view(), origin, size)
+                })
+            })
+            .collect();
+        self.regions = regions;
+    }
+
+    /// Draw the labels behind the ui panels, at the displayed center of each cell
+    pub fn paint(&self, ctx: &egui::Context, viewports: &[Viewport], view_parameters: &ViewParameters, lattice: Lattice, simulation_size: [u32; 2]) {
+        let size = Vec2::new(simulation_size[0] as f32, simulation_size[1] as f32);
+
+        for (region, viewport) in self.regions.iter().zip(viewports) {
+            let Some(region) = region else {
+                continue;
+            };
+
+            let viewport_rect = egui::Rect::from_min_size(egui::pos2(viewport.x, viewport.y), egui::vec2(viewport.width, viewport.height));
+            let painter = ctx.layer_painter(egui::LayerId::background()).with_clip_rect(viewport_rect);
+            let font = egui::FontId::monospace((Self::cell_size(view_parameters, viewport) / 4.0).min(24.0));
+
+            for row in 0..region.size[1] as i32 {
+                for column in 0..region.size[0] as i32 {
+                    let (x, y) = (region.origin[0] + column, region.origin[1] + row);
+                    // The display clamps the texture instead of wrapping it, unless tiled (the probe wraps the coordinates)
+                    if view_parameters.tile == 0 && (x < 0 || y < 0 || x >= simulation_size[0] as i32 || y >= simulation_size[1] as i32) {
+                        continue;
+                    }
+
+                    let value = region.cells[(row * region.size[0] as i32 + column) as usize][0];
+                    let position = view_parameters.viewport_position(lattice.cell_center(x, y) / size);
+
+                    // Readable over the color of the cell
+                    let cell_color = view_parameters.color(value);
+                    let luminance = 0.2126 * cell_color.x + 0.7152 * cell_color.y + 0.0722 * cell_color.z;
+                    let text_color = if luminance > 0.5 { egui::Color32::BLACK } else { egui::Color32::WHITE };
+
+                    painter.text(
+                        egui::pos2(viewport.x + position.x * viewport.width, viewport.y + position.y * viewport.height),
+                        egui::Align2::CENTER_CENTER,
+                        format!("{:.3}", value),
+                        font.clone(),
+                        text_color,
+                    );
+                }
+            }
+        }
+    }
+}
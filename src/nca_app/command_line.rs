@@ -0,0 +1,70 @@
+use anyhow::Context;
+use std::path::PathBuf;
+
+use nca_core::{kernel_symmetry::KernelSymmetryMode, Lattice};
+
+const USAGE: &str = "Usage: rust_nca [--preset <preset.json>] [--kernel <kernel file or kernel text>] [--timeline <timeline.json>] \
+[--explore <runs> [--explore-output <directory>] [--explore-activation <activation preset or random>] [--lattice <lattice>] [--symmetry <symmetry>]]";
+
+#[derive(Debug)]
+pub struct CommandLineOptions {
+    pub preset_path: Option<PathBuf>,
+    /// Either a path to a kernel file or the kernel itself in one of the supported text formats
+    pub kernel: Option<String>,
+    /// Scheduled parameter changes started with the simulation
+    pub timeline_path: Option<PathBuf>,
+    /// Number of runs of a headless exploration, the application is not started when set
+    pub explore: Option<usize>,
+    pub explore_output: PathBuf,
+    /// Name of the activation preset of the explored rules, `random` to draw one for each run
+    pub explore_activation: Option<String>,
+    pub explore_lattice: Lattice,
+    pub explore_symmetry: KernelSymmetryMode,
+}
+
+impl Default for CommandLineOptions {
+    fn default() -> Self {
+        Self {
+            preset_path: None,
+            kernel: None,
+            timeline_path: None,
+            explore: None,
+            explore_output: PathBuf::from("exploration"),
+            explore_activation: None,
+            explore_lattice: Lattice::Square,
+            explore_symmetry: KernelSymmetryMode::Any,
+        }
+    }
+}
+
+impl CommandLineOptions {
+    pub fn from_env() -> anyhow::Result<Self> { Self::parse(std::env::args().skip(1)) }
+
+    pub fn parse<I: Iterator<Item = String>>(mut args: I) -> anyhow::Result<Self> {
+        let mut options = CommandLineOptions::default();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--preset" => options.preset_path = Some(args.next().context(USAGE)?.into()),
+                "--kernel" => options.kernel = Some(args.next().context(USAGE)?),
+                "--timeline" => options.timeline_path = Some(args.next().context(USAGE)?.into()),
+                "--explore" => options.explore = Some(args.next().context(USAGE)?.parse().context(USAGE)?),
+                "--explore-output" => options.explore_output = args.next().context(USAGE)?.into(),
+                "--explore-activation" => options.explore_activation = Some(args.next().context(USAGE)?),
+                "--lattice" => options.explore_lattice = parse_variant(&Lattice::ALL, &args.next().context(USAGE)?)?,
+                "--symmetry" => options.explore_symmetry = parse_variant(&KernelSymmetryMode::ALL, &args.next().context(USAGE)?)?,
+                _ => anyhow::bail!("Unknown argument `{}`.\n{}", arg, USAGE),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+// Named as in the preset files, ignoring the case
+fn parse_variant<T: Copy + std::fmt::Debug>(variants: &[T], name: &str) -> anyhow::Result<T> {
+    variants.iter().copied().find(|variant| format!("{:?}", variant).eq_ignore_ascii_case(name)).with_context(|| {
+        let names: Vec<String> = variants.iter().map(|variant| format!("{:?}", variant)).collect();
+        format!("Unknown value `{}`, expected one of {}.\n{}", name, names.join(", "), USAGE)
+    })
+}
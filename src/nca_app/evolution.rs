@@ -0,0 +1,627 @@
+use anyhow::Context;
+use rand::{seq::IndexedRandom, Rng};
+use rand_distr::{Distribution, Normal};
+use serde::{Deserialize, Serialize};
+use std::{fs::File, path::Path};
+
+use oxyde::egui as egui;
+use oxyde::wgpu as wgpu;
+
+use egui_widgets::IqGradient;
+
+use nca_core::{
+    kernel_symmetry::KernelSymmetryMode,
+    preset::{KernelLayout, Preset},
+    simulation_data::KERNEL_SIZE,
+    BoundaryCondition,
+    ColorMap,
+    DisplayFramesMode,
+    InitSimulationUniforms,
+    Lattice,
+    PostProcessing,
+    Simulation,
+    StateReadback,
+    StateSnapshot,
+    ViewParameters,
+    DEFAULT_DISPLAY_CODE,
+};
+
+use super::explorer::snapshot_to_color_image;
+
+const THUMBNAIL_SIZE: f32 = 128.0;
+const GRID_COLUMNS: usize = 3;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Genome {
+    pub kernel: [f32; 9],
+    pub activation_code: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Individual {
+    pub id: usize,
+    pub generation: usize,
+    pub parents: Vec<usize>,
+    pub genome: Genome,
+}
+
+/// Every individual created during an evolution, to be able to go back to any of them
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Lineage {
+    #[serde(default)]
+    pub lattice: Lattice,
+    pub kernel_symmetry_mode: KernelSymmetryMode,
+    pub individuals: Vec<Individual>,
+}
+
+impl Lineage {
+    pub fn to_preset(&self, individual: &Individual, gradient: IqGradient, color_map: ColorMap, display_code: String, post_processing: PostProcessing) -> Preset {
+        Preset {
+            kernel: individual.genome.kernel,
+            kernel_layout: KernelLayout::RowMajor,
+            kernel_symmetry_mode: self.kernel_symmetry_mode,
+            activation_code: individual.genome.activation_code.clone(),
+            display_frames_mode: DisplayFramesMode::All,
+            gradient,
+            color_map,
+            display_code,
+            post_processing,
+            kernel_randomizer: None,
+            boundary_condition: BoundaryCondition::Wrap,
+            lattice: self.lattice,
+        }
+    }
+}
+
+pub struct EvolutionSettings {
+    pub population: usize,
+    pub simulation_size: u32,
+    pub steps_per_frame: u32,
+    /// The small simulations are re-initialised after this number of generations
+    pub restart_after: u32,
+    /// Number of generations between two refreshes of the thumbnails
+    pub thumbnail_interval: u32,
+    /// Probability for each gene to mutate
+    pub mutation_rate: f32,
+    /// Standard deviation of the (relative for activation constants) mutations
+    pub mutation_strength: f32,
+    /// Also mutate the numeric constants of the activation code
+    pub mutate_activation: bool,
+}
+
+impl Default for EvolutionSettings {
+    fn default() -> Self {
+        Self {
+            population: 9,
+            simulation_size: 128,
+            steps_per_frame: 1,
+            restart_after: 600,
+            thumbnail_interval: 10,
+            mutation_rate: 0.3,
+            mutation_strength: 0.2,
+            mutate_activation: true,
+        }
+    }
+}
+
+pub enum EvolutionAction {
+    Restart,
+    Open(Preset),
+    SaveLineage,
+    LoadLineage,
+}
+
+struct Member {
+    individual_id: usize,
+    simulation: Option<Simulation>,
+    error: Option<String>,
+    state: Option<StateSnapshot>,
+    // The thumbnails are read back without waiting for the GPU
+    readback: Option<StateReadback>,
+    // Generation of the simulation when the last readback was requested
+    readback_generation: u32,
+    thumbnail: Option<egui::TextureHandle>,
+    thumbnail_dirty: bool,
+    selected: bool,
+}
+
+impl Member {
+    fn new(individual_id: usize) -> Self {
+        Self {
+            individual_id,
+            simulation: None,
+            error: None,
+            state: None,
+            readback: None,
+            readback_generation: 0,
+            thumbnail: None,
+            thumbnail_dirty: false,
+            selected: false,
+        }
+    }
+}
+
+/// Interactive evolution: the user picks the parents of the next generation among a grid of small simulations
+pub struct Evolution {
+    pub open: bool,
+    pub settings: EvolutionSettings,
+    lineage: Lineage,
+    population: Vec<Member>,
+    generation: usize,
+    init_seed: f32,
+}
+
+impl Evolution {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            settings: EvolutionSettings::default(),
+            lineage: Lineage {
+                lattice: Lattice::Square,
+                kernel_symmetry_mode: KernelSymmetryMode::Any,
+                individuals: Vec::new(),
+            },
+            population: Vec::new(),
+            generation: 0,
+            init_seed: 0.0,
+        }
+    }
+
+    pub fn is_active(&self) -> bool { self.open && !self.population.is_empty() }
+
+    /// Start a new lineage from a single ancestor, the first generation being made of its mutations
+    pub fn restart(&mut self, ancestor: Genome, lattice: Lattice, kernel_symmetry_mode: KernelSymmetryMode, init_seed: f32) {
+        self.lineage = Lineage {
+            lattice,
+            kernel_symmetry_mode,
+            individuals: vec![Individual {
+                id: 0,
+                generation: 0,
+                parents: Vec::new(),
+                genome: ancestor,
+            }],
+        };
+        self.generation = 0;
+        self.init_seed = init_seed;
+        self.population = vec![Member::new(0)];
+        self.population[0].selected = true;
+        self.next_generation();
+    }
+
+    fn individual(&self, id: usize) -> &Individual { &self.lineage.individuals[id] }
+
+    pub fn selected_count(&self) -> usize { self.population.iter().filter(|member| member.selected).count() }
+
+    /// Replace the population by children of the selected members. The first selected one is kept as is.
+    pub fn next_generation(&mut self) {
+        let parents: Vec<usize> = self.population.iter().filter(|member| member.selected).map(|member| member.individual_id).collect();
+        let Some(&elite) = parents.first() else {
+            return;
+        };
+
+        let mut rng = rand::rng();
+        self.generation += 1;
+
+        let mut population = vec![Member::new(elite)];
+        while population.len() < self.settings.population {
+            let first_parent = *parents.choose(&mut rng).expect("At least one parent");
+            let second_parent = *parents.choose(&mut rng).expect("At least one parent");
+
+            let (first, second) = (&self.individual(first_parent).genome, &self.individual(second_parent).genome);
+            let mut genome = crossover(first, second, self.lineage.lattice, self.lineage.kernel_symmetry_mode, &mut rng);
+            mutate(&mut genome, &self.settings, self.lineage.lattice, self.lineage.kernel_symmetry_mode, &mut rng);
+
+            let id = self.lineage.individuals.len();
+            let mut individual_parents = vec![first_parent];
+            if second_parent != first_parent {
+                individual_parents.push(second_parent);
+            }
+            self.lineage.individuals.push(Individual {
+                id,
+                generation: self.generation,
+                parents: individual_parents,
+                genome,
+            });
+            population.push(Member::new(id));
+        }
+
+        self.population = population;
+    }
+
+    /// Step the simulations of the population. A single simulation is compiled per frame, and the thumbnails are
+    /// refreshed every `thumbnail_interval` generations without blocking on the GPU.
+    pub fn advance(&mut self, device: &mut wgpu::Device, queue: &wgpu::Queue) {
+        let size = [self.settings.simulation_size; 2];
+        let mut compiled = false;
+        for member in self.population.iter_mut() {
+            if member.error.is_some() {
+                continue;
+            }
+
+            if member.simulation.is_none() {
+                if compiled {
+                    continue;
+                }
+                compiled = true;
+
+                let preset = self.lineage.to_preset(&self.lineage.individuals[member.individual_id], IqGradient::default(), ColorMap::default(), DEFAULT_DISPLAY_CODE.to_owned(), PostProcessing::default());
+                match Simulation::new(device, queue, size, &preset) {
+                    Ok(mut simulation) => {
+                        simulation.init(InitSimulationUniforms::from_seed(self.init_seed));
+                        member.simulation = Some(simulation);
+                    },
+                    Err(error) => {
+                        member.error = Some(error.to_string());
+                        continue;
+                    },
+                }
+            }
+            let simulation = member.simulation.as_mut().expect("Created above");
+
+            if simulation.generation >= self.settings.restart_after {
+                simulation.init(InitSimulationUniforms::from_seed(self.init_seed));
+            }
+            simulation.step(self.settings.steps_per_frame.max(1));
+
+            // After a restart the generation goes back below the one of the last readback
+            let refresh = member.state.is_none() || simulation.generation.abs_diff(member.readback_generation) >= self.settings.thumbnail_interval.max(1);
+            if member.readback.is_none() && refresh {
+                member.readback = Some(simulation.request_state());
+                member.readback_generation = simulation.generation;
+            }
+        }
+
+        if let Err(error) = device.poll(wgpu::PollType::Poll) {
+            println!("Unable to poll the device.\n {:?}", error);
+        }
+
+        for member in self.population.iter_mut() {
+            let Some(result) = member.readback.as_ref().and_then(StateReadback::try_receive) else {
+                continue;
+            };
+            member.readback = None;
+            match result {
+                Ok(state) => {
+                    member.state = Some(state);
+                    member.thumbnail_dirty = true;
+                },
+                Err(error) => println!("Unable to read back the evolution member #{}.\n {:?}", member.individual_id, error),
+            }
+        }
+    }
+
+    pub fn save_lineage<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> { std::fs::write(path, serde_json::to_string_pretty(&self.lineage)?) }
+
+    pub fn load_lineage<P: AsRef<Path>>(&mut self, path: P) -> anyhow::Result<()> {
+        fn inner(path: &Path) -> anyhow::Result<Lineage> {
+            let string_path: &str = path.to_str().unwrap_or("");
+            let file = File::open(path).with_context(|| format!("Could not open file `{}`", string_path))?;
+            serde_json::from_reader(std::io::BufReader::new(file)).with_context(|| format!("Unable to Parse the file `{}`", string_path))
+        }
+
+        let lineage = inner(path.as_ref())?;
+        let last_generation = lineage.individuals.iter().map(|individual| individual.generation).max().context("The lineage is empty")?;
+
+        self.population = lineage
+            .individuals
+            .iter()
+            .filter(|individual| individual.generation == last_generation)
+            .map(|individual| Member::new(individual.id))
+            .collect();
+        self.generation = last_generation;
+        self.lineage = lineage;
+
+        Ok(())
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, view_parameters: &ViewParameters, color_map: &ColorMap, display_code: &str, post_processing: &PostProcessing) -> Option<EvolutionAction> {
+        let mut action: Option<EvolutionAction> = None;
+        let mut open = self.open;
+
+        egui::Window::new("Evolution").open(&mut open).default_width(460.0).show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("Restart from current").clicked() {
+                    action = Some(EvolutionAction::Restart);
+                }
+                if ui.button("Save lineage").clicked() {
+                    action = Some(EvolutionAction::SaveLineage);
+                }
+                if ui.button("Load lineage").clicked() {
+                    action = Some(EvolutionAction::LoadLineage);
+                }
+            });
+
+            egui::CollapsingHeader::new("Settings").show(ui, |ui| {
+                egui::Grid::new("evolution settings").show(ui, |ui| {
+                    ui.add(egui::DragValue::new(&mut self.settings.population).range(1..=36).prefix("population: "));
+                    ui.add(egui::DragValue::new(&mut self.settings.simulation_size).range(16..=512).prefix("size: "));
+                    ui.end_row();
+                    ui.add(egui::DragValue::new(&mut self.settings.mutation_rate).speed(0.01).range(0.0..=1.0).prefix("mutation rate: "));
+                    ui.add(egui::DragValue::new(&mut self.settings.mutation_strength).speed(0.01).range(0.0..=10.0).prefix("strength: "));
+                    ui.end_row();
+                    ui.add(egui::DragValue::new(&mut self.settings.steps_per_frame).range(1..=100).prefix("generations per frame: "));
+                    ui.add(egui::DragValue::new(&mut self.settings.restart_after).range(1..=100000).prefix("restart after: "));
+                    ui.end_row();
+                    ui.add(egui::DragValue::new(&mut self.settings.thumbnail_interval).range(1..=1000).prefix("thumbnail every: ").suffix(" generations"));
+                    ui.end_row();
+                });
+                ui.checkbox(&mut self.settings.mutate_activation, "mutate activation constants");
+            });
+
+            if self.population.is_empty() {
+                ui.label("Restart from the current preset to create a first generation.");
+                return;
+            }
+
+            ui.horizontal(|ui| {
+                ui.label(format!("Generation {}", self.generation));
+                let selected_count = self.selected_count();
+                if ui.add_enabled(selected_count > 0, egui::Button::new("Next generation")).on_hover_text("Children of the selected individuals").clicked() {
+                    self.next_generation();
+                }
+            });
+            ui.label("Click on the simulations to select them as parents.");
+
+            egui::Grid::new("evolution population").show(ui, |ui| {
+                for (index, member) in self.population.iter_mut().enumerate() {
+                    ui.vertical(|ui| {
+                        if let Some(error) = &member.error {
+                            ui.add_sized([THUMBNAIL_SIZE, THUMBNAIL_SIZE], egui::Label::new("Compilation failed")).on_hover_text(error);
+                        } else if let Some(state) = &member.state {
+                            // The image is only converted when a new state has been read back
+                            let texture = match &mut member.thumbnail {
+                                Some(texture) => {
+                                    if member.thumbnail_dirty {
+                                        texture.set(snapshot_to_color_image(state, view_parameters), egui::TextureOptions::NEAREST);
+                                    }
+                                    texture
+                                },
+                                None => member.thumbnail.insert(ctx.load_texture(format!("evolution member {}", index), snapshot_to_color_image(state, view_parameters), egui::TextureOptions::NEAREST)),
+                            };
+                            member.thumbnail_dirty = false;
+
+                            let response = ui.add(egui::Button::image((texture.id(), egui::vec2(THUMBNAIL_SIZE, THUMBNAIL_SIZE))).selected(member.selected));
+                            if response.clicked() {
+                                member.selected = !member.selected;
+                            }
+                        } else {
+                            ui.add_sized([THUMBNAIL_SIZE, THUMBNAIL_SIZE], egui::Spinner::new());
+                        }
+
+                        ui.horizontal(|ui| {
+                            ui.label(format!("#{}", member.individual_id));
+                            if ui.small_button("Open").clicked() {
+                                let individual = &self.lineage.individuals[member.individual_id];
+                                action = Some(EvolutionAction::Open(self.lineage.to_preset(individual, view_parameters.gradient, color_map.clone(), display_code.to_owned(), post_processing.clone())));
+                            }
+                        });
+                    });
+                    if (index + 1) % GRID_COLUMNS == 0 {
+                        ui.end_row();
+                    }
+                }
+            });
+
+            egui::CollapsingHeader::new("Lineage").show(ui, |ui| {
+                egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                    for individual in self.lineage.individuals.iter().rev() {
+                        ui.horizontal(|ui| {
+                            ui.label(format!("#{} (generation {}, parents {:?})", individual.id, individual.generation, individual.parents));
+                            if ui.small_button("Open").clicked() {
+                                action = Some(EvolutionAction::Open(self.lineage.to_preset(individual, view_parameters.gradient, color_map.clone(), display_code.to_owned(), post_processing.clone())));
+                            }
+                        });
+                    }
+                });
+            });
+        });
+
+        self.open = open;
+        action
+    }
+}
+
+/// Uniform crossover over the kernel symmetry orbits. Activation constants are mixed when both parents share the same code structure,
+/// otherwise the code of one of the parents is taken.
+fn crossover<R: Rng>(first: &Genome, second: &Genome, lattice: Lattice, kernel_symmetry_mode: KernelSymmetryMode, rng: &mut R) -> Genome {
+    let mut kernel = first.kernel;
+    for orbit in lattice.orbits(kernel_symmetry_mode) {
+        if rng.random_bool(0.5) {
+            for (col, row) in orbit {
+                kernel[row * KERNEL_SIZE + col] = second.kernel[row * KERNEL_SIZE + col];
+            }
+        }
+    }
+
+    let first_literals = float_literals(&first.activation_code);
+    let second_literals = float_literals(&second.activation_code);
+    let activation_code = if strip_literals(&first.activation_code, &first_literals) == strip_literals(&second.activation_code, &second_literals) {
+        let values: Vec<f32> = first_literals
+            .iter()
+            .zip(second_literals.iter())
+            .map(|(a, b)| if rng.random_bool(0.5) { a.value } else { b.value })
+            .collect();
+        replace_literals(&first.activation_code, &first_literals, &values)
+    } else if rng.random_bool(0.5) {
+        first.activation_code.clone()
+    } else {
+        second.activation_code.clone()
+    };
+
+    Genome { kernel, activation_code }
+}
+
+fn mutate<R: Rng>(genome: &mut Genome, settings: &EvolutionSettings, lattice: Lattice, kernel_symmetry_mode: KernelSymmetryMode, rng: &mut R) {
+    let rate = settings.mutation_rate.clamp(0.0, 1.0) as f64;
+    let Ok(noise) = Normal::new(0.0, settings.mutation_strength) else {
+        return;
+    };
+
+    for orbit in lattice.orbits(kernel_symmetry_mode) {
+        if rng.random_bool(rate) {
+            let delta = noise.sample(rng);
+            for (col, row) in orbit {
+                genome.kernel[row * KERNEL_SIZE + col] += delta;
+            }
+        }
+    }
+    lattice.apply_symmetry(&mut genome.kernel, kernel_symmetry_mode);
+
+    if settings.mutate_activation {
+        let literals = float_literals(&genome.activation_code);
+        let values: Vec<f32> = literals
+            .iter()
+            .map(|literal| if rng.random_bool(rate) { literal.value * (1.0 + noise.sample(rng)) } else { literal.value })
+            .collect();
+        genome.activation_code = replace_literals(&genome.activation_code, &literals, &values);
+    }
+}
+
+struct FloatLiteral {
+    range: std::ops::Range<usize>,
+    value: f32,
+}
+
+// Float literals (containing a dot or an exponent) of a wgsl code, outside of identifiers
+fn float_literals(code: &str) -> Vec<FloatLiteral> {
+    let bytes = code.as_bytes();
+    let is_identifier = |byte: u8| byte.is_ascii_alphanumeric() || byte == b'_';
+
+    let mut literals = Vec::new();
+    let mut index = 0;
+    while index < bytes.len() {
+        let starts_number = bytes[index].is_ascii_digit() || (bytes[index] == b'.' && bytes.get(index + 1).is_some_and(u8::is_ascii_digit));
+        if !starts_number || (index > 0 && is_identifier(bytes[index - 1])) {
+            index += 1;
+            continue;
+        }
+
+        let start = index;
+        while index < bytes.len() && (bytes[index].is_ascii_digit() || bytes[index] == b'.') {
+            index += 1;
+        }
+        let mut has_exponent = false;
+        if index < bytes.len() && (bytes[index] == b'e' || bytes[index] == b'E') {
+            let mut exponent_end = index + 1;
+            if exponent_end < bytes.len() && (bytes[exponent_end] == b'+' || bytes[exponent_end] == b'-') {
+                exponent_end += 1;
+            }
+            if exponent_end < bytes.len() && bytes[exponent_end].is_ascii_digit() {
+                has_exponent = true;
+                index = exponent_end;
+                while index < bytes.len() && bytes[index].is_ascii_digit() {
+                    index += 1;
+                }
+            }
+        }
+
+        let text = &code[start..index];
+        let followed_by_identifier = index < bytes.len() && is_identifier(bytes[index]);
+        if (text.contains('.') || has_exponent) && !followed_by_identifier {
+            if let Ok(value) = text.parse::<f32>() {
+                literals.push(FloatLiteral { range: start..index, value });
+            }
+        }
+    }
+
+    literals
+}
+
+fn strip_literals(code: &str, literals: &[FloatLiteral]) -> String { replace_literals_with(code, literals, |_| String::new()) }
+
+fn replace_literals(code: &str, literals: &[FloatLiteral], values: &[f32]) -> String {
+    // Debug formatting always keeps a dot (or an exponent), so the literal stays a float.
+    // The literals are unsigned, so negative values are parenthesized to stay valid after a binary operator (`a - (-0.5)`)
+    replace_literals_with(code, literals, |index| {
+        if values[index] < 0.0 {
+            format!("({:?})", values[index])
+        } else {
+            format!("{:?}", values[index])
+        }
+    })
+}
+
+fn replace_literals_with<F: Fn(usize) -> String>(code: &str, literals: &[FloatLiteral], replacement: F) -> String {
+    let mut result = String::with_capacity(code.len());
+    let mut last = 0;
+    for (index, literal) in literals.iter().enumerate() {
+        result.push_str(&code[last..literal.range.start]);
+        result.push_str(&replacement(index));
+        last = literal.range.end;
+    }
+    result.push_str(&code[last..]);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn values(code: &str) -> Vec<f32> { float_literals(code).iter().map(|literal| literal.value).collect() }
+
+    #[test]
+    fn literals_inside_identifiers_are_untouched() {
+        assert_eq!(values("let a: vec4<f32> = vec4<f32>(x1.0, y_2.5, 0.5, 3);"), vec![0.5]);
+        assert_eq!(values("let b = 1.0f + 2.5h + .25;"), vec![0.25]);
+        assert!(values("let c = 0x1A + 12u + 3i;").is_empty());
+    }
+
+    #[test]
+    fn exponent_literals() {
+        assert_eq!(values("a * 2.5e-3 + 1E2 - 4.e+1"), vec![2.5e-3, 1e2, 4e1]);
+
+        let code = "return 1.5e-2 * x;";
+        let literals = float_literals(code);
+        assert_eq!(&code[literals[0].range.clone()], "1.5e-2");
+        assert_eq!(replace_literals(code, &literals, &[2e-7]), "return 2e-7 * x;");
+    }
+
+    #[test]
+    fn negative_replacements_stay_valid() {
+        let code = "return a - 0.5 + vec4<f32>(1.0, b*2.0, 0.0, 1.0);";
+        let literals = float_literals(code);
+        let replaced = replace_literals(code, &literals, &[-0.5, 1.0, -2.0, 0.25, 3.0]);
+        assert_eq!(replaced, "return a - (-0.5) + vec4<f32>(1.0, b*(-2.0), 0.25, 3.0);");
+        assert_eq!(values(&replaced), vec![0.5, 1.0, 2.0, 0.25, 3.0]);
+        assert_eq!(strip_literals(&replaced, &float_literals(&replaced)), "return a - (-) + vec4<f32>(, b*(-), , );");
+    }
+
+    #[test]
+    fn crossover_mixes_constants_of_the_same_structure() {
+        let first = Genome {
+            kernel: [0.0; 9],
+            activation_code: "return x * 0.5 + 1.0;".to_owned(),
+        };
+        let second = Genome {
+            kernel: [1.0; 9],
+            activation_code: "return x * 2.0 + 3.0;".to_owned(),
+        };
+
+        for seed in 0..50 {
+            let child = crossover(&first, &second, Lattice::Square, KernelSymmetryMode::Any, &mut StdRng::seed_from_u64(seed));
+            let child_values = values(&child.activation_code);
+            assert!(matches!(child_values[..], [0.5 | 2.0, 1.0 | 3.0]), "{}", child.activation_code);
+            assert!(child.kernel.iter().all(|&value| value == 0.0 || value == 1.0));
+        }
+    }
+
+    #[test]
+    fn crossover_of_different_structures_takes_one_parent() {
+        let first = Genome {
+            kernel: [0.0; 9],
+            activation_code: "return x * 0.5;".to_owned(),
+        };
+        let second = Genome {
+            kernel: [1.0; 9],
+            activation_code: "return clamp(x, 0.0, 1.0);".to_owned(),
+        };
+
+        let children: Vec<String> = (0..50)
+            .map(|seed| crossover(&first, &second, Lattice::Square, KernelSymmetryMode::Any, &mut StdRng::seed_from_u64(seed)))
+            .map(|child| child.activation_code)
+            .collect();
+        assert!(children.iter().all(|code| *code == first.activation_code || *code == second.activation_code));
+        assert!(children.contains(&first.activation_code) && children.contains(&second.activation_code));
+    }
+}
@@ -0,0 +1,450 @@
+use anyhow::Context;
+use rand::{seq::IteratorRandom, Rng};
+use std::collections::VecDeque;
+
+use oxyde::egui as egui;
+use oxyde::wgpu as wgpu;
+
+use egui_widgets::IqGradient;
+use glam::Vec3;
+
+use nca_core::{
+    kernel_randomizer::KernelRandomizer,
+    kernel_symmetry::KernelSymmetryMode,
+    preset::{self, KernelLayout, Preset, ACTIVATION_FUNCTIONS_PRESETS, PRESETS},
+    statistics::StatisticsPass,
+    BoundaryCondition,
+    ColorMap,
+    DisplayFramesMode,
+    InitSimulationUniforms,
+    Lattice,
+    PostProcessing,
+    Simulation,
+    StateSnapshot,
+    ViewParameters,
+    DEFAULT_DISPLAY_CODE,
+};
+
+use super::{
+    command_line::CommandLineOptions,
+    scoring::{ScoreWeights, StateMetrics},
+    state_detection::{DetectionPolicy, StateDetector, StateEvent},
+};
+
+const THUMBNAIL_SIZE: f32 = 96.0;
+// Resets or randomizations of a run before it is kept as is
+const MAX_DETECTION_RETRIES: usize = 3;
+
+pub struct ExplorerSettings {
+    pub run_count: usize,
+    pub generations: u32,
+    pub simulation_size: u32,
+    /// Generations simulated per frame when running in the background
+    pub steps_per_frame: u32,
+    pub top_k: usize,
+    /// Pick a random activation preset for each run instead of the current activation
+    pub random_activation: bool,
+    pub weights: ScoreWeights,
+    /// Applied to dead, static or periodic runs, `Pause` ends the run early
+    pub detection_policy: DetectionPolicy,
+}
+
+impl Default for ExplorerSettings {
+    fn default() -> Self {
+        Self {
+            run_count: 32,
+            generations: 200,
+            simulation_size: 128,
+            steps_per_frame: 20,
+            top_k: 12,
+            random_activation: true,
+            weights: ScoreWeights::default(),
+            detection_policy: DetectionPolicy::Pause,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub kernel: [f32; 9],
+    pub lattice: Lattice,
+    pub kernel_symmetry_mode: KernelSymmetryMode,
+    pub kernel_randomizer: KernelRandomizer,
+    pub activation_name: String,
+    pub activation_code: String,
+    pub init_seed: f32,
+    pub state_event: Option<StateEvent>,
+}
+
+impl Candidate {
+    pub fn to_preset(&self, gradient: IqGradient, color_map: ColorMap, display_code: String, post_processing: PostProcessing) -> Preset {
+        Preset {
+            kernel: self.kernel,
+            kernel_layout: KernelLayout::RowMajor,
+            kernel_symmetry_mode: self.kernel_symmetry_mode,
+            activation_code: self.activation_code.clone(),
+            display_frames_mode: DisplayFramesMode::All,
+            gradient,
+            color_map,
+            display_code,
+            post_processing,
+            kernel_randomizer: Some(self.kernel_randomizer),
+            boundary_condition: BoundaryCondition::Wrap,
+            lattice: self.lattice,
+        }
+    }
+}
+
+pub struct ExplorationResult {
+    pub candidate: Candidate,
+    pub metrics: StateMetrics,
+    pub score: f32,
+    pub state: StateSnapshot,
+    thumbnail: Option<egui::TextureHandle>,
+}
+
+pub enum ExplorerAction {
+    Start,
+    Open(Preset),
+    Save(Preset),
+}
+
+/// Batch random search: runs candidates one after the other and keeps the best scored ones
+pub struct Explorer {
+    pub open: bool,
+    pub settings: ExplorerSettings,
+    pending: VecDeque<Candidate>,
+    running: Option<(Candidate, Simulation)>,
+    results: Vec<ExplorationResult>,
+    detector: StateDetector,
+    detection_retries: usize,
+    statistics_pass: Option<StatisticsPass>,
+    total: usize,
+    completed: usize,
+    failed: usize,
+}
+
+impl Explorer {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            settings: ExplorerSettings::default(),
+            pending: VecDeque::new(),
+            running: None,
+            results: Vec::new(),
+            detector: StateDetector::new(),
+            detection_retries: 0,
+            statistics_pass: None,
+            total: 0,
+            completed: 0,
+            failed: 0,
+        }
+    }
+
+    pub fn is_running(&self) -> bool { self.running.is_some() || !self.pending.is_empty() }
+
+    pub fn results(&self) -> &[ExplorationResult] { &self.results }
+
+    pub fn start(&mut self, randomizer: &KernelRandomizer, lattice: Lattice, kernel_symmetry_mode: KernelSymmetryMode, current_activation: &str, init_seed: f32) {
+        let mut rng = rand::rng();
+        let mut randomizer = *randomizer;
+
+        self.pending = (0..self.settings.run_count)
+            .map(|_| {
+                randomizer.seed = rng.random();
+                let (activation_name, activation_code) = if self.settings.random_activation {
+                    ACTIVATION_FUNCTIONS_PRESETS
+                        .iter()
+                        .choose(&mut rng)
+                        .map(|(name, code)| (name.to_string(), code.to_string()))
+                        .expect("There is at least one activation preset")
+                } else {
+                    ("Current".to_owned(), current_activation.to_owned())
+                };
+
+                Candidate {
+                    kernel: randomizer.generate(lattice, kernel_symmetry_mode),
+                    lattice,
+                    kernel_symmetry_mode,
+                    kernel_randomizer: randomizer,
+                    activation_name,
+                    activation_code,
+                    init_seed,
+                    state_event: None,
+                }
+            })
+            .collect();
+
+        self.running = None;
+        self.results.clear();
+        self.total = self.settings.run_count;
+        self.completed = 0;
+        self.failed = 0;
+    }
+
+    pub fn stop(&mut self) {
+        self.pending.clear();
+        self.running = None;
+    }
+
+    /// Do a bounded amount of work, to be called once per frame
+    pub fn advance(&mut self, device: &mut wgpu::Device, queue: &wgpu::Queue) {
+        let Some((candidate, simulation)) = self.running.as_mut() else {
+            let Some(candidate) = self.pending.pop_front() else {
+                return;
+            };
+            let size = [self.settings.simulation_size; 2];
+            match Simulation::new(device, queue, size, &candidate.to_preset(IqGradient::default(), ColorMap::default(), DEFAULT_DISPLAY_CODE.to_owned(), PostProcessing::default())) {
+                Ok(mut simulation) => {
+                    simulation.init(InitSimulationUniforms::from_seed(candidate.init_seed));
+                    self.running = Some((candidate, simulation));
+                    self.detector.reset();
+                    self.detection_retries = 0;
+                },
+                Err(error) => {
+                    println!("Unable to build the simulation for the `{}` activation.\n {}", candidate.activation_name, error);
+                    self.failed += 1;
+                },
+            }
+            return;
+        };
+
+        let remaining = self.settings.generations.saturating_sub(simulation.generation);
+        let mut finished = remaining <= 1;
+        if !finished {
+            let generations = (remaining - 1).min(self.settings.steps_per_frame.max(1));
+            if self.settings.detection_policy == DetectionPolicy::Disabled {
+                simulation.step(generations);
+                return;
+            }
+
+            let statistics_pass = self.statistics_pass.get_or_insert_with(|| StatisticsPass::new(device));
+            for _ in 0..generations {
+                simulation.step(1);
+                let Some(event) = self.detector.observe(&simulation.measure(statistics_pass)) else {
+                    continue;
+                };
+                candidate.state_event = Some(event);
+
+                let retry = matches!(self.settings.detection_policy, DetectionPolicy::Reset | DetectionPolicy::Randomize)
+                    && self.detection_retries < MAX_DETECTION_RETRIES;
+                if retry {
+                    let mut rng = rand::rng();
+                    if self.settings.detection_policy == DetectionPolicy::Randomize {
+                        candidate.kernel_randomizer.seed = rng.random();
+                        candidate.kernel = candidate.kernel_randomizer.generate(candidate.lattice, candidate.kernel_symmetry_mode);
+                        simulation.set_kernel(candidate.kernel);
+                    }
+                    candidate.init_seed = rng.random();
+                    simulation.init(InitSimulationUniforms::from_seed(candidate.init_seed));
+
+                    candidate.state_event = None;
+                    self.detection_retries += 1;
+                    self.detector.reset();
+                } else if self.settings.detection_policy != DetectionPolicy::Notify {
+                    finished = true;
+                }
+                break;
+            }
+        }
+
+        if !finished {
+            return;
+        }
+
+        // The activity is measured on the last generation
+        let previous = simulation.read_state();
+        simulation.step(1);
+        let state = simulation.read_state();
+
+        let (candidate, _) = self.running.take().expect("Checked above");
+        let metrics = StateMetrics::compute(&previous, &state);
+        self.results.push(ExplorationResult {
+            candidate,
+            metrics,
+            score: self.settings.weights.score(&metrics),
+            state,
+            thumbnail: None,
+        });
+        self.sort_results();
+        self.completed += 1;
+    }
+
+    pub fn run_to_completion(&mut self, device: &mut wgpu::Device, queue: &wgpu::Queue) {
+        while self.is_running() {
+            self.advance(device, queue);
+        }
+    }
+
+    fn sort_results(&mut self) {
+        self.results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+        self.results.truncate(self.settings.top_k);
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, view_parameters: &ViewParameters, color_map: &ColorMap, display_code: &str, post_processing: &PostProcessing) -> Option<ExplorerAction> {
+        let mut action: Option<ExplorerAction> = None;
+        let mut open = self.open;
+
+        egui::Window::new("Explorer").open(&mut open).default_width(480.0).show(ctx, |ui| {
+            let running = self.is_running();
+            ui.add_enabled_ui(!running, |ui| {
+                egui::Grid::new("explorer settings").show(ui, |ui| {
+                    ui.add(egui::DragValue::new(&mut self.settings.run_count).range(1..=10000).prefix("runs: "));
+                    ui.add(egui::DragValue::new(&mut self.settings.generations).range(2..=100000).prefix("generations: "));
+                    ui.end_row();
+                    ui.add(egui::DragValue::new(&mut self.settings.simulation_size).range(16..=1024).prefix("size: "));
+                    ui.add(egui::DragValue::new(&mut self.settings.top_k).range(1..=100).prefix("keep best: "));
+                    ui.end_row();
+                });
+                ui.checkbox(&mut self.settings.random_activation, "random activation presets");
+                ui.horizontal(|ui| {
+                    ui.label("dead, static or periodic runs:");
+                    self.settings.detection_policy.ui_control(ui, "explorer_detection_policy");
+                });
+                self.detector.ui_control(ui);
+            });
+            ui.add(egui::DragValue::new(&mut self.settings.steps_per_frame).range(1..=1000).prefix("generations per frame: "));
+
+            egui::CollapsingHeader::new("Score weights").show(ui, |ui| {
+                let weights = &mut self.settings.weights;
+                let mut changed: bool = false;
+                egui::Grid::new("explorer score weights").show(ui, |ui| {
+                    for (label, weight) in [
+                        ("activity", &mut weights.activity),
+                        ("entropy", &mut weights.entropy),
+                        ("non death", &mut weights.non_death),
+                        ("non saturation", &mut weights.non_saturation),
+                        ("spatial structure", &mut weights.structure),
+                    ] {
+                        ui.label(label);
+                        changed |= ui.add(egui::DragValue::new(weight).speed(0.05).range(0.0..=10.0)).changed();
+                        ui.end_row();
+                    }
+                });
+                if changed {
+                    for result in self.results.iter_mut() {
+                        result.score = self.settings.weights.score(&result.metrics);
+                    }
+                    self.sort_results();
+                }
+            });
+
+            ui.horizontal(|ui| {
+                if running {
+                    if ui.button("Stop").clicked() {
+                        self.stop();
+                    }
+                } else if ui.button("Start").clicked() {
+                    action = Some(ExplorerAction::Start);
+                }
+
+                if self.total > 0 {
+                    let done = self.completed + self.failed;
+                    ui.add(egui::ProgressBar::new(done as f32 / self.total as f32).text(format!("{}/{} ({} failed)", done, self.total, self.failed)));
+                }
+            });
+
+            ui.separator();
+
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    for (index, result) in self.results.iter_mut().enumerate() {
+                        ui.vertical(|ui| {
+                            let texture = result.thumbnail.get_or_insert_with(|| {
+                                ctx.load_texture(format!("explorer result {}", index), snapshot_to_color_image(&result.state, view_parameters), egui::TextureOptions::NEAREST)
+                            });
+                            ui.image((texture.id(), egui::vec2(THUMBNAIL_SIZE, THUMBNAIL_SIZE))).on_hover_text(format!(
+                                "{}{}\nactivity: {:.3}\nentropy: {:.3}\nnon death: {:.3}\nnon saturation: {:.3}\nstructure: {:.3}",
+                                result.candidate.activation_name,
+                                result.candidate.state_event.map(|event| format!(" ({})", event)).unwrap_or_default(),
+                                result.metrics.activity,
+                                result.metrics.entropy,
+                                result.metrics.non_death,
+                                result.metrics.non_saturation,
+                                result.metrics.structure,
+                            ));
+                            ui.label(format!("score: {:.3}", result.score));
+                            ui.horizontal(|ui| {
+                                if ui.small_button("Open").clicked() {
+                                    action = Some(ExplorerAction::Open(result.candidate.to_preset(view_parameters.gradient, color_map.clone(), display_code.to_owned(), post_processing.clone())));
+                                }
+                                if ui.small_button("Save").clicked() {
+                                    action = Some(ExplorerAction::Save(result.candidate.to_preset(view_parameters.gradient, color_map.clone(), display_code.to_owned(), post_processing.clone())));
+                                }
+                            });
+                        });
+                    }
+                });
+            });
+        });
+
+        self.open = open;
+        action
+    }
+}
+
+pub fn snapshot_to_color_image(snapshot: &StateSnapshot, view_parameters: &ViewParameters) -> egui::ColorImage {
+    let rgb: Vec<u8> = snapshot
+        .values()
+        .into_iter()
+        .flat_map(|value| {
+            let color: Vec3 = view_parameters.color(value);
+            [
+                egui::ecolor::gamma_u8_from_linear_f32(color[0]),
+                egui::ecolor::gamma_u8_from_linear_f32(color[1]),
+                egui::ecolor::gamma_u8_from_linear_f32(color[2]),
+            ]
+        })
+        .collect();
+    egui::ColorImage::from_rgb([snapshot.size[0] as usize, snapshot.size[1] as usize], &rgb)
+}
+
+// Continuous, the random kernels almost never sum exactly to the thresholds of discrete rules such as the game of life
+const HEADLESS_ACTIVATION_PRESET: &str = "Tanh";
+const RANDOM_ACTIVATION: &str = "random";
+
+// An activation preset, or the activation of a built-in preset (the names are compared ignoring the case and the surrounding spaces)
+fn find_activation(name: &str) -> anyhow::Result<&'static str> {
+    let matches = |preset: &str| preset.trim().eq_ignore_ascii_case(name.trim());
+    ACTIVATION_FUNCTIONS_PRESETS
+        .iter()
+        .find(|(preset, _)| matches(preset))
+        .map(|(_, activation_code)| *activation_code)
+        .or_else(|| PRESETS.iter().find(|(preset, _)| matches(preset)).map(|(_, preset)| preset.activation_code.as_str()))
+        .with_context(|| format!("Unknown activation preset `{}`", name))
+}
+
+/// Run an exploration without window and save the best candidates as presets in the output directory
+pub fn run_headless(run_count: usize, options: &CommandLineOptions) -> anyhow::Result<()> {
+    let (lattice, kernel_symmetry_mode) = (options.explore_lattice, options.explore_symmetry);
+    if !lattice.symmetry_modes().contains(&kernel_symmetry_mode) {
+        anyhow::bail!("The {} symmetry does not match the {} lattice", kernel_symmetry_mode, lattice);
+    }
+    let activation_name = options.explore_activation.as_deref().unwrap_or(HEADLESS_ACTIVATION_PRESET);
+    let random_activation = activation_name.eq_ignore_ascii_case(RANDOM_ACTIVATION);
+    let activation_code = if random_activation { "" } else { find_activation(activation_name)? };
+
+    let instance = wgpu::Instance::default();
+    let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default())).context("No suitable graphics adapter found")?;
+    let (mut device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default())).context("Unable to create the device")?;
+
+    let mut randomizer = KernelRandomizer::default();
+    randomizer.reseed();
+
+    let mut explorer = Explorer::new();
+    explorer.settings.run_count = run_count;
+    explorer.settings.random_activation = random_activation;
+    explorer.start(&randomizer, lattice, kernel_symmetry_mode, activation_code, rand::rng().random());
+    explorer.run_to_completion(&mut device, &queue);
+
+    let output_directory = options.explore_output.as_path();
+    std::fs::create_dir_all(output_directory).with_context(|| format!("Could not create the directory `{}`", output_directory.display()))?;
+    for (rank, result) in explorer.results().iter().enumerate() {
+        let path = output_directory.join(format!("explored_{:02}.json", rank));
+        preset::save_preset(&path, &result.candidate.to_preset(IqGradient::default(), ColorMap::default(), DEFAULT_DISPLAY_CODE.to_owned(), PostProcessing::default()))
+            .with_context(|| format!("Could not save the preset `{}`", path.display()))?;
+        println!("{} score: {:.3} ({})", path.display(), result.score, result.candidate.activation_name);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,27 @@
+use nca_core::{kernel_format, preset::{self, Preset}};
+
+/// Pasted text recognised as something that can be imported in the simulation
+#[derive(Debug, Clone)]
+pub enum PasteImport {
+    Preset(Preset),
+    Kernel([f32; 9]),
+}
+
+impl PasteImport {
+    pub fn detect(text: &str) -> Option<Self> {
+        let text = text.trim();
+
+        if text.starts_with('{') {
+            return preset::parse_preset(text).ok().map(PasteImport::Preset);
+        }
+
+        kernel_format::parse_kernel(text).ok().map(PasteImport::Kernel)
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            PasteImport::Preset(_) => "simulation preset",
+            PasteImport::Kernel(_) => "kernel",
+        }
+    }
+}
@@ -0,0 +1,326 @@
+use anyhow::Context;
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+};
+
+use oxyde::egui as egui;
+use oxyde::wgpu as wgpu;
+
+use nca_core::{read_texture, view_data::ViewData, PostProcessor};
+
+/// GIF delays are in hundredths of a second, and most viewers slow down the delays below 2 hundredths
+const MAX_GIF_FRAME_RATE: u32 = 50;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum VideoFormat {
+    /// Encoded natively
+    Gif,
+    /// Encoded by a local ffmpeg
+    Mp4,
+    /// Encoded by a local ffmpeg
+    WebM,
+}
+
+impl VideoFormat {
+    pub const ALL: [VideoFormat; 3] = [VideoFormat::Gif, VideoFormat::Mp4, VideoFormat::WebM];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            VideoFormat::Gif => "gif",
+            VideoFormat::Mp4 => "mp4",
+            VideoFormat::WebM => "webm",
+        }
+    }
+}
+
+impl Display for VideoFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            VideoFormat::Gif => write!(f, "GIF"),
+            VideoFormat::Mp4 => write!(f, "MP4 (ffmpeg)"),
+            VideoFormat::WebM => write!(f, "WebM (ffmpeg)"),
+        }
+    }
+}
+
+pub struct RecorderSettings {
+    /// Resolution of the video, independent of the window
+    pub size: [u32; 2],
+    pub frame_rate: u32,
+    /// Simulated generations between two video frames, whatever the update rate
+    pub steps_per_frame: u32,
+    /// The recording stops by itself after this many frames, 0 to record until stopped
+    pub frame_count: u32,
+    pub format: VideoFormat,
+}
+
+impl Default for RecorderSettings {
+    fn default() -> Self {
+        Self {
+            size: [512, 512],
+            frame_rate: 30,
+            steps_per_frame: 1,
+            frame_count: 300,
+            format: VideoFormat::Gif,
+        }
+    }
+}
+
+enum FrameSink {
+    Gif(gif::Encoder<BufWriter<File>>),
+    /// Raw rgba frames piped to ffmpeg
+    Ffmpeg(Child),
+}
+
+impl FrameSink {
+    fn create(path: &Path, settings: &RecorderSettings) -> anyhow::Result<Self> {
+        let [width, height] = settings.size;
+        match settings.format {
+            VideoFormat::Gif => {
+                let width: u16 = width.try_into().context("The GIF width is limited to 65535")?;
+                let height: u16 = height.try_into().context("The GIF height is limited to 65535")?;
+                let file = File::create(path).with_context(|| format!("Could not create the file `{}`", path.display()))?;
+                let mut encoder = gif::Encoder::new(BufWriter::new(file), width, height, &[])?;
+                encoder.set_repeat(gif::Repeat::Infinite)?;
+                Ok(FrameSink::Gif(encoder))
+            },
+            VideoFormat::Mp4 | VideoFormat::WebM => {
+                let codec: &[&str] = if settings.format == VideoFormat::Mp4 { &["-c:v", "libx264"] } else { &["-c:v", "libvpx-vp9"] };
+                let child = Command::new("ffmpeg")
+                    .args(["-y", "-loglevel", "error", "-f", "rawvideo", "-pixel_format", "rgba"])
+                    .args(["-video_size", &format!("{}x{}", width, height)])
+                    .args(["-framerate", &settings.frame_rate.max(1).to_string()])
+                    .args(["-i", "-"])
+                    .args(codec)
+                    // yuv420p requires even dimensions
+                    .args(["-vf", "pad=ceil(iw/2)*2:ceil(ih/2)*2", "-pix_fmt", "yuv420p"])
+                    .arg(path)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::null())
+                    .spawn()
+                    .context("Unable to start ffmpeg, is it installed and in the PATH?")?;
+                Ok(FrameSink::Ffmpeg(child))
+            },
+        }
+    }
+
+    fn write(&mut self, rgba: &mut [u8], size: [u32; 2], frame_rate: u32, frame_index: u32) -> anyhow::Result<()> {
+        match self {
+            FrameSink::Gif(encoder) => {
+                let mut frame = gif::Frame::from_rgba_speed(size[0] as u16, size[1] as u16, rgba, 10);
+                // In hundredths of a second, each frame ends at its exact time rounded down,
+                // so the rounding errors do not add up (3, 3, 4 hundredths at 30 fps)
+                let frame_rate = frame_rate.clamp(1, MAX_GIF_FRAME_RATE) as u64;
+                let end = (frame_index as u64 + 1) * 100 / frame_rate;
+                let start = frame_index as u64 * 100 / frame_rate;
+                frame.delay = (end - start) as u16;
+                encoder.write_frame(&frame)?;
+            },
+            FrameSink::Ffmpeg(child) => {
+                child.stdin.as_mut().context("The ffmpeg input is closed")?.write_all(rgba).context("Unable to send the frame to ffmpeg")?;
+            },
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> anyhow::Result<()> {
+        match self {
+            // Writes the trailer
+            FrameSink::Gif(encoder) => {
+                encoder.into_inner()?.flush()?;
+            },
+            FrameSink::Ffmpeg(mut child) => {
+                // Closing the input ends the encoding
+                drop(child.stdin.take());
+                let status = child.wait()?;
+                anyhow::ensure!(status.success(), "ffmpeg failed ({})", status);
+            },
+        }
+        Ok(())
+    }
+}
+
+struct Recording {
+    path: PathBuf,
+    size: [u32; 2],
+    frame_rate: u32,
+    frame_count: u32,
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    screen_render_pipeline: wgpu::RenderPipeline,
+    // Own view uniforms, the view of the window resized to the video
+    view_data: ViewData,
+    // Own trails history, at the size of the video
+    post_processor: PostProcessor,
+    sink: FrameSink,
+    frames: u32,
+}
+
+pub enum RecorderAction {
+    Start,
+    Stop,
+}
+
+/// Offscreen capture of the displayed simulation to a video file
+pub struct Recorder {
+    pub open: bool,
+    pub settings: RecorderSettings,
+    recording: Option<Recording>,
+    status: Option<String>,
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            settings: RecorderSettings::default(),
+            recording: None,
+            status: None,
+        }
+    }
+
+    pub fn is_recording(&self) -> bool { self.recording.is_some() }
+
+    /// Format of the capture texture, the screen pipeline given to `start` must target it
+    pub fn texture_format(surface_format: wgpu::TextureFormat) -> wgpu::TextureFormat {
+        if surface_format.is_srgb() {
+            wgpu::TextureFormat::Bgra8UnormSrgb
+        } else {
+            wgpu::TextureFormat::Bgra8Unorm
+        }
+    }
+
+    pub fn start(&mut self, device: &wgpu::Device, path: &Path, texture_format: wgpu::TextureFormat, screen_render_pipeline: wgpu::RenderPipeline) -> anyhow::Result<()> {
+        self.stop();
+
+        let sink = FrameSink::create(path, &self.settings)?;
+        let [width, height] = self.settings.size;
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Recording texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: texture_format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        self.recording = Some(Recording {
+            path: path.to_owned(),
+            size: self.settings.size,
+            frame_rate: self.settings.frame_rate,
+            frame_count: self.settings.frame_count,
+            texture,
+            view,
+            screen_render_pipeline,
+            view_data: ViewData::new(device),
+            post_processor: PostProcessor::new(device, texture_format),
+            sink,
+            frames: 0,
+        });
+        self.status = None;
+        Ok(())
+    }
+
+    /// Target view, pipeline, view uniforms and post processing of the next frame
+    pub fn target(&mut self) -> Option<(&wgpu::TextureView, &wgpu::RenderPipeline, &mut ViewData, &mut PostProcessor)> {
+        self.recording
+            .as_mut()
+            .map(|recording| (&recording.view, &recording.screen_render_pipeline, &mut recording.view_data, &mut recording.post_processor))
+    }
+
+    /// Blocking read back of the frame rendered into the target, to be called once its rendering is submitted
+    pub fn capture(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let Some(recording) = &mut self.recording else {
+            return;
+        };
+
+        let mut snapshot = read_texture(device, queue, &recording.texture, recording.size);
+        match recording.sink.write(&mut snapshot.rgba, recording.size, recording.frame_rate, recording.frames) {
+            Ok(()) => recording.frames += 1,
+            Err(error) => {
+                println!("Unable to write the frame {} of the recording.\n {:?}", recording.frames, error);
+                self.stop();
+                self.status = Some(format!("{:#}", error));
+                return;
+            },
+        }
+
+        if recording.frame_count > 0 && recording.frames >= recording.frame_count {
+            self.stop();
+        }
+    }
+
+    pub fn stop(&mut self) {
+        let Some(recording) = self.recording.take() else {
+            return;
+        };
+
+        let frames = recording.frames;
+        let path = recording.path;
+        self.status = Some(match recording.sink.finish() {
+            Ok(()) => format!("{} frames saved to {}", frames, path.display()),
+            Err(error) => {
+                println!("Unable to finish the recording at path {}.\n {:?}", path.display(), error);
+                format!("{:#}", error)
+            },
+        });
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<RecorderAction> {
+        let mut action: Option<RecorderAction> = None;
+        let mut open = self.open;
+
+        egui::Window::new("Recorder").open(&mut open).default_width(320.0).show(ctx, |ui| {
+            ui.add_enabled_ui(!self.is_recording(), |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Resolution: ");
+                    ui.add(egui::DragValue::new(&mut self.settings.size[0]).range(16..=8192));
+                    ui.add(egui::DragValue::new(&mut self.settings.size[1]).range(16..=8192));
+                });
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut self.settings.frame_rate).range(1..=120).suffix(" fps"))
+                        .on_hover_text(format!("Limited to {} fps in GIF", MAX_GIF_FRAME_RATE));
+                    ui.add(egui::DragValue::new(&mut self.settings.steps_per_frame).range(1..=1000).prefix("steps per frame: "));
+                });
+                ui.add(egui::DragValue::new(&mut self.settings.frame_count).range(0..=u32::MAX).prefix("frames: "))
+                    .on_hover_text("0 to record until stopped");
+                egui::ComboBox::from_id_source("video_format").selected_text(self.settings.format.to_string()).show_ui(ui, |ui| {
+                    for format in VideoFormat::ALL {
+                        ui.selectable_value(&mut self.settings.format, format, format.to_string());
+                    }
+                });
+            });
+
+            match &self.recording {
+                Some(recording) => {
+                    ui.label(format!("Recording {} ({} frames)", recording.path.display(), recording.frames));
+                    if ui.button("Stop").clicked() {
+                        action = Some(RecorderAction::Stop);
+                    }
+                },
+                None =>
+                    if ui.button("Record").clicked() {
+                        action = Some(RecorderAction::Start);
+                    },
+            }
+
+            if let Some(status) = &self.status {
+                ui.label(status);
+            }
+        });
+
+        self.open = open;
+        action
+    }
+}
@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+
+use nca_core::StateSnapshot;
+
+const HISTOGRAM_BINS: usize = 32;
+// Smallest difference representable in the 8 bits simulation textures
+const VALUE_EPSILON: f32 = 0.5 / 255.0;
+// Fraction of alive (or unsaturated) cells above which a state is fully considered as alive (or unsaturated)
+const POPULATION_THRESHOLD: f32 = 0.05;
+
+/// Measures of how interesting a simulation state is, all of them in [0, 1]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Default)]
+pub struct StateMetrics {
+    /// Fraction of cells that changed since the previous generation
+    pub activity: f32,
+    /// Normalized Shannon entropy of the value histogram
+    pub entropy: f32,
+    /// Close to 0 when (almost) every cell is dead
+    pub non_death: f32,
+    /// Close to 0 when (almost) every cell is saturated
+    pub non_saturation: f32,
+    /// Correlation between neighbouring cells: 0 for noise or uniform fields, close to 1 for large coherent patterns
+    pub structure: f32,
+}
+
+impl StateMetrics {
+    pub fn compute(previous: &StateSnapshot, current: &StateSnapshot) -> Self {
+        let previous_values = previous.values();
+        let values = current.values();
+        let count = values.len() as f32;
+        let [width, height] = current.size.map(|x| x as usize);
+
+        let changed = values.iter().zip(previous_values.iter()).filter(|(a, b)| (*a - *b).abs() > VALUE_EPSILON).count();
+
+        let mut histogram = [0usize; HISTOGRAM_BINS];
+        for value in values.iter() {
+            histogram[((value * HISTOGRAM_BINS as f32) as usize).min(HISTOGRAM_BINS - 1)] += 1;
+        }
+        let entropy: f32 = histogram
+            .iter()
+            .filter(|&&bin| bin > 0)
+            .map(|&bin| {
+                let p = bin as f32 / count;
+                -p * p.log2()
+            })
+            .sum();
+
+        let alive = values.iter().filter(|&&value| value > VALUE_EPSILON).count();
+        let unsaturated = values.iter().filter(|&&value| value < 1.0 - VALUE_EPSILON).count();
+
+        Self {
+            activity: changed as f32 / count,
+            entropy: (entropy / (HISTOGRAM_BINS as f32).log2()).max(0.0),
+            non_death: (alive as f32 / count / POPULATION_THRESHOLD).min(1.0),
+            non_saturation: (unsaturated as f32 / count / POPULATION_THRESHOLD).min(1.0),
+            structure: neighbour_correlation(&values, width, height).clamp(0.0, 1.0),
+        }
+    }
+}
+
+// Lag one autocorrelation along both axes on the torus
+fn neighbour_correlation(values: &[f32], width: usize, height: usize) -> f32 {
+    let count = values.len() as f32;
+    let mean: f32 = values.iter().sum::<f32>() / count;
+    let variance: f32 = values.iter().map(|value| (value - mean).powi(2)).sum::<f32>() / count;
+    if variance < 1e-6 {
+        return 0.0;
+    }
+
+    let mut covariance = 0.0;
+    for y in 0..height {
+        for x in 0..width {
+            let centered = values[y * width + x] - mean;
+            let right = values[y * width + (x + 1) % width] - mean;
+            let bottom = values[((y + 1) % height) * width + x] - mean;
+            covariance += centered * (right + bottom) * 0.5;
+        }
+    }
+
+    covariance / count / variance
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct ScoreWeights {
+    pub activity: f32,
+    pub entropy: f32,
+    pub non_death: f32,
+    pub non_saturation: f32,
+    pub structure: f32,
+}
+
+impl Default for ScoreWeights {
+    fn default() -> Self {
+        Self {
+            activity: 1.0,
+            entropy: 1.0,
+            non_death: 1.0,
+            non_saturation: 1.0,
+            structure: 1.0,
+        }
+    }
+}
+
+impl ScoreWeights {
+    /// Weighted geometric mean of the metrics, so that a state failing on one of them (dead, pure noise, ...) gets a low score
+    pub fn score(&self, metrics: &StateMetrics) -> f32 {
+        const MIN_METRIC: f32 = 0.01;
+
+        let weighted_metrics = [
+            (self.activity, metrics.activity),
+            (self.entropy, metrics.entropy),
+            (self.non_death, metrics.non_death),
+            (self.non_saturation, metrics.non_saturation),
+            (self.structure, metrics.structure),
+        ];
+
+        let total_weight: f32 = weighted_metrics.iter().map(|(weight, _)| weight).sum();
+        if total_weight <= 0.0 {
+            return 0.0;
+        }
+
+        let log_sum: f32 = weighted_metrics.iter().map(|(weight, metric)| weight * metric.max(MIN_METRIC).ln()).sum();
+        (log_sum / total_weight).exp()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: u32 = 64;
+
+    fn state(value: impl Fn(u32, u32) -> f32) -> StateSnapshot {
+        let rgba = (0..SIZE * SIZE)
+            .flat_map(|index| {
+                let byte = (value(index % SIZE, index / SIZE).clamp(0.0, 1.0) * 255.0).round() as u8;
+                [byte, byte, byte, 255]
+            })
+            .collect();
+        StateSnapshot { size: [SIZE, SIZE], rgba }
+    }
+
+    // Smooth waves travelling along the diagonal
+    fn waves(phase: f32) -> StateSnapshot {
+        state(|x, y| 0.5 + 0.5 * ((x + y) as f32 * std::f32::consts::TAU / 16.0 + phase).sin())
+    }
+
+    fn score(previous: &StateSnapshot, current: &StateSnapshot) -> f32 { ScoreWeights::default().score(&StateMetrics::compute(previous, current)) }
+
+    #[test]
+    fn active_structured_state_scores_best() {
+        let active = score(&waves(0.0), &waves(0.5));
+
+        let extinct = score(&state(|_, _| 0.0), &state(|_, _| 0.0));
+        let saturated = score(&state(|_, _| 1.0), &state(|_, _| 1.0));
+        let frozen = score(&waves(0.5), &waves(0.5));
+
+        for (name, other) in [("extinct", extinct), ("saturated", saturated), ("static", frozen)] {
+            assert!(other < active, "The {} state scores {} and the active one {}", name, other, active);
+        }
+    }
+}
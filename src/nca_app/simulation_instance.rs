@@ -0,0 +1,74 @@
+use oxyde::wgpu as wgpu;
+
+use nca_core::{kernel_symmetry::KernelSymmetryMode, preset::Preset, BoundaryCondition, Lattice, Simulation};
+
+use super::ShaderState;
+
+/// Render states shared by the screen pipelines
+pub struct PipelineStates<'a> {
+    pub primitive_state: &'a wgpu::PrimitiveState,
+    pub multisample_state: &'a wgpu::MultisampleState,
+    pub screen_shader: &'a wgpu::ShaderModule,
+}
+
+/// One simulation of the central viewport: its own textures, kernel and activation.
+/// The initialisation, the simulation size and the view transform are shared between instances.
+pub struct SimulationInstance {
+    pub name: String,
+
+    pub simulation: Simulation,
+
+    pub kernel_symmetry_mode: KernelSymmetryMode,
+    /// Requested boundary condition, applied by `update_boundary_condition`
+    pub boundary_condition: BoundaryCondition,
+
+    /// Edited activation code, compiled by `try_generate_simulation_pipeline`
+    pub activation_code: String,
+    pub shader_state: ShaderState,
+}
+
+impl SimulationInstance {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        name: String,
+        simulation_size: [u32; 2],
+        kernel: [f32; 9],
+        kernel_symmetry_mode: KernelSymmetryMode,
+        boundary_condition: BoundaryCondition,
+        lattice: Lattice,
+        activation_code: String,
+    ) -> Result<Self, wgpu::Error> {
+        let preset = Preset {
+            kernel,
+            kernel_symmetry_mode,
+            activation_code: activation_code.clone(),
+            boundary_condition,
+            lattice,
+            ..Preset::default()
+        };
+
+        Ok(Self {
+            name,
+            simulation: Simulation::new(device, queue, simulation_size, &preset)?,
+            kernel_symmetry_mode,
+            boundary_condition,
+            activation_code,
+            shader_state: ShaderState::Compiled,
+        })
+    }
+
+    pub fn try_generate_simulation_pipeline(&mut self) -> Result<(), wgpu::Error> {
+        self.simulation.set_activation(&self.activation_code)?;
+        self.shader_state = ShaderState::Compiled;
+        Ok(())
+    }
+
+    /// Apply the requested boundary condition when it changed,
+    /// so that the sampler address mode stays consistent with the mode read by the shader
+    pub fn update_boundary_condition(&mut self) {
+        if self.boundary_condition != self.simulation.boundary_condition() {
+            self.simulation.set_boundary_condition(self.boundary_condition);
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::VecDeque, fmt::Display};
+
+use oxyde::egui as egui;
+
+use nca_core::statistics::GenerationStatistics;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StateEvent {
+    /// Every cell is dead
+    Extinction,
+    /// Every cell is saturated
+    Saturation,
+    /// The state does not change anymore
+    FixedPoint,
+    /// The state repeats itself with the given period
+    Periodic(usize),
+}
+
+impl Display for StateEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            StateEvent::Extinction => write!(f, "Extinction"),
+            StateEvent::Saturation => write!(f, "Saturation"),
+            StateEvent::FixedPoint => write!(f, "Fixed point"),
+            StateEvent::Periodic(period) => write!(f, "Period {} oscillation", period),
+        }
+    }
+}
+
+/// What to do once an event is detected
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DetectionPolicy {
+    Disabled,
+    Notify,
+    Pause,
+    /// Restart the simulation with a new seed
+    Reset,
+    /// Restart the simulation with a new random kernel
+    Randomize,
+}
+
+impl DetectionPolicy {
+    pub const ALL: [DetectionPolicy; 5] = [
+        DetectionPolicy::Disabled,
+        DetectionPolicy::Notify,
+        DetectionPolicy::Pause,
+        DetectionPolicy::Reset,
+        DetectionPolicy::Randomize,
+    ];
+
+    pub fn ui_control(&mut self, ui: &mut egui::Ui, id_source: &str) -> bool {
+        let mut changed: bool = false;
+        egui::ComboBox::from_id_source(id_source).selected_text(self.to_string()).show_ui(ui, |ui| {
+            for policy in DetectionPolicy::ALL {
+                changed |= ui.selectable_value(self, policy, policy.to_string()).changed();
+            }
+        });
+        changed
+    }
+}
+
+impl Display for DetectionPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            DetectionPolicy::Disabled => write!(f, "Disabled"),
+            DetectionPolicy::Notify => write!(f, "Notify"),
+            DetectionPolicy::Pause => write!(f, "Pause"),
+            DetectionPolicy::Reset => write!(f, "Reset with a new seed"),
+            DetectionPolicy::Randomize => write!(f, "Randomize the kernel"),
+        }
+    }
+}
+
+/// Detects dead, static and periodic states from the statistics of consecutive generations
+pub struct StateDetector {
+    /// Longest detected period
+    pub max_period: usize,
+    /// Number of repetitions (of the fixed point or of the cycle) required to raise an event
+    pub confirmations: usize,
+    hashes: VecDeque<u64>,
+    static_generations: usize,
+    raised: Option<StateEvent>,
+}
+
+impl StateDetector {
+    pub fn new() -> Self {
+        Self {
+            max_period: 32,
+            confirmations: 2,
+            hashes: VecDeque::new(),
+            static_generations: 0,
+            raised: None,
+        }
+    }
+
+    /// To be called when the simulation restarts
+    pub fn reset(&mut self) {
+        self.hashes.clear();
+        self.static_generations = 0;
+        self.raised = None;
+    }
+
+    /// Event raised since the last reset
+    pub fn raised(&self) -> Option<StateEvent> { self.raised }
+
+    /// Feed the statistics of the next generation, an event is returned only once until the next reset
+    pub fn observe(&mut self, statistics: &GenerationStatistics) -> Option<StateEvent> {
+        if self.raised.is_some() {
+            return None;
+        }
+
+        let confirmations = self.confirmations.max(1);
+        let max_period = self.max_period.max(2);
+
+        self.hashes.push_back(statistics.hash);
+        while self.hashes.len() > max_period * (confirmations + 1) {
+            self.hashes.pop_front();
+        }
+
+        if statistics.changed_fraction == 0.0 {
+            self.static_generations += 1;
+        } else {
+            self.static_generations = 0;
+        }
+
+        let event = if statistics.max == 0.0 {
+            Some(StateEvent::Extinction)
+        } else if statistics.min == 1.0 {
+            Some(StateEvent::Saturation)
+        } else if self.static_generations >= confirmations {
+            Some(StateEvent::FixedPoint)
+        } else {
+            (2..=max_period).find(|&period| self.repeats_with_period(period, confirmations)).map(StateEvent::Periodic)
+        };
+
+        self.raised = event;
+        event
+    }
+
+    // The last `confirmations` cycles are equal to the cycle before them
+    fn repeats_with_period(&self, period: usize, confirmations: usize) -> bool {
+        let compared = period * confirmations;
+        if self.hashes.len() < compared + period {
+            return false;
+        }
+        let last = self.hashes.len() - 1;
+        (0..compared).all(|i| self.hashes[last - i] == self.hashes[last - i - period])
+    }
+
+    pub fn ui_control(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed: bool = false;
+        ui.horizontal(|ui| {
+            changed |= ui.add(egui::DragValue::new(&mut self.max_period).range(2..=256).prefix("max period: ")).changed();
+            changed |= ui
+                .add(egui::DragValue::new(&mut self.confirmations).range(1..=16).prefix("confirmations: "))
+                .on_hover_text("Repetitions required before raising an event")
+                .changed();
+        });
+        changed
+    }
+}
@@ -0,0 +1,161 @@
+use std::{collections::VecDeque, io::Write, path::Path};
+
+use oxyde::egui as egui;
+
+use nca_core::statistics::{GenerationStatistics, HISTOGRAM_BINS};
+
+// Bins merged together for the displayed histogram
+const DISPLAY_HISTOGRAM_BINS: usize = 32;
+
+type StatisticAccessor = fn(&GenerationStatistics) -> f32;
+
+/// History of the statistics of a run, displayed as time series
+pub struct Statistics {
+    pub open: bool,
+    /// Generations between two samples
+    pub sample_interval: u64,
+    pub max_history: usize,
+    history: VecDeque<GenerationStatistics>,
+}
+
+impl Statistics {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            sample_interval: 1,
+            max_history: 2000,
+            history: VecDeque::new(),
+        }
+    }
+
+    /// Whether the given generation should be measured
+    pub fn wants_sample(&self, generation: u64) -> bool { self.open && generation % self.sample_interval.max(1) == 0 }
+
+    pub fn push(&mut self, statistics: GenerationStatistics) {
+        // A new run started
+        if self.history.back().is_some_and(|last| last.generation >= statistics.generation) {
+            self.history.clear();
+        }
+        self.history.push_back(statistics);
+        while self.history.len() > self.max_history {
+            self.history.pop_front();
+        }
+    }
+
+    pub fn latest(&self) -> Option<&GenerationStatistics> { self.history.back() }
+
+    pub fn export_csv<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        writeln!(file, "generation,min,max,mean,variance,changed_fraction")?;
+        for statistics in self.history.iter() {
+            writeln!(
+                file,
+                "{},{},{},{},{},{}",
+                statistics.generation, statistics.min, statistics.max, statistics.mean, statistics.variance, statistics.changed_fraction
+            )?;
+        }
+        file.flush()
+    }
+
+    /// Returns true when a CSV export is requested
+    pub fn show(&mut self, ctx: &egui::Context) -> bool {
+        let mut export_requested: bool = false;
+        let mut open = self.open;
+
+        egui::Window::new("Statistics").open(&mut open).default_width(360.0).show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(&mut self.sample_interval).range(1..=1000).prefix("sample every: "));
+                ui.add(egui::DragValue::new(&mut self.max_history).range(10..=100000).prefix("history: "));
+            });
+            ui.horizontal(|ui| {
+                if ui.button("Clear").clicked() {
+                    self.history.clear();
+                }
+                if ui.add_enabled(!self.history.is_empty(), egui::Button::new("Export CSV")).clicked() {
+                    export_requested = true;
+                }
+            });
+
+            let Some(latest) = self.history.back() else {
+                ui.label("Waiting for the simulation.");
+                return;
+            };
+
+            ui.label(format!(
+                "generation {}\nmin {:.3}  max {:.3}\nmean {:.3}  variance {:.4}\nchanged {:.1}%",
+                latest.generation,
+                latest.min,
+                latest.max,
+                latest.mean,
+                latest.variance,
+                latest.changed_fraction * 100.0
+            ));
+
+            ui.separator();
+            ui.label("Histogram");
+            histogram_ui(ui, &latest.histogram);
+
+            let series: [(&str, egui::Color32, StatisticAccessor); 5] = [
+                ("mean", egui::Color32::LIGHT_BLUE, |s: &GenerationStatistics| s.mean),
+                ("variance", egui::Color32::LIGHT_GREEN, |s: &GenerationStatistics| s.variance),
+                ("changed fraction", egui::Color32::LIGHT_RED, |s: &GenerationStatistics| s.changed_fraction),
+                ("min", egui::Color32::GRAY, |s: &GenerationStatistics| s.min),
+                ("max", egui::Color32::WHITE, |s: &GenerationStatistics| s.max),
+            ];
+            for (label, color, value) in series {
+                ui.separator();
+                let values: Vec<f32> = self.history.iter().map(value).collect();
+                time_series_ui(ui, label, color, &values);
+            }
+        });
+
+        self.open = open;
+        export_requested
+    }
+}
+
+fn histogram_ui(ui: &mut egui::Ui, histogram: &[u32; HISTOGRAM_BINS]) {
+    let merged: Vec<u32> = histogram.chunks(HISTOGRAM_BINS / DISPLAY_HISTOGRAM_BINS).map(|bins| bins.iter().sum()).collect();
+    let highest = merged.iter().copied().max().unwrap_or(0).max(1) as f32;
+
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(ui.available_width(), 60.0), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+
+    let bar_width = rect.width() / merged.len() as f32;
+    for (index, &count) in merged.iter().enumerate() {
+        let height = rect.height() * count as f32 / highest;
+        let left = rect.left() + index as f32 * bar_width;
+        painter.rect_filled(
+            egui::Rect::from_min_max(egui::pos2(left, rect.bottom() - height), egui::pos2(left + bar_width - 1.0, rect.bottom())),
+            0.0,
+            ui.visuals().selection.bg_fill,
+        );
+    }
+}
+
+fn time_series_ui(ui: &mut egui::Ui, label: &str, color: egui::Color32, values: &[f32]) {
+    let (low, high) = values.iter().fold((f32::INFINITY, f32::NEG_INFINITY), |(low, high), &value| (low.min(value), high.max(value)));
+    ui.label(format!("{} [{:.4}, {:.4}]", label, low, high));
+
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(ui.available_width(), 50.0), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+
+    if values.len() < 2 {
+        return;
+    }
+
+    let range = (high - low).max(f32::EPSILON);
+    let points: Vec<egui::Pos2> = values
+        .iter()
+        .enumerate()
+        .map(|(index, value)| {
+            egui::pos2(
+                rect.left() + rect.width() * index as f32 / (values.len() - 1) as f32,
+                rect.bottom() - rect.height() * (value - low) / range,
+            )
+        })
+        .collect();
+    painter.add(egui::Shape::line(points, egui::Stroke::new(1.0, color)));
+}
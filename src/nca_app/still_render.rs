@@ -0,0 +1,313 @@
+use anyhow::Context;
+use glam::Vec2;
+use std::{fmt::Display, path::Path};
+
+use oxyde::egui as egui;
+use oxyde::wgpu as wgpu;
+
+use nca_core::{pipeline_helpers::build_screen_pipeline, read_texture_bytes, view_data::ViewData, ViewParameters};
+
+use super::simulation_instance::PipelineStates;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    /// Floating point colors, as written by the display shader
+    Exr,
+}
+
+impl ImageFormat {
+    pub const ALL: [ImageFormat; 2] = [ImageFormat::Png, ImageFormat::Exr];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Exr => "exr",
+        }
+    }
+}
+
+// Both formats are rendered in floating point, so that the supersampled texels are averaged in linear space
+const RENDER_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+const RENDER_BYTES_PER_TEXEL: u32 = 8;
+
+fn texel(bytes: &[u8], index: usize) -> [f32; 4] {
+    let texel = &bytes[index * 8..index * 8 + 8];
+    std::array::from_fn(|channel| half::f16::from_le_bytes([texel[channel * 2], texel[channel * 2 + 1]]).to_f32())
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+impl Display for ImageFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ImageFormat::Png => write!(f, "PNG"),
+            ImageFormat::Exr => write!(f, "EXR"),
+        }
+    }
+}
+
+enum StillImage {
+    Png(image::RgbaImage),
+    Exr(image::Rgba32FImage),
+}
+
+impl StillImage {
+    fn new(format: ImageFormat, size: [u32; 2]) -> Self {
+        match format {
+            ImageFormat::Png => StillImage::Png(image::RgbaImage::new(size[0], size[1])),
+            ImageFormat::Exr => StillImage::Exr(image::Rgba32FImage::new(size[0], size[1])),
+        }
+    }
+
+    // Linear color for png, encoded to sRGB here (alpha excepted)
+    fn put(&mut self, x: u32, y: u32, color: [f32; 4]) {
+        match self {
+            StillImage::Png(image) => {
+                let [r, g, b, a] = color;
+                let encoded = [linear_to_srgb(r.clamp(0.0, 1.0)), linear_to_srgb(g.clamp(0.0, 1.0)), linear_to_srgb(b.clamp(0.0, 1.0)), a];
+                image.put_pixel(x, y, image::Rgba(encoded.map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8)))
+            },
+            StillImage::Exr(image) => image.put_pixel(x, y, image::Rgba(color)),
+        }
+    }
+
+    fn save(&self, path: &Path) -> anyhow::Result<()> {
+        match self {
+            StillImage::Png(image) => image.save_with_format(path, image::ImageFormat::Png),
+            StillImage::Exr(image) => image.save_with_format(path, image::ImageFormat::OpenExr),
+        }
+        .with_context(|| format!("Unable to save the image `{}`", path.display()))
+    }
+}
+
+pub struct StillRenderSettings {
+    /// Resolution of the image, independent of the window
+    pub size: [u32; 2],
+    /// Rendered texels per image pixel along each axis, averaged down
+    pub supersampling: u32,
+    /// Copies of the toroidal grid along each axis
+    pub repeat: u32,
+    pub format: ImageFormat,
+}
+
+impl Default for StillRenderSettings {
+    fn default() -> Self {
+        Self {
+            size: [7680, 4320],
+            supersampling: 2,
+            repeat: 1,
+            format: ImageFormat::Png,
+        }
+    }
+}
+
+pub enum StillRenderAction {
+    Render,
+}
+
+/// Offscreen rendering of the displayed state to an image file of arbitrary size
+pub struct StillRenderer {
+    pub open: bool,
+    pub settings: StillRenderSettings,
+    status: Option<String>,
+}
+
+impl StillRenderer {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            settings: StillRenderSettings::default(),
+            status: None,
+        }
+    }
+
+    /// Draw the state through the display shader with the given view and save it,
+    /// the image is rendered by tiles so that its size is not limited by the maximum texture size
+    pub fn render(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: &Path,
+        surface_format: wgpu::TextureFormat,
+        pipeline_states: &PipelineStates,
+        texture_bind_group_layout: &wgpu::BindGroupLayout,
+        display_bind_group: &wgpu::BindGroup,
+        view_parameters: &ViewParameters,
+    ) -> anyhow::Result<()> {
+        let [width, height] = self.settings.size;
+        let format = self.settings.format;
+        let supersampling = self.settings.supersampling.clamp(1, 8);
+
+        // The same number of tiles along both axes, so that every tile covers the same fraction of the view horizontally and vertically.
+        // The tiles may exceed the image by a few pixels on the right and bottom borders, these are cropped.
+        let max_tile_size = device.limits().max_texture_dimension_2d / supersampling;
+        let tile_count = width.max(height).div_ceil(max_tile_size).max(1);
+        let tile_size = [width.div_ceil(tile_count), height.div_ceil(tile_count)];
+        let render_size = [tile_size[0] * supersampling, tile_size[1] * supersampling];
+
+        // The screen shader writes linear colors for an sRGB surface, and already encoded ones otherwise:
+        // the png matches the screen once those are decoded before averaging
+        let decode_srgb = format == ImageFormat::Png && !surface_format.is_srgb();
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Still render texture"),
+            size: wgpu::Extent3d {
+                width: render_size[0],
+                height: render_size[1],
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: RENDER_TEXTURE_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        // Own view uniforms, each tile is a zoomed part of the view
+        let mut view_data = ViewData::new(device);
+        let screen_render_pipeline = build_screen_pipeline(
+            device,
+            RENDER_TEXTURE_FORMAT,
+            pipeline_states.primitive_state,
+            pipeline_states.multisample_state,
+            pipeline_states.screen_shader,
+            texture_bind_group_layout,
+            &view_data,
+        );
+
+        let mut image = StillImage::new(format, self.settings.size);
+        // The view of the window resized to the whole image (all the tiles together), zoomed out on the repeated copies
+        let image_size = Vec2::new((render_size[0] * tile_count) as f32, (render_size[1] * tile_count) as f32);
+        let mut image_view = view_parameters.resized(image_size, view_parameters.texture_size);
+        image_view.zoom_level *= self.settings.repeat.max(1) as f32;
+        // The copies are only seamless when the screen shader wraps the texture
+        image_view.tile = (view_parameters.tile != 0 || self.settings.repeat > 1) as u32;
+        let image_extent = image_view.visible_extent();
+        let samples = (supersampling * supersampling) as f32;
+
+        for tile_row in 0..tile_count {
+            for tile_column in 0..tile_count {
+                let tile_offset = Vec2::new(tile_column as f32 + 0.5, tile_row as f32 + 0.5) / tile_count as f32 - Vec2::splat(0.5);
+                view_data.uniform = ViewParameters {
+                    center: image_view.center + tile_offset * image_extent,
+                    viewport_size: Vec2::new(render_size[0] as f32, render_size[1] as f32),
+                    ..image_view
+                };
+                view_data.update(queue);
+
+                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Still render encoder") });
+                {
+                    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some("Still Render Pass"),
+                        color_attachments: &[
+                            Some(wgpu::RenderPassColorAttachment {
+                                view: &texture_view,
+                                resolve_target: None,
+                                ops: wgpu::Operations { load: wgpu::LoadOp::Clear(wgpu::Color::BLACK), store: wgpu::StoreOp::Store },
+                                depth_slice: None,
+                            }),
+                        ],
+                        depth_stencil_attachment: None,
+                        timestamp_writes: None,
+                        occlusion_query_set: None,
+                    });
+
+                    render_pass.set_pipeline(&screen_render_pipeline);
+                    render_pass.set_bind_group(0, display_bind_group, &[]);
+                    render_pass.set_bind_group(1, &view_data.bind_group, &[]);
+                    render_pass.draw(0..3, 0..1);
+                }
+                queue.submit(Some(encoder.finish()));
+
+                let bytes = read_texture_bytes(device, queue, &texture, render_size, RENDER_BYTES_PER_TEXEL);
+
+                // Box filter of the supersampled texels
+                for y in 0..tile_size[1] {
+                    let image_y = tile_row * tile_size[1] + y;
+                    if image_y >= height {
+                        break;
+                    }
+                    for x in 0..tile_size[0] {
+                        let image_x = tile_column * tile_size[0] + x;
+                        if image_x >= width {
+                            break;
+                        }
+
+                        let mut color = [0.0f32; 4];
+                        for sample_y in 0..supersampling {
+                            for sample_x in 0..supersampling {
+                                let index = (y * supersampling + sample_y) * render_size[0] + x * supersampling + sample_x;
+                                let mut texel = texel(&bytes, index as usize);
+                                if decode_srgb {
+                                    texel[..3].iter_mut().for_each(|channel| *channel = srgb_to_linear(channel.clamp(0.0, 1.0)));
+                                }
+                                color.iter_mut().zip(texel).for_each(|(channel, value)| *channel += value);
+                            }
+                        }
+                        image.put(image_x, image_y, color.map(|channel| channel / samples));
+                    }
+                }
+            }
+        }
+
+        image.save(path)?;
+        self.status = Some(format!("{}x{} image saved to {}", width, height, path.display()));
+        Ok(())
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<StillRenderAction> {
+        let mut action: Option<StillRenderAction> = None;
+        let mut open = self.open;
+
+        egui::Window::new("Render image").open(&mut open).default_width(320.0).show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Resolution: ");
+                ui.add(egui::DragValue::new(&mut self.settings.size[0]).range(1..=65535));
+                ui.add(egui::DragValue::new(&mut self.settings.size[1]).range(1..=65535));
+            });
+            ui.horizontal(|ui| {
+                for (name, size) in [("4K", [3840, 2160]), ("8K", [7680, 4320])] {
+                    if ui.button(name).clicked() {
+                        self.settings.size = size;
+                    }
+                }
+            });
+            ui.add(egui::DragValue::new(&mut self.settings.supersampling).range(1..=8).prefix("supersampling: ").suffix("x"));
+            ui.add(egui::DragValue::new(&mut self.settings.repeat).range(1..=16).prefix("repeat: "))
+                .on_hover_text("Copies of the toroidal grid along each axis");
+            egui::ComboBox::from_id_source("image_format").selected_text(self.settings.format.to_string()).show_ui(ui, |ui| {
+                for format in ImageFormat::ALL {
+                    ui.selectable_value(&mut self.settings.format, format, format.to_string());
+                }
+            });
+
+            if ui.button("Render…").clicked() {
+                action = Some(StillRenderAction::Render);
+            }
+
+            if let Some(status) = &self.status {
+                ui.label(status);
+            }
+        });
+
+        self.open = open;
+        action
+    }
+}
@@ -0,0 +1,224 @@
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use oxyde::egui as egui;
+
+/// When an event fires, counted from the start of the timeline
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Trigger {
+    /// Simulated generations, not reset by the initialisations
+    Generation(u64),
+    /// Seconds of unpaused run
+    Seconds(f32),
+}
+
+impl std::fmt::Display for Trigger {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Trigger::Generation(generation) => write!(f, "generation {}", generation),
+            Trigger::Seconds(seconds) => write!(f, "{:.1} s", seconds),
+        }
+    }
+}
+
+/// Change applied to the selected simulation
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum TimelineAction {
+    /// Every setting of a built-in preset or of a preset file
+    LoadPreset { preset: String },
+    /// Only the kernel of a built-in preset or of a preset file
+    KernelFromPreset { preset: String },
+    SetKernel { kernel: [f32; 9] },
+    /// Add a gaussian noise to the kernel (symmetry is kept)
+    PerturbKernel { std_dev: f32 },
+    /// New kernel from the kernel randomizer settings
+    RandomizeKernel,
+    SetActivation { activation_code: String },
+    SetView {
+        #[serde(default)]
+        center: Option<[f32; 2]>,
+        #[serde(default)]
+        zoom_level: Option<f32>,
+        #[serde(default)]
+        tile: Option<bool>,
+    },
+    /// Set the cells of a disc of the selected simulation to `value` (`center` in cells, row 0 at the bottom)
+    Brush { center: [i32; 2], radius: f32, value: f32 },
+    /// Add a gaussian noise to the state of the selected simulation, from a random seed when none is given
+    PerturbState {
+        std_dev: f32,
+        #[serde(default)]
+        seed: Option<u64>,
+    },
+    /// Restart the simulation, from a random seed when none is given
+    Init {
+        #[serde(default)]
+        seed: Option<f32>,
+    },
+    Pause,
+    SavePreset { path: PathBuf },
+    SaveKernel { path: PathBuf },
+    ExportStatistics { path: PathBuf },
+}
+
+impl std::fmt::Display for TimelineAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TimelineAction::LoadPreset { preset } => write!(f, "load preset `{}`", preset),
+            TimelineAction::KernelFromPreset { preset } => write!(f, "kernel of `{}`", preset),
+            TimelineAction::SetKernel { .. } => write!(f, "set kernel"),
+            TimelineAction::PerturbKernel { std_dev } => write!(f, "perturb kernel (std dev {})", std_dev),
+            TimelineAction::RandomizeKernel => write!(f, "randomize kernel"),
+            TimelineAction::SetActivation { .. } => write!(f, "set activation"),
+            TimelineAction::SetView { .. } => write!(f, "set view"),
+            TimelineAction::Brush { center, radius, value } => write!(f, "brush ({}, {}) radius {} value {}", center[0], center[1], radius, value),
+            TimelineAction::PerturbState { std_dev, .. } => write!(f, "perturb state (std dev {})", std_dev),
+            TimelineAction::Init { .. } => write!(f, "init"),
+            TimelineAction::Pause => write!(f, "pause"),
+            TimelineAction::SavePreset { path } => write!(f, "save preset `{}`", path.display()),
+            TimelineAction::SaveKernel { path } => write!(f, "save kernel `{}`", path.display()),
+            TimelineAction::ExportStatistics { path } => write!(f, "export statistics `{}`", path.display()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TimelineEvent {
+    pub at: Trigger,
+    #[serde(flatten)]
+    pub action: TimelineAction,
+}
+
+/// Content of a timeline file:
+/// `{ "events": [{ "at": { "generation": 500 }, "action": "kernel_from_preset", "preset": "Slime" }] }`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct TimelineScript {
+    pub events: Vec<TimelineEvent>,
+}
+
+/// Scheduled parameter changes of a run, each event fires once when its trigger is reached
+pub struct Timeline {
+    pub name: String,
+    script: TimelineScript,
+    fired: Vec<bool>,
+    generation: u64,
+    elapsed: Duration,
+    last_tick: Option<Instant>,
+}
+
+impl Timeline {
+    pub fn new(name: String, script: TimelineScript) -> Self {
+        Self {
+            name,
+            fired: vec![false; script.events.len()],
+            script,
+            generation: 0,
+            elapsed: Duration::ZERO,
+            last_tick: None,
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        fn inner(path: &Path) -> anyhow::Result<TimelineScript> {
+            let string_path: &str = path.to_str().unwrap_or("");
+            let file = File::open(path).with_context(|| format!("Could not open file `{}`", string_path))?;
+            serde_json::from_reader(std::io::BufReader::new(file)).with_context(|| format!("Unable to Parse the file `{}`", string_path))
+        }
+
+        let path = path.as_ref();
+        let name = path.file_stem().and_then(|name| name.to_str()).unwrap_or("timeline").to_owned();
+        Ok(Self::new(name, inner(path)?))
+    }
+
+    pub fn restart(&mut self) {
+        self.fired.iter_mut().for_each(|fired| *fired = false);
+        self.generation = 0;
+        self.elapsed = Duration::ZERO;
+        self.last_tick = None;
+    }
+
+    pub fn is_finished(&self) -> bool { self.fired.iter().all(|fired| *fired) }
+
+    /// To be called for each simulated generation
+    pub fn on_generation(&mut self) { self.generation += 1; }
+
+    /// To be called once per frame, the time only runs while the simulation is not paused
+    pub fn tick(&mut self, paused: bool) {
+        let now = Instant::now();
+        if let Some(last_tick) = self.last_tick {
+            if !paused {
+                self.elapsed += now - last_tick;
+            }
+        }
+        self.last_tick = Some(now);
+    }
+
+    /// Actions whose trigger is reached, in the order of the file, they are not returned again until a restart
+    pub fn due_actions(&mut self) -> Vec<TimelineAction> {
+        let mut actions = Vec::new();
+        for (event, fired) in self.script.events.iter().zip(self.fired.iter_mut()) {
+            let due = match event.at {
+                Trigger::Generation(generation) => self.generation >= generation,
+                Trigger::Seconds(seconds) => self.elapsed.as_secs_f32() >= seconds,
+            };
+            if due && !*fired {
+                *fired = true;
+                actions.push(event.action.clone());
+            }
+        }
+        actions
+    }
+
+    pub fn ui_control(&mut self, ui: &mut egui::Ui) {
+        ui.label(format!("generation {}, {:.1} s", self.generation, self.elapsed.as_secs_f32()));
+        egui::Grid::new("timeline events").striped(true).show(ui, |ui| {
+            for (event, fired) in self.script.events.iter().zip(self.fired.iter()) {
+                ui.label(if *fired { "✔" } else { "" });
+                ui.label(event.at.to_string());
+                ui.label(event.action.to_string());
+                ui.end_row();
+            }
+        });
+        if self.is_finished() {
+            ui.label("Every event fired, restart to run them again.");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_perturb_state() {
+        let script: TimelineScript = serde_json::from_str(
+            r#"{ "events": [
+                { "at": { "generation": 100 }, "action": "perturb_state", "std_dev": 0.05, "seed": 7 },
+                { "at": { "seconds": 2.5 }, "action": "perturb_state", "std_dev": 0.1 }
+            ] }"#,
+        )
+        .expect("The script parses");
+
+        assert_eq!(
+            script.events,
+            vec![
+                TimelineEvent {
+                    at: Trigger::Generation(100),
+                    action: TimelineAction::PerturbState { std_dev: 0.05, seed: Some(7) },
+                },
+                TimelineEvent {
+                    at: Trigger::Seconds(2.5),
+                    action: TimelineAction::PerturbState { std_dev: 0.1, seed: None },
+                },
+            ]
+        );
+        assert!(serde_json::from_str::<TimelineEvent>(r#"{ "at": { "generation": 1 }, "action": "perturb_state" }"#).is_err());
+    }
+}